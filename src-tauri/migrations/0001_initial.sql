@@ -0,0 +1,35 @@
+-- Recently opened repositories, most recent first by last_opened
+CREATE TABLE recent_repos (
+    path        TEXT PRIMARY KEY NOT NULL,
+    name        TEXT NOT NULL,
+    last_opened TEXT NOT NULL
+);
+
+CREATE INDEX idx_recent_repos_last_opened ON recent_repos (last_opened DESC);
+
+-- Per-repository settings as a JSON document, keyed by work tree path
+CREATE TABLE repo_settings (
+    repo_path  TEXT PRIMARY KEY NOT NULL,
+    settings   TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+-- Completed AI responses, keyed by a hash of provider, model and prompt
+CREATE TABLE ai_cache (
+    cache_key  TEXT PRIMARY KEY NOT NULL,
+    provider   TEXT NOT NULL,
+    model      TEXT NOT NULL,
+    response   TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_ai_cache_created_at ON ai_cache (created_at);
+
+-- Arbitrary cached repository data (branch lists, commit graphs, ...) as JSON
+CREATE TABLE repo_metadata (
+    repo_path  TEXT NOT NULL,
+    key        TEXT NOT NULL,
+    value      TEXT NOT NULL,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (repo_path, key)
+);
@@ -0,0 +1,11 @@
+-- Destructive operations and the state needed to undo them, newest last
+CREATE TABLE operation_journal (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    repo_path   TEXT NOT NULL,
+    description TEXT NOT NULL,
+    action      TEXT NOT NULL,
+    created_at  INTEGER NOT NULL,
+    undone_at   INTEGER
+);
+
+CREATE INDEX idx_operation_journal_repo ON operation_journal (repo_path, id DESC);
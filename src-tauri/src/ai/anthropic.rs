@@ -0,0 +1,260 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::ai::provider::{
+    check_response, AiProvider, ChunkCallback, CompletionChunk, CompletionRequest, ProviderConfig,
+};
+use crate::ai::stream::{for_each_line, sse_data};
+use crate::error::{AppError, AppResult};
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// The Messages API requires `max_tokens`; used when the request leaves it unset
+const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+/// Anthropic Messages API provider
+pub struct AnthropicProvider {
+    config: ProviderConfig,
+    client: Client,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+}
+
+/// A streamed Messages API event; only the fields we consume are modelled
+#[derive(Deserialize)]
+struct StreamEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    delta: Option<StreamDelta>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct StreamDelta {
+    text: Option<String>,
+}
+
+impl AnthropicProvider {
+    pub fn new(config: ProviderConfig, api_key: Option<String>) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+            api_key,
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let builder = self
+            .client
+            .request(method, format!("{}{}", self.config.base_url, path))
+            .header("anthropic-version", ANTHROPIC_VERSION);
+        match &self.api_key {
+            Some(key) => builder.header("x-api-key", key),
+            None => builder,
+        }
+    }
+
+    /// Build the Messages API body; system messages go into the top-level `system` field
+    fn build_body(&self, request: &CompletionRequest, stream: bool) -> serde_json::Value {
+        let system: Vec<&str> = request
+            .messages
+            .iter()
+            .filter(|m| m.role == "system")
+            .map(|m| m.content.as_str())
+            .collect();
+        let messages: Vec<_> = request
+            .messages
+            .iter()
+            .filter(|m| m.role != "system")
+            .collect();
+
+        let mut body = serde_json::json!({
+            "model": self.config.model,
+            "messages": messages,
+            "max_tokens": request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            "stream": stream,
+        });
+        if !system.is_empty() {
+            body["system"] = system.join("\n\n").into();
+        }
+        if let Some(temperature) = request.temperature {
+            body["temperature"] = temperature.into();
+        }
+        body
+    }
+}
+
+#[async_trait]
+impl AiProvider for AnthropicProvider {
+    fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> AppResult<String> {
+        let resp = self
+            .request(reqwest::Method::POST, "/v1/messages")
+            .json(&self.build_body(request, false))
+            .send()
+            .await?;
+        let resp = check_response(resp, &self.config.name).await?;
+        let parsed = resp.json::<MessagesResponse>().await?;
+
+        let text: String = parsed
+            .content
+            .into_iter()
+            .filter(|block| block.kind == "text")
+            .map(|block| block.text)
+            .collect();
+        if text.trim().is_empty() {
+            return Err(AppError::AiProvider(format!(
+                "{} returned no content",
+                self.config.name
+            )));
+        }
+        Ok(text.trim().to_string())
+    }
+
+    async fn complete_stream(
+        &self,
+        request: &CompletionRequest,
+        on_chunk: &ChunkCallback,
+    ) -> AppResult<String> {
+        let resp = self
+            .request(reqwest::Method::POST, "/v1/messages")
+            .json(&self.build_body(request, true))
+            .send()
+            .await?;
+        let resp = check_response(resp, &self.config.name).await?;
+
+        let mut full = String::new();
+        for_each_line(resp, |line| {
+            let data = match sse_data(line) {
+                Some(data) => data,
+                None => return Ok(true),
+            };
+            let event: StreamEvent = serde_json::from_str(data)?;
+            match event.kind.as_str() {
+                "content_block_delta" => {
+                    if let Some(text) = event.delta.and_then(|d| d.text).filter(|t| !t.is_empty()) {
+                        full.push_str(&text);
+                        on_chunk(CompletionChunk {
+                            content: text,
+                            done: false,
+                        });
+                    }
+                    Ok(true)
+                }
+                "message_stop" => Ok(false),
+                "error" => Err(AppError::AiProvider(format!(
+                    "{} stream error: {}",
+                    self.config.name,
+                    event.error.unwrap_or_default()
+                ))),
+                // message_start, content_block_start/stop, message_delta, ping
+                _ => Ok(true),
+            }
+        })
+        .await?;
+
+        on_chunk(CompletionChunk {
+            content: String::new(),
+            done: true,
+        });
+        Ok(full.trim().to_string())
+    }
+
+    async fn validate(&self) -> AppResult<bool> {
+        let resp = self.request(reqwest::Method::GET, "/v1/models").send().await?;
+        Ok(resp.status().is_success())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::provider::ProviderKind;
+    use crate::ai::test_server::{completion_request, TestServer};
+    use crate::error::ErrorCode;
+
+    fn provider(server: &TestServer) -> AnthropicProvider {
+        AnthropicProvider::new(server.config(ProviderKind::Anthropic), Some("sk-ant-test".into()))
+    }
+
+    #[tokio::test]
+    async fn complete_posts_messages() {
+        let server = TestServer::start(
+            200,
+            r#"{"content":[{"type":"text","text":"fix: handle "},{"type":"tool_use"},{"type":"text","text":"empty diffs\n"}]}"#,
+        )
+        .await;
+
+        let text = provider(&server).complete(&completion_request()).await.unwrap();
+        assert_eq!(text, "fix: handle empty diffs");
+
+        let request = &server.requests()[0];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/v1/messages");
+        assert_eq!(request.header("x-api-key"), Some("sk-ant-test"));
+        assert_eq!(request.header("anthropic-version"), Some(ANTHROPIC_VERSION));
+        let body = request.json();
+        assert_eq!(body["model"], "test-model");
+        assert_eq!(body["max_tokens"], 100);
+        assert_eq!(body["system"], "You write commit messages.");
+        // The system message moves out of `messages`
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][0]["role"], "user");
+    }
+
+    #[tokio::test]
+    async fn max_tokens_defaults_when_unset() {
+        let server = TestServer::start(200, r#"{"content":[{"type":"text","text":"ok"}]}"#).await;
+        let mut request = completion_request();
+        request.max_tokens = None;
+        provider(&server).complete(&request).await.unwrap();
+        assert_eq!(server.requests()[0].json()["max_tokens"], DEFAULT_MAX_TOKENS);
+    }
+
+    #[tokio::test]
+    async fn complete_without_text_fails() {
+        let server = TestServer::start(200, r#"{"content":[]}"#).await;
+        let err = provider(&server).complete(&completion_request()).await.unwrap_err();
+        assert!(matches!(err, AppError::AiProvider(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn validate_checks_models() {
+        let server = TestServer::start(200, r#"{"data":[]}"#).await;
+        assert!(provider(&server).validate().await.unwrap());
+        let request = &server.requests()[0];
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/v1/models");
+        assert_eq!(request.header("x-api-key"), Some("sk-ant-test"));
+
+        let server = TestServer::start(401, r#"{"type":"error"}"#).await;
+        assert!(!provider(&server).validate().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn error_statuses_map_to_provider_errors() {
+        for (status, body) in [(401, "invalid x-api-key"), (529, "overloaded_error")] {
+            let server = TestServer::start(status, body).await;
+            let err = provider(&server).complete(&completion_request()).await.unwrap_err();
+            assert_eq!(err.code(), ErrorCode::AiProvider);
+            let message = err.to_string();
+            assert!(message.contains(&status.to_string()), "{message}");
+            assert!(message.contains(body), "{message}");
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::ai::provider::ProviderKind;
+use crate::error::{AppError, AppResult};
+
+/// Keyring account name for a provider's API key
+fn account_name(kind: &ProviderKind) -> &'static str {
+    match kind {
+        ProviderKind::OpenAI => "ai-openai-key",
+        ProviderKind::Anthropic => "ai-anthropic-key",
+        ProviderKind::Ollama => "ai-ollama-key",
+        ProviderKind::OpenAICompatible => "ai-openai-compatible-key",
+    }
+}
+
+fn entry(kind: &ProviderKind) -> AppResult<keyring::Entry> {
+    keyring::Entry::new("gitsage", account_name(kind))
+        .map_err(|e| AppError::General(format!("Keyring error: {}", e)))
+}
+
+pub fn store_api_key(kind: &ProviderKind, api_key: &str) -> AppResult<()> {
+    entry(kind)?
+        .set_password(api_key)
+        .map_err(|e| AppError::General(format!("Failed to store API key: {}", e)))?;
+    Ok(())
+}
+
+pub fn load_api_key(kind: &ProviderKind) -> AppResult<Option<String>> {
+    match entry(kind)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(AppError::General(format!("Failed to load API key: {}", e))),
+    }
+}
+
+pub fn delete_api_key(kind: &ProviderKind) -> AppResult<()> {
+    match entry(kind)?.delete_credential() {
+        Ok(_) => Ok(()),
+        Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(AppError::General(format!("Failed to delete API key: {}", e))),
+    }
+}
@@ -0,0 +1,443 @@
+//! Token-budgeted diff condensation for AI prompts
+//!
+//! Real commits often contain lockfiles, generated code or vendored
+//! directories that would blow past a model's context window while adding
+//! nothing to a commit message. This module ranks the files of a `DiffOutput`,
+//! drops the noise, and fits the rest into a token budget, reporting
+//! everything it left out.
+
+use serde::Serialize;
+
+use crate::git::diff::{DiffFile, DiffOutput};
+
+/// Tokens kept aside for the omitted-files footer
+const FOOTER_RESERVE: usize = 128;
+
+/// Why a file (or part of it) was left out of the prompt
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub enum OmitReason {
+    Lockfile,
+    Binary,
+    Generated,
+    Vendored,
+    OverBudget,
+}
+
+/// A file that was dropped or truncated
+#[derive(Debug, Clone, Serialize)]
+pub struct OmittedFile {
+    pub path: String,
+    pub reason: OmitReason,
+    pub insertions: usize,
+    pub deletions: usize,
+    /// Hunks left out; equals the file's hunk count when it was dropped entirely
+    pub hunks_omitted: usize,
+}
+
+/// Diff text ready to be pasted into a prompt
+#[derive(Debug, Clone, Serialize)]
+pub struct CondensedDiff {
+    pub text: String,
+    pub estimated_tokens: usize,
+    pub budget: usize,
+    /// True when no hunk fit and only stats plus a file list were included
+    pub stats_only: bool,
+    pub omitted: Vec<OmittedFile>,
+}
+
+/// Rough token estimate (~4 characters per token for code and English)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Classify files whose content is noise for an AI reader
+fn noise_reason(file: &DiffFile) -> Option<OmitReason> {
+    if file.is_binary {
+        return Some(OmitReason::Binary);
+    }
+
+    let path = file.display_path();
+    let name = path.rsplit('/').next().unwrap_or(path);
+
+    const LOCKFILES: &[&str] = &[
+        "Cargo.lock",
+        "package-lock.json",
+        "npm-shrinkwrap.json",
+        "yarn.lock",
+        "pnpm-lock.yaml",
+        "bun.lockb",
+        "Gemfile.lock",
+        "poetry.lock",
+        "Pipfile.lock",
+        "composer.lock",
+        "go.sum",
+        "flake.lock",
+        "pubspec.lock",
+        "Podfile.lock",
+        "mix.lock",
+    ];
+    if LOCKFILES.contains(&name) || name.ends_with(".lock") {
+        return Some(OmitReason::Lockfile);
+    }
+
+    const VENDORED_DIRS: &[&str] = &["vendor/", "node_modules/", "third_party/", "third-party/"];
+    if VENDORED_DIRS
+        .iter()
+        .any(|dir| path.starts_with(dir) || path.contains(&format!("/{}", dir)))
+    {
+        return Some(OmitReason::Vendored);
+    }
+
+    const GENERATED_DIRS: &[&str] = &["dist/", "build/", "out/", "target/", "generated/"];
+    const GENERATED_SUFFIXES: &[&str] = &[
+        ".min.js",
+        ".min.css",
+        ".map",
+        ".pb.go",
+        ".pb.rs",
+        "_pb2.py",
+        ".g.dart",
+        ".freezed.dart",
+        ".designer.cs",
+        ".snap",
+    ];
+    if GENERATED_DIRS
+        .iter()
+        .any(|dir| path.starts_with(dir) || path.contains(&format!("/{}", dir)))
+        || GENERATED_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+        || name.contains(".generated.")
+        || name.contains("_generated.")
+    {
+        return Some(OmitReason::Generated);
+    }
+
+    None
+}
+
+/// Lower rank is more useful to the model: source before tests before docs/config
+fn rank(file: &DiffFile) -> (u8, usize) {
+    let path = file.display_path().to_lowercase();
+    let name = path.rsplit('/').next().unwrap_or(&path);
+    let category = if path.contains("test") || path.contains("spec") {
+        1
+    } else if name.ends_with(".md")
+        || name.ends_with(".txt")
+        || name.ends_with(".json")
+        || name.ends_with(".yaml")
+        || name.ends_with(".yml")
+        || name.ends_with(".toml")
+    {
+        2
+    } else {
+        0
+    };
+    let (ins, del) = file.line_counts();
+    // Smaller changes first so that as many files as possible make it in
+    (category, ins + del)
+}
+
+fn omitted(file: &DiffFile, reason: OmitReason, hunks_omitted: usize) -> OmittedFile {
+    let (insertions, deletions) = file.line_counts();
+    OmittedFile {
+        path: file.display_path().to_string(),
+        reason,
+        insertions,
+        deletions,
+        hunks_omitted,
+    }
+}
+
+/// Stats plus a per-file list, used when not even one hunk fits the budget
+fn stats_summary(diff: &DiffOutput, budget: usize) -> String {
+    let mut text = format!(
+        "{} files changed, {} insertions(+), {} deletions(-)\n",
+        diff.stats.files_changed, diff.stats.insertions, diff.stats.deletions
+    );
+    for (idx, file) in diff.files.iter().enumerate() {
+        let (ins, del) = file.line_counts();
+        let line = format!("{} | +{} -{}\n", file.display_path(), ins, del);
+        if estimate_tokens(&text) + estimate_tokens(&line) > budget {
+            text.push_str(&format!("... and {} more files\n", diff.files.len() - idx));
+            break;
+        }
+        text.push_str(&line);
+    }
+    text
+}
+
+fn footer(omitted: &[OmittedFile]) -> String {
+    if omitted.is_empty() {
+        return String::new();
+    }
+    let mut text = String::from("\n[Omitted from this diff]\n");
+    for file in omitted {
+        let reason = match file.reason {
+            OmitReason::Lockfile => "lockfile",
+            OmitReason::Binary => "binary",
+            OmitReason::Generated => "generated",
+            OmitReason::Vendored => "vendored",
+            OmitReason::OverBudget => "too large",
+        };
+        text.push_str(&format!(
+            "- {} (+{} -{}, {}",
+            file.path, file.insertions, file.deletions, reason
+        ));
+        if file.hunks_omitted > 0 {
+            text.push_str(&format!(", {} hunks left out", file.hunks_omitted));
+        }
+        text.push_str(")\n");
+    }
+    text
+}
+
+/// Condense a diff so that it fits into `budget` tokens.
+pub fn condense_diff(diff: &DiffOutput, budget: usize) -> CondensedDiff {
+    let mut omitted_files = Vec::new();
+    let mut candidates: Vec<&DiffFile> = Vec::new();
+
+    for file in &diff.files {
+        match noise_reason(file) {
+            Some(reason) => omitted_files.push(omitted(file, reason, file.hunks.len())),
+            None => candidates.push(file),
+        }
+    }
+    candidates.sort_by_key(|file| rank(file));
+
+    let body_budget = budget.saturating_sub(FOOTER_RESERVE);
+    let mut text = String::new();
+    let mut used = 0;
+    let mut included_any = false;
+
+    for file in candidates {
+        let whole = file.to_unified();
+        let whole_tokens = estimate_tokens(&whole);
+        if used + whole_tokens <= body_budget {
+            text.push_str(&whole);
+            used += whole_tokens;
+            included_any = true;
+            continue;
+        }
+
+        // Doesn't fit whole: keep whichever hunks still fit, in file order
+        let header = file.header_text();
+        let mut partial = header.clone();
+        let mut partial_tokens = estimate_tokens(&header);
+        let mut hunks_omitted = 0;
+        for hunk in &file.hunks {
+            let hunk_text = hunk.to_unified();
+            let hunk_tokens = estimate_tokens(&hunk_text);
+            if used + partial_tokens + hunk_tokens <= body_budget {
+                partial.push_str(&hunk_text);
+                partial_tokens += hunk_tokens;
+            } else {
+                hunks_omitted += 1;
+            }
+        }
+
+        if hunks_omitted < file.hunks.len() {
+            text.push_str(&partial);
+            used += partial_tokens;
+            included_any = true;
+        } else {
+            hunks_omitted = file.hunks.len();
+        }
+        omitted_files.push(omitted(file, OmitReason::OverBudget, hunks_omitted));
+    }
+
+    let stats_only = !included_any && !diff.files.is_empty();
+    if stats_only {
+        text = stats_summary(diff, body_budget);
+    }
+    text.push_str(&footer(&omitted_files));
+
+    CondensedDiff {
+        estimated_tokens: estimate_tokens(&text),
+        text,
+        budget,
+        stats_only,
+        omitted: omitted_files,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::diff::{DiffFileStatus, DiffHunk, DiffLine, DiffLineType, DiffStats};
+
+    /// A hunk adding `count` lines of about 40 characters
+    fn hunk(start: u32, count: u32) -> DiffHunk {
+        let lines: Vec<DiffLine> = (0..count)
+            .map(|i| DiffLine {
+                origin: DiffLineType::Addition,
+                content: format!("let value_{:04} = compute_something_here();\n", i),
+                old_lineno: None,
+                new_lineno: Some(start + i),
+                changes: Vec::new(),
+            })
+            .collect();
+        DiffHunk {
+            old_start: start,
+            old_lines: 0,
+            new_start: start,
+            new_lines: count,
+            header: format!("@@ -{},0 +{},{} @@\n", start, start, count),
+            fingerprint: DiffHunk::fingerprint_of(&lines),
+            lines,
+        }
+    }
+
+    fn file(path: &str, hunk_sizes: &[u32]) -> DiffFile {
+        DiffFile {
+            old_path: Some(path.to_string()),
+            new_path: Some(path.to_string()),
+            status: DiffFileStatus::Modified,
+            hunks: hunk_sizes
+                .iter()
+                .enumerate()
+                .map(|(i, &count)| hunk(1 + i as u32 * 1000, count))
+                .collect(),
+            is_binary: false,
+            old_mode: 0o100644,
+            new_mode: 0o100644,
+        }
+    }
+
+    fn diff(files: Vec<DiffFile>) -> DiffOutput {
+        let insertions = files.iter().map(|f| f.line_counts().0).sum();
+        DiffOutput {
+            stats: DiffStats {
+                files_changed: files.len(),
+                insertions,
+                deletions: 0,
+            },
+            files,
+        }
+    }
+
+    fn omitted_entry<'a>(condensed: &'a CondensedDiff, path: &str) -> Option<&'a OmittedFile> {
+        condensed.omitted.iter().find(|file| file.path == path)
+    }
+
+    #[test]
+    fn small_diff_is_kept_whole() {
+        let input = diff(vec![file("src/main.rs", &[5]), file("src/lib.rs", &[3])]);
+        let condensed = condense_diff(&input, 4000);
+
+        assert!(condensed.omitted.is_empty());
+        assert!(!condensed.stats_only);
+        assert!(condensed.estimated_tokens <= condensed.budget);
+        assert!(condensed.text.contains("diff --git a/src/main.rs b/src/main.rs"));
+        assert!(condensed.text.contains("diff --git a/src/lib.rs b/src/lib.rs"));
+        assert!(!condensed.text.contains("[Omitted from this diff]"));
+    }
+
+    #[test]
+    fn noise_files_are_omitted_even_when_they_fit() {
+        let mut image = file("assets/logo.png", &[]);
+        image.is_binary = true;
+        let input = diff(vec![
+            file("Cargo.lock", &[10]),
+            file("web/yarn.lock", &[10]),
+            file("dist/app.min.js", &[10]),
+            file("src/schema.generated.ts", &[10]),
+            file("vendor/github.com/x/y.go", &[10]),
+            image,
+            file("src/main.rs", &[10]),
+        ]);
+        let condensed = condense_diff(&input, 100_000);
+
+        let expected = [
+            ("Cargo.lock", OmitReason::Lockfile),
+            ("web/yarn.lock", OmitReason::Lockfile),
+            ("dist/app.min.js", OmitReason::Generated),
+            ("src/schema.generated.ts", OmitReason::Generated),
+            ("vendor/github.com/x/y.go", OmitReason::Vendored),
+            ("assets/logo.png", OmitReason::Binary),
+        ];
+        assert_eq!(condensed.omitted.len(), expected.len());
+        for (path, reason) in expected {
+            let file = omitted_entry(&condensed, path).unwrap_or_else(|| panic!("{path} omitted"));
+            assert_eq!(file.reason, reason, "{path}");
+            assert!(!condensed.text.contains(&format!("diff --git a/{path}")), "{path}");
+            assert!(condensed.text.contains(&format!("- {path} (")), "{path} in footer");
+        }
+        assert_eq!(omitted_entry(&condensed, "Cargo.lock").unwrap().insertions, 10);
+        assert_eq!(omitted_entry(&condensed, "Cargo.lock").unwrap().hunks_omitted, 1);
+        assert!(condensed.text.contains("diff --git a/src/main.rs"));
+    }
+
+    #[test]
+    fn large_file_keeps_the_hunks_that_fit() {
+        // Each 20-line hunk is about 230 tokens
+        let input = diff(vec![
+            file("src/big.rs", &[20, 20, 20, 20, 20, 20]),
+            file("src/small.rs", &[2]),
+        ]);
+        let budget = 1000;
+        let condensed = condense_diff(&input, budget);
+
+        assert!(!condensed.stats_only);
+        assert!(condensed.estimated_tokens <= budget, "{}", condensed.estimated_tokens);
+        // The smaller file ranks first and is kept whole
+        assert!(omitted_entry(&condensed, "src/small.rs").is_none());
+        assert!(condensed.text.contains("diff --git a/src/small.rs"));
+
+        let big = omitted_entry(&condensed, "src/big.rs").unwrap();
+        assert_eq!(big.reason, OmitReason::OverBudget);
+        assert!(big.hunks_omitted > 0 && big.hunks_omitted < 6, "{}", big.hunks_omitted);
+        assert!(condensed.text.contains("diff --git a/src/big.rs"));
+        assert!(condensed.text.contains("@@ -1,0 +1,20 @@"));
+        assert!(condensed.text.contains("hunks left out"));
+    }
+
+    #[test]
+    fn files_that_do_not_fit_at_all_are_dropped() {
+        let input = diff(vec![file("src/a.rs", &[10]), file("src/b.rs", &[100])]);
+        let condensed = condense_diff(&input, 600);
+
+        assert!(condensed.estimated_tokens <= 600);
+        assert!(omitted_entry(&condensed, "src/a.rs").is_none());
+        let b = omitted_entry(&condensed, "src/b.rs").unwrap();
+        assert_eq!(b.reason, OmitReason::OverBudget);
+        assert_eq!(b.hunks_omitted, 1);
+        assert!(!condensed.text.contains("diff --git a/src/b.rs"));
+    }
+
+    #[test]
+    fn sources_come_before_tests_and_docs() {
+        let input = diff(vec![
+            file("README.md", &[1]),
+            file("tests/parse.rs", &[1]),
+            file("src/parse.rs", &[3]),
+        ]);
+        let text = condense_diff(&input, 4000).text;
+
+        let position = |path: &str| text.find(&format!("diff --git a/{path}")).unwrap();
+        assert!(position("src/parse.rs") < position("tests/parse.rs"));
+        assert!(position("tests/parse.rs") < position("README.md"));
+    }
+
+    #[test]
+    fn falls_back_to_stats_when_no_hunk_fits() {
+        let input = diff(vec![file("src/a.rs", &[40]), file("src/b.rs", &[40])]);
+        let condensed = condense_diff(&input, 200);
+
+        assert!(condensed.stats_only);
+        assert!(condensed.text.starts_with("2 files changed, 80 insertions(+), 0 deletions(-)"));
+        assert!(condensed.text.contains("src/a.rs | +40 -0"));
+        assert!(!condensed.text.contains("@@"));
+        for path in ["src/a.rs", "src/b.rs"] {
+            let file = omitted_entry(&condensed, path).unwrap();
+            assert_eq!(file.reason, OmitReason::OverBudget);
+            assert_eq!(file.hunks_omitted, 1);
+        }
+    }
+
+    #[test]
+    fn empty_diff_is_not_stats_only() {
+        let condensed = condense_diff(&DiffOutput::empty(), 1000);
+        assert!(!condensed.stats_only);
+        assert!(condensed.text.is_empty());
+        assert!(condensed.omitted.is_empty());
+    }
+}
@@ -1,2 +1,11 @@
+pub mod anthropic;
+pub mod auth;
+pub mod condense;
+pub mod ollama;
+pub mod openai;
 pub mod prompts;
 pub mod provider;
+pub mod redact;
+pub mod stream;
+#[cfg(test)]
+mod test_server;
@@ -0,0 +1,210 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::ai::provider::{
+    check_response, AiProvider, ChunkCallback, CompletionChunk, CompletionRequest, ProviderConfig,
+};
+use crate::ai::stream::for_each_line;
+use crate::error::{AppError, AppResult};
+
+/// Ollama local model provider (`/api/chat`)
+pub struct OllamaProvider {
+    config: ProviderConfig,
+    client: Client,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: Option<ChatResponseMessage>,
+    #[serde(default)]
+    done: bool,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatResponseMessage {
+    content: String,
+}
+
+impl OllamaProvider {
+    pub fn new(config: ProviderConfig, api_key: Option<String>) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+            api_key,
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let builder = self
+            .client
+            .request(method, format!("{}{}", self.config.base_url, path));
+        // Ollama has no auth of its own, but is often deployed behind a proxy that does
+        match &self.api_key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+
+    fn build_body(&self, request: &CompletionRequest, stream: bool) -> serde_json::Value {
+        let mut options = serde_json::Map::new();
+        if let Some(max_tokens) = request.max_tokens {
+            options.insert("num_predict".into(), max_tokens.into());
+        }
+        if let Some(temperature) = request.temperature {
+            options.insert("temperature".into(), temperature.into());
+        }
+        serde_json::json!({
+            "model": self.config.model,
+            "messages": request.messages,
+            "stream": stream,
+            "options": options,
+        })
+    }
+}
+
+#[async_trait]
+impl AiProvider for OllamaProvider {
+    fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> AppResult<String> {
+        let resp = self
+            .request(reqwest::Method::POST, "/api/chat")
+            .json(&self.build_body(request, false))
+            .send()
+            .await?;
+        let resp = check_response(resp, &self.config.name).await?;
+        let parsed = resp.json::<ChatResponse>().await?;
+
+        parsed
+            .message
+            .map(|m| m.content.trim().to_string())
+            .filter(|content| !content.is_empty())
+            .ok_or_else(|| AppError::AiProvider(format!("{} returned no content", self.config.name)))
+    }
+
+    async fn complete_stream(
+        &self,
+        request: &CompletionRequest,
+        on_chunk: &ChunkCallback,
+    ) -> AppResult<String> {
+        let resp = self
+            .request(reqwest::Method::POST, "/api/chat")
+            .json(&self.build_body(request, true))
+            .send()
+            .await?;
+        let resp = check_response(resp, &self.config.name).await?;
+
+        // Ollama streams newline-delimited JSON objects rather than SSE
+        let mut full = String::new();
+        for_each_line(resp, |line| {
+            if line.trim().is_empty() {
+                return Ok(true);
+            }
+            let event: ChatResponse = serde_json::from_str(line)?;
+            if let Some(error) = event.error {
+                return Err(AppError::AiProvider(format!(
+                    "{} stream error: {}",
+                    self.config.name, error
+                )));
+            }
+            if let Some(content) = event.message.map(|m| m.content).filter(|c| !c.is_empty()) {
+                full.push_str(&content);
+                on_chunk(CompletionChunk {
+                    content,
+                    done: false,
+                });
+            }
+            Ok(!event.done)
+        })
+        .await?;
+
+        on_chunk(CompletionChunk {
+            content: String::new(),
+            done: true,
+        });
+        Ok(full.trim().to_string())
+    }
+
+    async fn validate(&self) -> AppResult<bool> {
+        let resp = self.request(reqwest::Method::GET, "/api/tags").send().await?;
+        Ok(resp.status().is_success())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::provider::ProviderKind;
+    use crate::ai::test_server::{completion_request, TestServer};
+    use crate::error::ErrorCode;
+
+    fn provider(server: &TestServer, api_key: Option<&str>) -> OllamaProvider {
+        OllamaProvider::new(server.config(ProviderKind::Ollama), api_key.map(String::from))
+    }
+
+    #[tokio::test]
+    async fn complete_posts_chat() {
+        let server = TestServer::start(
+            200,
+            r#"{"message":{"role":"assistant","content":"\nchore: bump deps "},"done":true}"#,
+        )
+        .await;
+
+        let text = provider(&server, None).complete(&completion_request()).await.unwrap();
+        assert_eq!(text, "chore: bump deps");
+
+        let request = &server.requests()[0];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/api/chat");
+        assert_eq!(request.header("authorization"), None);
+        let body = request.json();
+        assert_eq!(body["model"], "test-model");
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["options"]["num_predict"], 100);
+        assert_eq!(body["messages"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn api_key_is_sent_for_proxies() {
+        let server = TestServer::start(200, r#"{"models":[]}"#).await;
+        assert!(provider(&server, Some("proxy-key")).validate().await.unwrap());
+        let request = &server.requests()[0];
+        assert_eq!(request.header("authorization"), Some("Bearer proxy-key"));
+    }
+
+    #[tokio::test]
+    async fn empty_content_fails() {
+        let server = TestServer::start(200, r#"{"message":{"content":"  "},"done":true}"#).await;
+        let err = provider(&server, None).complete(&completion_request()).await.unwrap_err();
+        assert!(matches!(err, AppError::AiProvider(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn validate_checks_tags() {
+        let server = TestServer::start(200, r#"{"models":[]}"#).await;
+        assert!(provider(&server, None).validate().await.unwrap());
+        let request = &server.requests()[0];
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/api/tags");
+
+        let server = TestServer::start(401, "unauthorized").await;
+        assert!(!provider(&server, None).validate().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn error_statuses_map_to_provider_errors() {
+        for (status, body) in [(401, "unauthorized"), (500, r#"{"error":"model not found"}"#)] {
+            let server = TestServer::start(status, body).await;
+            let err = provider(&server, None).complete(&completion_request()).await.unwrap_err();
+            assert_eq!(err.code(), ErrorCode::AiProvider);
+            let message = err.to_string();
+            assert!(message.contains(&status.to_string()), "{message}");
+            assert!(message.contains(body), "{message}");
+        }
+    }
+}
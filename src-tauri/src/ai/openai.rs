@@ -0,0 +1,230 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::ai::provider::{
+    check_response, AiProvider, ChunkCallback, CompletionChunk, CompletionRequest, ProviderConfig,
+};
+use crate::ai::stream::{for_each_line, sse_data};
+use crate::error::{AppError, AppResult};
+
+/// OpenAI Chat Completions provider.
+/// Also used for OpenAI-compatible servers (vLLM, LM Studio, DeepSeek, ...).
+pub struct OpenAiProvider {
+    config: ProviderConfig,
+    client: Client,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatChoiceMessage {
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StreamResponse {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct StreamChoice {
+    delta: StreamDelta,
+}
+
+#[derive(Deserialize)]
+struct StreamDelta {
+    content: Option<String>,
+}
+
+impl OpenAiProvider {
+    pub fn new(config: ProviderConfig, api_key: Option<String>) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+            api_key,
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let builder = self
+            .client
+            .request(method, format!("{}{}", self.config.base_url, path));
+        match &self.api_key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+
+    fn build_body(&self, request: &CompletionRequest, stream: bool) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "model": self.config.model,
+            "messages": request.messages,
+            "stream": stream,
+        });
+        if let Some(max_tokens) = request.max_tokens {
+            body["max_tokens"] = max_tokens.into();
+        }
+        if let Some(temperature) = request.temperature {
+            body["temperature"] = temperature.into();
+        }
+        body
+    }
+}
+
+#[async_trait]
+impl AiProvider for OpenAiProvider {
+    fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> AppResult<String> {
+        let resp = self
+            .request(reqwest::Method::POST, "/chat/completions")
+            .json(&self.build_body(request, false))
+            .send()
+            .await?;
+        let resp = check_response(resp, &self.config.name).await?;
+        let parsed = resp.json::<ChatResponse>().await?;
+
+        parsed
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|c| c.message.content)
+            .map(|content| content.trim().to_string())
+            .ok_or_else(|| AppError::AiProvider(format!("{} returned no content", self.config.name)))
+    }
+
+    async fn complete_stream(
+        &self,
+        request: &CompletionRequest,
+        on_chunk: &ChunkCallback,
+    ) -> AppResult<String> {
+        let resp = self
+            .request(reqwest::Method::POST, "/chat/completions")
+            .json(&self.build_body(request, true))
+            .send()
+            .await?;
+        let resp = check_response(resp, &self.config.name).await?;
+
+        let mut full = String::new();
+        for_each_line(resp, |line| {
+            let data = match sse_data(line) {
+                Some(data) => data,
+                None => return Ok(true),
+            };
+            if data == "[DONE]" {
+                return Ok(false);
+            }
+            let event: StreamResponse = serde_json::from_str(data)?;
+            if let Some(error) = event.error {
+                return Err(AppError::AiProvider(format!(
+                    "{} stream error: {}",
+                    self.config.name, error
+                )));
+            }
+            for choice in event.choices {
+                if let Some(content) = choice.delta.content.filter(|c| !c.is_empty()) {
+                    full.push_str(&content);
+                    on_chunk(CompletionChunk {
+                        content,
+                        done: false,
+                    });
+                }
+            }
+            Ok(true)
+        })
+        .await?;
+
+        on_chunk(CompletionChunk {
+            content: String::new(),
+            done: true,
+        });
+        Ok(full.trim().to_string())
+    }
+
+    async fn validate(&self) -> AppResult<bool> {
+        let resp = self.request(reqwest::Method::GET, "/models").send().await?;
+        Ok(resp.status().is_success())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::provider::ProviderKind;
+    use crate::ai::test_server::{completion_request, TestServer};
+    use crate::error::ErrorCode;
+
+    fn provider(server: &TestServer) -> OpenAiProvider {
+        OpenAiProvider::new(server.config(ProviderKind::OpenAI), Some("sk-test".into()))
+    }
+
+    #[tokio::test]
+    async fn complete_posts_chat_completion() {
+        let server = TestServer::start(
+            200,
+            r#"{"choices":[{"message":{"content":"  feat: add parser\n"}}]}"#,
+        )
+        .await;
+
+        let text = provider(&server).complete(&completion_request()).await.unwrap();
+        assert_eq!(text, "feat: add parser");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/chat/completions");
+        assert_eq!(request.header("authorization"), Some("Bearer sk-test"));
+        let body = request.json();
+        assert_eq!(body["model"], "test-model");
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["max_tokens"], 100);
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["content"], "diff --git a/f b/f");
+    }
+
+    #[tokio::test]
+    async fn complete_without_choices_fails() {
+        let server = TestServer::start(200, r#"{"choices":[]}"#).await;
+        let err = provider(&server).complete(&completion_request()).await.unwrap_err();
+        assert!(matches!(err, AppError::AiProvider(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn validate_checks_models() {
+        let server = TestServer::start(200, r#"{"data":[]}"#).await;
+        assert!(provider(&server).validate().await.unwrap());
+        let request = &server.requests()[0];
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/models");
+
+        let server = TestServer::start(401, r#"{"error":{"message":"bad key"}}"#).await;
+        assert!(!provider(&server).validate().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn error_statuses_map_to_provider_errors() {
+        for (status, body) in [(401, "invalid api key"), (503, "overloaded")] {
+            let server = TestServer::start(status, body).await;
+            let err = provider(&server).complete(&completion_request()).await.unwrap_err();
+            assert_eq!(err.code(), ErrorCode::AiProvider);
+            let message = err.to_string();
+            assert!(message.contains(&status.to_string()), "{message}");
+            assert!(message.contains(body), "{message}");
+        }
+    }
+}
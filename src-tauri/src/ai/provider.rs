@@ -1,7 +1,12 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
-use crate::error::AppResult;
+use crate::ai::anthropic::AnthropicProvider;
+use crate::ai::auth;
+use crate::ai::ollama::OllamaProvider;
+use crate::ai::openai::OpenAiProvider;
+use crate::config::AiConfig;
+use crate::error::{AppError, AppResult};
 
 /// AI provider types
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -12,6 +17,50 @@ pub enum ProviderKind {
     OpenAICompatible,
 }
 
+impl ProviderKind {
+    pub const ALL: [ProviderKind; 4] = [
+        ProviderKind::OpenAI,
+        ProviderKind::Anthropic,
+        ProviderKind::Ollama,
+        ProviderKind::OpenAICompatible,
+    ];
+
+    /// Human-readable provider name
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ProviderKind::OpenAI => "OpenAI",
+            ProviderKind::Anthropic => "Anthropic",
+            ProviderKind::Ollama => "Ollama",
+            ProviderKind::OpenAICompatible => "OpenAI Compatible",
+        }
+    }
+
+    /// Default API endpoint, `None` when the user must supply one
+    pub fn default_base_url(&self) -> Option<&'static str> {
+        match self {
+            ProviderKind::OpenAI => Some("https://api.openai.com/v1"),
+            ProviderKind::Anthropic => Some("https://api.anthropic.com"),
+            ProviderKind::Ollama => Some("http://localhost:11434"),
+            ProviderKind::OpenAICompatible => None,
+        }
+    }
+
+    /// Default model suggested for a provider
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            ProviderKind::OpenAI => "gpt-4o",
+            ProviderKind::Anthropic => "claude-3-5-sonnet-latest",
+            ProviderKind::Ollama => "llama3.1",
+            ProviderKind::OpenAICompatible => "",
+        }
+    }
+
+    /// Whether requests are rejected without an API key
+    pub fn requires_api_key(&self) -> bool {
+        matches!(self, ProviderKind::OpenAI | ProviderKind::Anthropic)
+    }
+}
+
 /// AI provider configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderConfig {
@@ -30,6 +79,22 @@ pub struct ChatMessage {
     pub content: String,
 }
 
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: "system".to_string(),
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: content.into(),
+        }
+    }
+}
+
 /// AI completion request
 #[derive(Debug, Clone)]
 pub struct CompletionRequest {
@@ -46,6 +111,9 @@ pub struct CompletionChunk {
     pub done: bool,
 }
 
+/// Receiver for streamed completion chunks
+pub type ChunkCallback = dyn Fn(CompletionChunk) + Send + Sync;
+
 /// Trait for AI providers
 #[async_trait]
 pub trait AiProvider: Send + Sync {
@@ -55,6 +123,72 @@ pub trait AiProvider: Send + Sync {
     /// Send a completion request (non-streaming)
     async fn complete(&self, request: &CompletionRequest) -> AppResult<String>;
 
+    /// Send a streaming completion request.
+    /// Each text delta is passed to `on_chunk` as it arrives, followed by a final
+    /// chunk with `done: true`. Returns the full concatenated response.
+    async fn complete_stream(
+        &self,
+        request: &CompletionRequest,
+        on_chunk: &ChunkCallback,
+    ) -> AppResult<String>;
+
     /// Validate connection / API key
     async fn validate(&self) -> AppResult<bool>;
 }
+
+/// Build the provider selected in `AiConfig`, loading its API key from the keyring
+pub fn create_provider(config: &AiConfig) -> AppResult<Box<dyn AiProvider>> {
+    let kind = config.provider.clone();
+    let base_url = config
+        .base_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .or(kind.default_base_url())
+        .ok_or_else(|| {
+            AppError::Config(format!("{} requires a base URL", kind.display_name()))
+        })?
+        .trim_end_matches('/')
+        .to_string();
+
+    let api_key = auth::load_api_key(&kind)?;
+    if api_key.is_none() && kind.requires_api_key() {
+        return Err(AppError::Config(format!(
+            "API key for {} is not set",
+            kind.display_name()
+        )));
+    }
+
+    let provider_config = ProviderConfig {
+        name: kind.display_name().to_string(),
+        kind: kind.clone(),
+        base_url,
+        model: config.model.clone(),
+        has_api_key: api_key.is_some(),
+    };
+
+    let provider: Box<dyn AiProvider> = match kind {
+        ProviderKind::OpenAI | ProviderKind::OpenAICompatible => {
+            Box::new(OpenAiProvider::new(provider_config, api_key))
+        }
+        ProviderKind::Anthropic => Box::new(AnthropicProvider::new(provider_config, api_key)),
+        ProviderKind::Ollama => Box::new(OllamaProvider::new(provider_config, api_key)),
+    };
+    Ok(provider)
+}
+
+/// Turn a non-2xx response into an `AppError::AiProvider` carrying the response body
+pub(crate) async fn check_response(
+    resp: reqwest::Response,
+    provider: &str,
+) -> AppResult<reqwest::Response> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let text = resp.text().await.unwrap_or_default();
+    Err(AppError::AiProvider(format!(
+        "{} request failed ({}): {}",
+        provider, status, text
+    )))
+}
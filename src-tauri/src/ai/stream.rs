@@ -0,0 +1,40 @@
+//! Helpers for reading streamed completion responses (SSE and NDJSON)
+
+use tokio_stream::StreamExt;
+
+use crate::error::AppResult;
+
+/// Read a streaming response body line by line.
+///
+/// Bytes are buffered until a full line is available, so multi-byte UTF-8
+/// characters split across network chunks are decoded correctly. The
+/// callback returns `Ok(false)` to stop reading early.
+pub async fn for_each_line<F>(resp: reqwest::Response, mut on_line: F) -> AppResult<()>
+where
+    F: FnMut(&str) -> AppResult<bool>,
+{
+    let mut body = resp.bytes_stream();
+    let mut buf: Vec<u8> = Vec::new();
+
+    while let Some(bytes) = body.next().await {
+        buf.extend_from_slice(&bytes?);
+        while let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+            let raw: Vec<u8> = buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&raw);
+            if !on_line(line.trim_end_matches(['\r', '\n']))? {
+                return Ok(());
+            }
+        }
+    }
+
+    if !buf.is_empty() {
+        let line = String::from_utf8_lossy(&buf);
+        on_line(line.trim_end_matches(['\r', '\n']))?;
+    }
+    Ok(())
+}
+
+/// Extract the payload of an SSE `data:` line; other fields and comments yield `None`
+pub fn sse_data(line: &str) -> Option<&str> {
+    line.strip_prefix("data:").map(str::trim_start)
+}
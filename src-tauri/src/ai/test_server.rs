@@ -0,0 +1,136 @@
+//! Local HTTP stand-in for provider APIs in tests
+
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::ai::provider::{ChatMessage, CompletionRequest, ProviderConfig, ProviderKind};
+
+/// A request the server received
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    /// Names are lowercased
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).expect("request body is JSON")
+    }
+}
+
+/// Answers every request on 127.0.0.1 with the same status and body
+pub struct TestServer {
+    pub base_url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl TestServer {
+    pub async fn start(status: u16, body: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let response = format!(
+            "HTTP/1.1 {} Test\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\
+             connection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let request = read_request(&mut socket).await;
+                recorded.lock().unwrap().push(request);
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+        Self { base_url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Provider config pointing at this server
+    pub fn config(&self, kind: ProviderKind) -> ProviderConfig {
+        ProviderConfig {
+            name: kind.display_name().to_string(),
+            kind,
+            base_url: self.base_url.clone(),
+            model: "test-model".to_string(),
+            has_api_key: true,
+        }
+    }
+}
+
+/// A request with a system and a user message
+pub fn completion_request() -> CompletionRequest {
+    CompletionRequest {
+        messages: vec![
+            ChatMessage::system("You write commit messages."),
+            ChatMessage::user("diff --git a/f b/f"),
+        ],
+        max_tokens: Some(100),
+        temperature: Some(0.2),
+        stream: false,
+    }
+}
+
+async fn read_request(socket: &mut tokio::net::TcpStream) -> RecordedRequest {
+    let mut data = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = socket.read(&mut chunk).await.unwrap();
+        data.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if n == 0 {
+            break data.len();
+        }
+    };
+
+    let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or("").split(' ');
+    let method = request_line.next().unwrap_or("").to_string();
+    let path = request_line.next().unwrap_or("").to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .iter()
+        .find(|(name, _)| name == "content-length")
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    while data.len() < header_end + length {
+        let n = socket.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&chunk[..n]);
+    }
+
+    RecordedRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&data[header_end..]).to_string(),
+    }
+}
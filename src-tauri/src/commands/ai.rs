@@ -1,29 +1,319 @@
-use tauri::State;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
 
+use crate::ai::auth;
+use crate::ai::condense::{condense_diff, CondensedDiff};
+use crate::ai::prompts;
+use crate::ai::redact::{RedactionConfig, RedactionReport, Redactor};
+use crate::ai::provider::{
+    create_provider, AiProvider, ChatMessage, CompletionChunk, CompletionRequest, ProviderConfig,
+    ProviderKind,
+};
+use crate::config::repo::save_repo_settings;
 use crate::error::AppError;
-use crate::state::AppState;
+use crate::git::diff::DiffOptions;
+use crate::state::{AppState, OpenRepo};
+
+/// Number of recent commit summaries passed as style reference
+const RECENT_COMMITS_FOR_STYLE: usize = 10;
+
+/// Payload of the `ai:stream-chunk` event
+#[derive(Debug, Clone, Serialize)]
+pub struct AiStreamChunk {
+    pub request_id: String,
+    pub content: String,
+    pub done: bool,
+}
+
+/// Provider for the global AI config, with `repo`'s overrides applied if given
+async fn current_provider(
+    repo: Option<&OpenRepo>,
+    state: &State<'_, AppState>,
+) -> Result<Box<dyn AiProvider>, AppError> {
+    let config = state.config.lock().await;
+    if !config.ai.enabled {
+        return Err(AppError::Config("AI features are disabled".into()));
+    }
+    match repo {
+        Some(repo) => create_provider(&repo.settings.lock().await.ai_config(&config.ai)),
+        None => create_provider(&config.ai),
+    }
+}
+
+/// Diff content prepared for an AI prompt, with everything that was masked or left out
+#[derive(Debug, Clone, Serialize)]
+pub struct AiDiffContext {
+    #[serde(flatten)]
+    pub condensed: CondensedDiff,
+    pub redaction: RedactionReport,
+}
+
+/// Staged diff with secrets redacted, condensed to the configured token budget
+async fn staged_context(
+    repo: &OpenRepo,
+    state: &State<'_, AppState>,
+) -> Result<AiDiffContext, AppError> {
+    let mut diff = repo.diff_staged(&DiffOptions::default()).await?;
+
+    if diff.files.is_empty() {
+        return Err(AppError::InvalidInput("No staged changes".into()));
+    }
+
+    // Redact before condensing so no secret can survive in any rendering of the diff
+    let settings = repo.settings.lock().await.clone();
+    let redaction = Redactor::new(&settings.redaction)?.redact_diff(&mut diff);
+    if !redaction.findings.is_empty() {
+        tracing::info!(
+            "Redacted {} secret(s) from staged diff before AI request",
+            redaction.findings.len()
+        );
+    }
+
+    let budget = settings
+        .ai_config(&state.config.lock().await.ai)
+        .diff_token_budget();
+    Ok(AiDiffContext {
+        condensed: condense_diff(&diff, budget),
+        redaction,
+    })
+}
+
+async fn commit_message_request(
+    repo: &OpenRepo,
+    state: &State<'_, AppState>,
+) -> Result<CompletionRequest, AppError> {
+    let context = staged_context(repo, state).await?;
+    let template = repo.settings.lock().await.commit_template.clone();
+    // An unborn HEAD has no history to use as style reference
+    let recent_commits = repo
+        .log(RECENT_COMMITS_FOR_STYLE, false)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| c.summary)
+        .collect::<Vec<String>>();
+
+    Ok(CompletionRequest {
+        messages: vec![
+            ChatMessage::system(prompts::COMMIT_MESSAGE_SYSTEM),
+            ChatMessage::user(prompts::build_commit_prompt(
+                &context.condensed.text,
+                &recent_commits,
+                template.as_deref(),
+            )),
+        ],
+        max_tokens: Some(512),
+        temperature: Some(0.2),
+        stream: false,
+    })
+}
+
+async fn change_summary_request(
+    repo: &OpenRepo,
+    state: &State<'_, AppState>,
+) -> Result<CompletionRequest, AppError> {
+    let context = staged_context(repo, state).await?;
+
+    Ok(CompletionRequest {
+        messages: vec![
+            ChatMessage::system(prompts::CHANGE_SUMMARY_SYSTEM),
+            ChatMessage::user(prompts::build_summary_prompt(&context.condensed.text)),
+        ],
+        max_tokens: Some(1024),
+        temperature: Some(0.3),
+        stream: false,
+    })
+}
+
+/// Run a completion as a stream, emitting `ai:stream-chunk` events tagged with
+/// `request_id`. The request can be stopped with `cancel_ai_request`.
+async fn run_stream(
+    request_id: String,
+    mut request: CompletionRequest,
+    repo: &OpenRepo,
+    app: AppHandle,
+    state: &State<'_, AppState>,
+) -> Result<String, AppError> {
+    let provider = current_provider(Some(repo), state).await?;
+    request.stream = true;
+
+    let cancel_rx = state.ai_requests.lock().await.register(&request_id)?;
+
+    let chunk_app = app.clone();
+    let chunk_request_id = request_id.clone();
+    let on_chunk = move |chunk: CompletionChunk| {
+        let event = AiStreamChunk {
+            request_id: chunk_request_id.clone(),
+            content: chunk.content,
+            done: chunk.done,
+        };
+        if let Err(e) = chunk_app.emit("ai:stream-chunk", event) {
+            tracing::error!("Failed to emit ai:stream-chunk event: {}", e);
+        }
+    };
+
+    // Dropping the provider future on cancel closes the HTTP connection
+    let result = tokio::select! {
+        result = provider.complete_stream(&request, &on_chunk) => result,
+        _ = cancel_rx => Err(AppError::Cancelled),
+    };
+
+    state.ai_requests.lock().await.finish(&request_id);
+    if result.is_err() {
+        // Let listeners close the stream even when no final chunk was produced
+        let _ = app.emit(
+            "ai:stream-chunk",
+            AiStreamChunk {
+                request_id,
+                content: String::new(),
+                done: true,
+            },
+        );
+    }
+    result
+}
 
-/// Placeholder for AI commit message generation
-/// Full implementation will come in Phase 1 W7-8
 #[tauri::command]
 pub async fn generate_commit_message(
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<String, AppError> {
-    let repo = state.current_repo.lock().await;
-    let _repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-
-    // TODO: Get staged diff, build prompt, call AI provider
-    Err(AppError::AiProvider("AI provider not configured yet".into()))
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let request = commit_message_request(&repo, &state).await?;
+    let provider = current_provider(Some(&repo), &state).await?;
+    provider.complete(&request).await
 }
 
-/// Placeholder for change summary generation
 #[tauri::command]
 pub async fn generate_change_summary(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let request = change_summary_request(&repo, &state).await?;
+    let provider = current_provider(Some(&repo), &state).await?;
+    provider.complete(&request).await
+}
+
+#[tauri::command]
+pub async fn generate_commit_message_stream(
+    request_id: String,
+    repo_id: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<String, AppError> {
-    let repo = state.current_repo.lock().await;
-    let _repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let request = commit_message_request(&repo, &state).await?;
+    run_stream(request_id, request, &repo, app, &state).await
+}
 
-    // TODO: Get staged diff, build prompt, call AI provider
-    Err(AppError::AiProvider("AI provider not configured yet".into()))
+#[tauri::command]
+pub async fn generate_change_summary_stream(
+    request_id: String,
+    repo_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let request = change_summary_request(&repo, &state).await?;
+    run_stream(request_id, request, &repo, app, &state).await
+}
+
+#[tauri::command]
+pub async fn cancel_ai_request(
+    request_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    state.ai_requests.lock().await.cancel(&request_id);
+    Ok(())
+}
+
+/// Preview exactly what diff content would be sent to the AI provider,
+/// including the files that were dropped or truncated and the secrets masked
+#[tauri::command]
+pub async fn get_ai_diff_context(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<AiDiffContext, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    staged_context(&repo, &state).await
+}
+
+#[tauri::command]
+pub async fn get_redaction_config(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<RedactionConfig, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let redaction = repo.settings.lock().await.redaction.clone();
+    Ok(redaction)
+}
+
+#[tauri::command]
+pub async fn update_redaction_config(
+    config: RedactionConfig,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    // Reject rules that don't compile before they are persisted
+    Redactor::new(&config)?;
+
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let mut settings = repo.settings.lock().await;
+    let mut updated = settings.clone();
+    updated.redaction = config;
+    save_repo_settings(&state.db, &repo.path, &updated).await?;
+    *settings = updated;
+    Ok(())
+}
+
+/// List all supported providers along with their key status
+#[tauri::command]
+pub async fn get_ai_providers(
+    state: State<'_, AppState>,
+) -> Result<Vec<ProviderConfig>, AppError> {
+    let config = state.config.lock().await;
+    let mut providers = Vec::new();
+
+    for kind in ProviderKind::ALL {
+        let is_active = kind == config.ai.provider;
+        let base_url = if is_active {
+            config.ai.base_url.clone()
+        } else {
+            None
+        }
+        .or_else(|| kind.default_base_url().map(str::to_string))
+        .unwrap_or_default();
+        let model = if is_active {
+            config.ai.model.clone()
+        } else {
+            kind.default_model().to_string()
+        };
+
+        providers.push(ProviderConfig {
+            name: kind.display_name().to_string(),
+            has_api_key: auth::load_api_key(&kind)?.is_some(),
+            kind,
+            base_url,
+            model,
+        });
+    }
+
+    Ok(providers)
+}
+
+#[tauri::command]
+pub async fn set_ai_api_key(provider: ProviderKind, api_key: String) -> Result<(), AppError> {
+    if api_key.trim().is_empty() {
+        auth::delete_api_key(&provider)
+    } else {
+        auth::store_api_key(&provider, api_key.trim())
+    }
+}
+
+/// Check that the configured provider is reachable and accepts the stored key
+#[tauri::command]
+pub async fn validate_ai_provider(state: State<'_, AppState>) -> Result<bool, AppError> {
+    let provider = current_provider(None, &state).await?;
+    provider.validate().await
 }
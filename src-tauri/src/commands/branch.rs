@@ -2,49 +2,68 @@ use std::collections::HashMap;
 
 use tauri::State;
 
+use crate::commands::journal::journaled;
 use crate::error::AppError;
 use crate::git::repository::BranchInfo;
 use crate::state::AppState;
 
 #[tauri::command]
-pub async fn get_branches(state: State<'_, AppState>) -> Result<Vec<BranchInfo>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn get_branches(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<BranchInfo>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.branches().await
 }
 
 #[tauri::command]
-pub async fn get_current_branch(state: State<'_, AppState>) -> Result<String, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn get_current_branch(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.current_branch().await
 }
 
 #[tauri::command]
-pub async fn create_branch(name: String, state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn create_branch(
+    name: String,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.create_branch(&name).await
 }
 
 #[tauri::command]
-pub async fn checkout_branch(name: String, state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn checkout_branch(
+    name: String,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.checkout_branch(&name).await
 }
 
 #[tauri::command]
-pub async fn delete_branch(name: String, state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.delete_branch(&name).await
+pub async fn delete_branch(
+    name: String,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.settings.lock().await.check_not_protected(&name)?;
+    let action = repo.capture_ref_deletion(&format!("refs/heads/{}", name)).await;
+    let description = format!("Delete branch {}", name);
+    journaled(&state, &repo, description, action, repo.delete_branch(&name)).await
 }
 
 #[tauri::command]
-pub async fn get_branch_tips(state: State<'_, AppState>) -> Result<HashMap<String, Vec<String>>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn get_branch_tips(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<HashMap<String, Vec<String>>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.branch_tips().await
 }
 
@@ -54,9 +73,10 @@ pub async fn get_branch_tips(state: State<'_, AppState>) -> Result<HashMap<Strin
 pub async fn rename_branch(
     old_name: String,
     new_name: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.settings.lock().await.check_not_protected(&old_name)?;
     repo.rename_branch(&old_name, &new_name).await
 }
@@ -1,7 +1,10 @@
 use tauri::State;
 
+use crate::commands::journal::journaled;
 use crate::error::AppError;
-use crate::git::diff::DiffOutput;
+use crate::git::combined::CombinedDiff;
+use crate::git::diff::{DiffOptions, DiffOutput, DiffStatOutput};
+use crate::git::rebase::{AutosquashResult, FixupKind, RebaseStatus, SplitStatus};
 use crate::git::repository::CommitInfo;
 use crate::state::AppState;
 
@@ -9,10 +12,10 @@ use crate::state::AppState;
 pub async fn create_commit(
     message: String,
     amend: bool,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<String, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.commit(&message, amend).await
 }
 
@@ -20,10 +23,10 @@ pub async fn create_commit(
 pub async fn get_commit_log(
     limit: Option<usize>,
     all: Option<bool>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Vec<CommitInfo>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.log(limit.unwrap_or(200), all.unwrap_or(true)).await
 }
 
@@ -32,21 +35,23 @@ pub async fn get_commit_log(
 #[tauri::command]
 pub async fn amend_commit(
     message: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<String, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.commit(&message, true).await
 }
 
 #[tauri::command]
 pub async fn undo_last_commit(
     soft: bool,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.undo_last_commit(soft).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let action = repo.capture_reset(false).await;
+    let undo = repo.undo_last_commit(soft);
+    journaled(&state, &repo, "Undo last commit".into(), action, undo).await
 }
 
 // ==================== Phase 1: Reset ====================
@@ -55,11 +60,14 @@ pub async fn undo_last_commit(
 pub async fn reset_to_commit(
     commit_id: String,
     mode: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.reset(&commit_id, &mode).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let action = repo.capture_reset(mode == "hard").await;
+    let short_id = commit_id.get(..7).unwrap_or(&commit_id);
+    let description = format!("Reset ({}) to {}", mode, short_id);
+    journaled(&state, &repo, description, action, repo.reset(&commit_id, &mode)).await
 }
 
 // ==================== Phase 4: Commit diff & History & Search ====================
@@ -67,21 +75,64 @@ pub async fn reset_to_commit(
 #[tauri::command]
 pub async fn get_commit_diff(
     commit_id: String,
+    parent: Option<usize>,
+    options: Option<DiffOptions>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<DiffOutput, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.commit_diff(&commit_id).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.commit_diff(&commit_id, parent, &options.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub async fn get_combined_diff(
+    commit_id: String,
+    options: Option<DiffOptions>,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<CombinedDiff, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.combined_diff(&commit_id, &options.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub async fn get_revision_diff(
+    from: String,
+    to: Option<String>,
+    pathspecs: Option<Vec<String>>,
+    options: Option<DiffOptions>,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DiffOutput, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let pathspecs = pathspecs.unwrap_or_default();
+    repo.diff_revisions(&from, to.as_deref(), &pathspecs, &options.unwrap_or_default())
+        .await
+}
+
+#[tauri::command]
+pub async fn get_revision_diff_stats(
+    from: String,
+    to: Option<String>,
+    pathspecs: Option<Vec<String>>,
+    options: Option<DiffOptions>,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DiffStatOutput, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let pathspecs = pathspecs.unwrap_or_default();
+    repo.diff_revisions_stats(&from, to.as_deref(), &pathspecs, &options.unwrap_or_default())
+        .await
 }
 
 #[tauri::command]
 pub async fn get_file_history(
     path: String,
     limit: Option<usize>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Vec<CommitInfo>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.file_log(&path, limit.unwrap_or(50)).await
 }
 
@@ -89,10 +140,10 @@ pub async fn get_file_history(
 pub async fn search_commits(
     query: String,
     limit: Option<usize>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Vec<CommitInfo>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.search_commits(&query, limit.unwrap_or(50)).await
 }
 
@@ -100,10 +151,10 @@ pub async fn search_commits(
 pub async fn get_commit_log_paged(
     max_count: usize,
     skip: usize,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Vec<CommitInfo>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.log_paged(max_count, skip).await
 }
 
@@ -112,9 +163,72 @@ pub async fn get_branch_log(
     branch: String,
     limit: Option<usize>,
     first_parent: Option<bool>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Vec<CommitInfo>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.log_branch(&branch, limit.unwrap_or(200), first_parent.unwrap_or(false)).await
 }
+
+// ==================== History rewriting ====================
+
+/// Commit the staged changes as a `fixup!`, `squash!` or `amend!` commit for
+/// `commit_id` and autosquash it into that commit
+#[tauri::command]
+pub async fn create_fixup_commit(
+    commit_id: String,
+    kind: FixupKind,
+    message: Option<String>,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<AutosquashResult, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.fixup_commit(kind, &commit_id, message.as_deref()).await
+}
+
+/// Change the message of a commit on the current branch without touching the
+/// work tree. Returns the new branch tip.
+#[tauri::command]
+pub async fn reword_commit(
+    commit_id: String,
+    message: String,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.reword_commit(&commit_id, &message).await
+}
+
+/// Start splitting a commit. Its changes are left in the work tree to be
+/// staged and committed in parts; `finish_split_commit` replays the commits
+/// after it.
+#[tauri::command]
+pub async fn start_split_commit(
+    commit_id: String,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SplitStatus, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.start_split(&commit_id).await
+}
+
+#[tauri::command]
+pub async fn get_split_status(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<SplitStatus>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.split_status().await
+}
+
+/// Commit what is left of the split commit with `message`, if anything, and
+/// replay the commits after it. `rebase_abort` cancels the split instead.
+#[tauri::command]
+pub async fn finish_split_commit(
+    message: Option<String>,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<RebaseStatus>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.finish_split(message.as_deref()).await
+}
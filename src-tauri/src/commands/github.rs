@@ -78,16 +78,18 @@ pub async fn github_logout(
 
 #[tauri::command]
 pub async fn github_detect_repo(
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Option<GitHubRepo>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = match repo.as_ref() {
-        Some(r) => r,
-        None => return Ok(None),
+    let repo = match state.repo(repo_id.as_deref()).await {
+        Ok(r) => r,
+        Err(_) if repo_id.is_none() => return Ok(None),
+        Err(e) => return Err(e),
     };
+    let default_remote = repo.settings.lock().await.remote(None);
     let remotes = repo.remotes().await?;
     for remote in &remotes {
-        if remote.name == "origin" {
+        if remote.name == default_remote {
             return Ok(GitHubClient::parse_github_remote(&remote.url));
         }
     }
@@ -105,7 +107,7 @@ async fn get_client(state: &State<'_, AppState>) -> Result<GitHubClient, AppErro
     guard
         .as_ref()
         .map(|c| c.clone())
-        .ok_or_else(|| AppError::General("GitHub 未认证，请先连接 GitHub".into()))
+        .ok_or_else(|| AppError::Unauthenticated("GitHub".into()))
 }
 
 // ==================== Search Repos ====================
@@ -0,0 +1,83 @@
+use std::future::Future;
+
+use tauri::State;
+
+use crate::error::{AppError, AppResult};
+use crate::git::journal::{JournalAction, JournalEntry};
+use crate::state::{AppState, OpenRepo};
+
+/// Run a destructive operation and, once it succeeded, journal the state
+/// `action` captured beforehand so it can be undone.
+///
+/// Failing to capture or record is logged rather than returned: it must not
+/// block the operation, and must not report an operation that already
+/// happened as failed.
+pub(crate) async fn journaled<T>(
+    state: &State<'_, AppState>,
+    repo: &OpenRepo,
+    description: String,
+    action: AppResult<JournalAction>,
+    operation: impl Future<Output = AppResult<T>>,
+) -> AppResult<T> {
+    let result = operation.await?;
+    match action {
+        Ok(action) if action.is_empty() => {}
+        Ok(action) => {
+            if let Err(e) = record(state, repo, &description, &action).await {
+                tracing::error!("Failed to journal '{}': {}", description, e);
+            }
+        }
+        Err(e) => tracing::warn!("Could not capture state for '{}': {}", description, e),
+    }
+    Ok(result)
+}
+
+async fn record(
+    state: &State<'_, AppState>,
+    repo: &OpenRepo,
+    description: &str,
+    action: &JournalAction,
+) -> AppResult<()> {
+    let id = state.db.add_journal_entry(&repo.path, description, action).await?;
+    repo.anchor_journal_objects(id, action.objects()).await?;
+
+    let pruned = state.db.prune_journal(&repo.path).await?;
+    if !pruned.is_empty() {
+        repo.drop_journal_anchors(pruned).await?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_operation_journal(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<JournalEntry>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    state.db.journal_entries(&repo.path).await
+}
+
+/// Restore the state from before a journaled operation
+#[tauri::command]
+pub async fn undo_operation(
+    entry_id: i64,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let entry = state
+        .db
+        .journal_entry(&repo.path, entry_id)
+        .await?
+        .ok_or_else(|| AppError::InvalidInput(format!("No journal entry {}", entry_id)))?;
+    if entry.undone {
+        return Err(AppError::InvalidInput(format!(
+            "'{}' was already undone",
+            entry.description
+        )));
+    }
+
+    repo.undo_journal_action(&entry.action).await?;
+    state.db.mark_journal_entry_undone(entry.id).await?;
+    repo.drop_journal_anchors(vec![entry.id]).await
+}
@@ -1,6 +1,7 @@
 use tauri::State;
 
 use crate::error::AppError;
+use crate::git::rebase::{RebaseStatus, RebaseStep};
 use crate::git::repository::RepoOperationState;
 use crate::state::AppState;
 
@@ -10,31 +11,37 @@ use crate::state::AppState;
 pub async fn merge_branch(
     branch: String,
     no_ff: bool,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<String, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.merge(&branch, no_ff).await
 }
 
 #[tauri::command]
-pub async fn merge_abort(state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn merge_abort(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.merge_abort().await
 }
 
 #[tauri::command]
-pub async fn merge_continue(state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn merge_continue(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.merge_continue().await
 }
 
 #[tauri::command]
-pub async fn get_repo_state(state: State<'_, AppState>) -> Result<RepoOperationState, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn get_repo_state(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<RepoOperationState, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.repo_state().await
 }
 
@@ -43,24 +50,28 @@ pub async fn get_repo_state(state: State<'_, AppState>) -> Result<RepoOperationS
 #[tauri::command]
 pub async fn cherry_pick(
     commit_id: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.cherry_pick(&commit_id).await
 }
 
 #[tauri::command]
-pub async fn cherry_pick_abort(state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn cherry_pick_abort(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.cherry_pick_abort().await
 }
 
 #[tauri::command]
-pub async fn cherry_pick_continue(state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn cherry_pick_continue(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.cherry_pick_continue().await
 }
 
@@ -69,24 +80,28 @@ pub async fn cherry_pick_continue(state: State<'_, AppState>) -> Result<(), AppE
 #[tauri::command]
 pub async fn revert_commit(
     commit_id: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.revert(&commit_id).await
 }
 
 #[tauri::command]
-pub async fn revert_abort(state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn revert_abort(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.revert_abort().await
 }
 
 #[tauri::command]
-pub async fn revert_continue(state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn revert_continue(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.revert_continue().await
 }
 
@@ -95,30 +110,70 @@ pub async fn revert_continue(state: State<'_, AppState>) -> Result<(), AppError>
 #[tauri::command]
 pub async fn rebase_onto(
     onto: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.rebase(&onto).await
 }
 
+/// Default plan for an interactive rebase of HEAD onto `base`
 #[tauri::command]
-pub async fn rebase_abort(state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn get_rebase_todo(
+    base: String,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<RebaseStep>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.rebase_todo(&base).await
+}
+
+/// Run an edited plan. Returns the status when the rebase paused at an
+/// `edit` step; conflicts fail with a merge conflict error and leave the
+/// rebase in progress.
+#[tauri::command]
+pub async fn interactive_rebase(
+    base: String,
+    steps: Vec<RebaseStep>,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<RebaseStatus>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.interactive_rebase(&base, &steps).await
+}
+
+#[tauri::command]
+pub async fn get_rebase_status(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<RebaseStatus>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.rebase_status().await
+}
+
+#[tauri::command]
+pub async fn rebase_abort(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.rebase_abort().await
 }
 
 #[tauri::command]
-pub async fn rebase_continue(state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn rebase_continue(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.rebase_continue().await
 }
 
 #[tauri::command]
-pub async fn rebase_skip(state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn rebase_skip(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.rebase_skip().await
 }
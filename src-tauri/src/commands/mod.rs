@@ -2,9 +2,13 @@ pub mod ai;
 pub mod branch;
 pub mod commit;
 pub mod github;
+pub mod journal;
 pub mod merge;
+pub mod operation;
+pub mod reflog;
 pub mod remote;
 pub mod repo;
+pub mod settings;
 pub mod stash;
 pub mod submodule;
 pub mod tag;
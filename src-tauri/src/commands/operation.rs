@@ -0,0 +1,107 @@
+use std::future::Future;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::error::{AppError, AppResult};
+use crate::git::askpass::CredentialResponse;
+use crate::git::progress::GitProgress;
+use crate::state::AppState;
+
+/// Payload of `git:progress`
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationProgress {
+    pub operation_id: String,
+    #[serde(flatten)]
+    pub progress: GitProgress,
+}
+
+/// Progress callback emitting `git:progress` events for an operation.
+///
+/// Without an `operation_id` the frontend has nothing to match events
+/// against, so progress is dropped.
+pub(crate) fn progress_emitter(
+    app: AppHandle,
+    operation_id: Option<String>,
+) -> impl Fn(GitProgress) + Send + Sync {
+    move |progress| {
+        let Some(operation_id) = operation_id.clone() else {
+            return;
+        };
+        let payload = OperationProgress {
+            operation_id,
+            progress,
+        };
+        if let Err(e) = app.emit("git:progress", payload) {
+            tracing::error!("Failed to emit git:progress event: {}", e);
+        }
+    }
+}
+
+/// Run a long git operation under the configured network timeout.
+///
+/// With an `operation_id` the operation can also be stopped through
+/// `cancel_operation`, even one sent while the command was still preparing
+/// it. Either way the future is dropped, which kills the underlying git
+/// process.
+pub(crate) async fn run_operation<T, F>(
+    state: &State<'_, AppState>,
+    operation_id: Option<String>,
+    operation: F,
+) -> AppResult<T>
+where
+    F: Future<Output = AppResult<T>>,
+{
+    let timeout = state.config.lock().await.git.network_timeout();
+    let limited = async {
+        match timeout {
+            Some(limit) => tokio::time::timeout(limit, operation)
+                .await
+                .map_err(|_| AppError::Timeout(limit.as_secs()))?,
+            None => operation.await,
+        }
+    };
+
+    let Some(operation_id) = operation_id else {
+        return limited.await;
+    };
+
+    let cancel_rx = state.operations.lock().await.register(&operation_id)?;
+
+    let result = tokio::select! {
+        result = limited => result,
+        Ok(()) = cancel_rx => Err(AppError::Cancelled),
+    };
+
+    state.operations.lock().await.finish(&operation_id);
+    result
+}
+
+#[tauri::command]
+pub async fn cancel_operation(
+    operation_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    state.operations.lock().await.cancel(&operation_id);
+    Ok(())
+}
+
+/// Answer a `git:credential-request`; `None` declines it
+#[tauri::command]
+pub async fn respond_credential_request(
+    request_id: String,
+    response: Option<CredentialResponse>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let pending = state.credential_requests.lock().await.remove(&request_id);
+    match pending {
+        Some(answer) => {
+            let _ = answer.send(response);
+            Ok(())
+        }
+        None => Err(AppError::InvalidInput(format!(
+            "No pending credential request '{}'",
+            request_id
+        ))),
+    }
+}
@@ -0,0 +1,59 @@
+use tauri::State;
+
+use crate::error::AppError;
+use crate::git::repository::{DanglingCommit, ReflogEntry};
+use crate::state::AppState;
+
+/// Refs with a reflog: HEAD first, then local branches
+#[tauri::command]
+pub async fn get_reflog_refs(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.reflog_refs().await
+}
+
+#[tauri::command]
+pub async fn get_reflog(
+    refname: Option<String>,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ReflogEntry>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.reflog(refname.as_deref().unwrap_or("HEAD")).await
+}
+
+#[tauri::command]
+pub async fn get_dangling_commits(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<DanglingCommit>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.dangling_commits().await
+}
+
+/// Move a branch back to a commit from its reflog
+#[tauri::command]
+pub async fn restore_branch(
+    name: String,
+    commit_id: String,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.settings.lock().await.check_not_protected(&name)?;
+    repo.restore_branch(&name, &commit_id).await
+}
+
+/// Create a branch at a reflog entry or dangling commit
+#[tauri::command]
+pub async fn create_branch_at(
+    name: String,
+    commit_id: String,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.create_branch_at(&name, &commit_id).await
+}
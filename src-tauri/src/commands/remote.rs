@@ -1,47 +1,71 @@
-use tauri::State;
+use tauri::{AppHandle, State};
 
+use crate::commands::operation::{progress_emitter, run_operation};
 use crate::error::AppError;
 use crate::git::repository::RemoteInfo;
 use crate::state::AppState;
 
 #[tauri::command]
 pub async fn fetch_remote(
-    remote: String,
+    remote: Option<String>,
+    operation_id: Option<String>,
+    repo_id: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.fetch(&remote).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let on_progress = progress_emitter(app, operation_id.clone());
+    let remote = repo.settings.lock().await.remote(remote);
+    run_operation(&state, operation_id, repo.fetch(&remote, &on_progress)).await
 }
 
 #[tauri::command]
 pub async fn pull_remote(
-    remote: String,
+    remote: Option<String>,
     branch: String,
+    operation_id: Option<String>,
+    repo_id: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.pull(&remote, &branch).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let on_progress = progress_emitter(app, operation_id.clone());
+    let (remote, strategy) = {
+        let settings = repo.settings.lock().await;
+        (settings.remote(remote), settings.pull_strategy())
+    };
+    let pull = repo.pull_with_strategy(&remote, &branch, strategy, &on_progress);
+    run_operation(&state, operation_id, pull).await
 }
 
 #[tauri::command]
 pub async fn push_remote(
-    remote: String,
+    remote: Option<String>,
     branch: String,
+    operation_id: Option<String>,
+    repo_id: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.push(&remote, &branch).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let on_progress = progress_emitter(app, operation_id.clone());
+    let remote = repo.settings.lock().await.remote(remote);
+    run_operation(
+        &state,
+        operation_id,
+        repo.push(&remote, &branch, &on_progress),
+    )
+    .await
 }
 
 // ==================== Phase 2: Remote management ====================
 
 #[tauri::command]
-pub async fn get_remotes(state: State<'_, AppState>) -> Result<Vec<RemoteInfo>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn get_remotes(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<RemoteInfo>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.remotes().await
 }
 
@@ -49,20 +73,20 @@ pub async fn get_remotes(state: State<'_, AppState>) -> Result<Vec<RemoteInfo>,
 pub async fn add_remote(
     name: String,
     url: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.add_remote(&name, &url).await
 }
 
 #[tauri::command]
 pub async fn remove_remote(
     name: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.remove_remote(&name).await
 }
 
@@ -70,52 +94,87 @@ pub async fn remove_remote(
 pub async fn rename_remote(
     old_name: String,
     new_name: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.rename_remote(&old_name, &new_name).await
 }
 
 #[tauri::command]
 pub async fn fetch_prune(
-    remote: String,
+    remote: Option<String>,
+    operation_id: Option<String>,
+    repo_id: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.fetch_prune(&remote).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let on_progress = progress_emitter(app, operation_id.clone());
+    let remote = repo.settings.lock().await.remote(remote);
+    run_operation(
+        &state,
+        operation_id,
+        repo.fetch_prune(&remote, &on_progress),
+    )
+    .await
 }
 
 #[tauri::command]
 pub async fn push_set_upstream(
-    remote: String,
+    remote: Option<String>,
     branch: String,
+    operation_id: Option<String>,
+    repo_id: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.push_set_upstream(&remote, &branch).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let on_progress = progress_emitter(app, operation_id.clone());
+    let remote = repo.settings.lock().await.remote(remote);
+    let push = repo.push_set_upstream(&remote, &branch, &on_progress);
+    run_operation(&state, operation_id, push).await
 }
 
 #[tauri::command]
 pub async fn pull_rebase(
-    remote: String,
+    remote: Option<String>,
     branch: String,
+    operation_id: Option<String>,
+    repo_id: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.pull_rebase(&remote, &branch).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let on_progress = progress_emitter(app, operation_id.clone());
+    let remote = repo.settings.lock().await.remote(remote);
+    run_operation(
+        &state,
+        operation_id,
+        repo.pull_rebase(&remote, &branch, &on_progress),
+    )
+    .await
 }
 
 #[tauri::command]
 pub async fn sync_remote(
-    remote: String,
+    remote: Option<String>,
     branch: String,
+    operation_id: Option<String>,
+    repo_id: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.sync(&remote, &branch).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let on_progress = progress_emitter(app, operation_id.clone());
+    let (remote, strategy) = {
+        let settings = repo.settings.lock().await;
+        (settings.remote(remote), settings.pull_strategy())
+    };
+    run_operation(&state, operation_id, async {
+        repo.pull_with_strategy(&remote, &branch, strategy, &on_progress)
+            .await?;
+        repo.push(&remote, &branch, &on_progress).await
+    })
+    .await
 }
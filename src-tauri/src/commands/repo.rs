@@ -1,78 +1,179 @@
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
 
-use tauri::State;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
 
-use crate::error::AppError;
+use crate::ai::redact::Redactor;
+use crate::commands::operation::{progress_emitter, run_operation};
+use crate::config::repo::{load_repo_settings, save_repo_settings, RepoSettings};
+use crate::error::{AppError, AppResult};
 use crate::git::repository::GitRepository;
-use crate::persistence;
-use crate::state::{AppState, RepoEntry};
+use crate::state::{AppState, OpenRepoInfo, RepoEntry};
 
 #[tauri::command]
-pub async fn open_repo(path: String, state: State<'_, AppState>) -> Result<String, AppError> {
+pub async fn open_repo(
+    path: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<OpenRepoInfo, AppError> {
     let repo_path = PathBuf::from(&path);
     if !repo_path.exists() {
         return Err(AppError::RepoNotFound(path));
     }
 
-    let repo = GitRepository::open(&repo_path)?;
-    let name = repo.name();
+    let info = register_repo(&repo_path, GitRepository::open, app, &state).await?;
 
     // Update recent repos
-    {
-        let mut recent = state.recent_repos.lock().await;
-        recent.retain(|r| r.path != repo_path);
-        recent.insert(
-            0,
-            RepoEntry {
-                path: repo_path,
-                name: name.clone(),
-                last_opened: chrono::Utc::now(),
-            },
-        );
-        if recent.len() > 20 {
-            recent.truncate(20);
-        }
-
-        // Persist to disk
-        if let Err(e) = persistence::save_recent_repos(&state.app_data_dir, &recent) {
-            tracing::warn!("Failed to save recent repos: {e}");
-        }
+    let entry = RepoEntry {
+        path: repo_path,
+        name: info.name.clone(),
+        last_opened: chrono::Utc::now(),
+    };
+    if let Err(e) = state.db.touch_recent_repo(&entry).await {
+        tracing::warn!("Failed to save recent repos: {e}");
     }
 
-    *state.current_repo.lock().await = Some(Arc::new(repo));
-    Ok(name)
+    Ok(info)
 }
 
 #[tauri::command]
-pub async fn init_repo(path: String, state: State<'_, AppState>) -> Result<String, AppError> {
+pub async fn init_repo(
+    path: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<OpenRepoInfo, AppError> {
     let repo_path = PathBuf::from(&path);
-    let repo = GitRepository::init(&repo_path)?;
-    let name = repo.name();
+    register_repo(&repo_path, GitRepository::init, app, &state).await
+}
+
+/// Make the repository at `path` the active one. Opening a repository that
+/// already has a tab just switches to it; otherwise `open` opens it, and it
+/// is added with its per-repo settings resolved and its file watcher running.
+async fn register_repo(
+    path: &Path,
+    open: fn(&Path) -> AppResult<GitRepository>,
+    app: AppHandle,
+    state: &State<'_, AppState>,
+) -> AppResult<OpenRepoInfo> {
+    // Held from the lookup to the insert, so opening the same repository
+    // twice at once cannot add two entries and two watchers
+    let mut repos = state.repos.lock().await;
+    if let Some(existing) = repos.find_by_path(path) {
+        repos.set_active(&existing.id)?;
+        return Ok(repos.info(&existing));
+    }
 
-    *state.current_repo.lock().await = Some(Arc::new(repo));
-    Ok(name)
+    let repo = open(path)?;
+    let settings = load_repo_settings(&state.db, &repo.path)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to load repo settings: {e}");
+            RepoSettings::default()
+        });
+    let entry = repos.insert(repo, settings);
+    let info = repos.info(&entry);
+    drop(repos);
+
+    let watched = entry
+        .watcher
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .watch(&entry.repo, entry.id.clone(), app);
+    // The repository is still usable without live updates
+    if let Err(e) = watched {
+        tracing::warn!("Failed to watch repository {}: {e}", entry.path.display());
+    }
+    Ok(info)
 }
 
 #[tauri::command]
 pub async fn get_recent_repos(state: State<'_, AppState>) -> Result<Vec<RepoEntry>, AppError> {
-    let recent = state.recent_repos.lock().await;
-    Ok(recent.clone())
+    state.db.recent_repos().await
 }
 
+/// Close a repository (the active one when `repo_id` is omitted)
 #[tauri::command]
-pub async fn close_repo(state: State<'_, AppState>) -> Result<(), AppError> {
-    *state.current_repo.lock().await = None;
+pub async fn close_repo(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    state.repos.lock().await.remove(&repo.id);
+    // Commands still holding the entry must not keep the watcher alive
+    repo.watcher.lock().unwrap_or_else(|e| e.into_inner()).unwatch();
     Ok(())
 }
 
+/// Open repositories in tab order
+#[tauri::command]
+pub async fn list_open_repos(state: State<'_, AppState>) -> Result<Vec<OpenRepoInfo>, AppError> {
+    Ok(state.repos.lock().await.list())
+}
+
+/// Make a repository the default target of commands called without a `repo_id`
+#[tauri::command]
+pub async fn set_active_repo(repo_id: String, state: State<'_, AppState>) -> Result<(), AppError> {
+    state.repos.lock().await.set_active(&repo_id)
+}
+
+/// Payload of the `repo-settings:changed` event
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoSettingsChanged {
+    pub repo_id: String,
+    pub settings: RepoSettings,
+}
+
+#[tauri::command]
+pub async fn get_repo_settings(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<RepoSettings, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let settings = repo.settings.lock().await.clone();
+    Ok(settings)
+}
+
+/// Validate, persist and apply settings for a repository, then broadcast
+/// `repo-settings:changed`
+#[tauri::command]
+pub async fn update_repo_settings(
+    settings: RepoSettings,
+    repo_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<RepoSettings, AppError> {
+    settings.validate()?;
+    Redactor::new(&settings.redaction)?;
+
+    let repo = state.repo(repo_id.as_deref()).await?;
+    {
+        let mut current = repo.settings.lock().await;
+        save_repo_settings(&state.db, &repo.path, &settings).await?;
+        *current = settings.clone();
+    }
+
+    let event = RepoSettingsChanged {
+        repo_id: repo.id.clone(),
+        settings: settings.clone(),
+    };
+    if let Err(e) = app.emit("repo-settings:changed", event) {
+        tracing::error!("Failed to emit repo-settings:changed event: {}", e);
+    }
+    Ok(settings)
+}
+
 // ==================== Phase 2: Clone ====================
 
 #[tauri::command]
 pub async fn clone_repo(
     url: String,
     dest_path: String,
+    operation_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
 ) -> Result<(), AppError> {
     let dest = PathBuf::from(&dest_path);
-    crate::git::cli::clone_repo(&url, &dest).await
+    let on_progress = progress_emitter(app, operation_id.clone());
+    let clone = crate::git::cli::clone_repo(&url, &dest, &on_progress);
+    run_operation(&state, operation_id, clone).await
 }
@@ -0,0 +1,32 @@
+use tauri::{AppHandle, Emitter, State};
+
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::persistence;
+use crate::state::AppState;
+
+#[tauri::command]
+pub async fn get_settings(state: State<'_, AppState>) -> Result<AppConfig, AppError> {
+    Ok(state.config.lock().await.clone())
+}
+
+/// Validate, persist and apply new settings, then broadcast `settings:changed`
+#[tauri::command]
+pub async fn update_settings(
+    config: AppConfig,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<AppConfig, AppError> {
+    config.validate()?;
+
+    {
+        let mut current = state.config.lock().await;
+        persistence::save_settings(&state.app_data_dir, &config)?;
+        *current = config.clone();
+    }
+
+    if let Err(e) = app.emit("settings:changed", &config) {
+        tracing::error!("Failed to emit settings:changed event: {}", e);
+    }
+    Ok(config)
+}
@@ -1,5 +1,6 @@
 use tauri::State;
 
+use crate::commands::journal::journaled;
 use crate::error::AppError;
 use crate::git::repository::StashEntry;
 use crate::state::AppState;
@@ -8,50 +9,60 @@ use crate::state::AppState;
 pub async fn stash_save(
     message: Option<String>,
     include_untracked: bool,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.stash_push(message.as_deref(), include_untracked).await
 }
 
 #[tauri::command]
-pub async fn stash_pop(index: Option<usize>, state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn stash_pop(
+    index: Option<usize>,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.stash_pop(index).await
 }
 
 #[tauri::command]
 pub async fn stash_apply(
     index: Option<usize>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.stash_apply(index).await
 }
 
 #[tauri::command]
 pub async fn stash_drop(
     index: Option<usize>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.stash_drop(index).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let action = repo.capture_stash_drop(index).await;
+    let description = format!("Drop stash@{{{}}}", index.unwrap_or(0));
+    journaled(&state, &repo, description, action, repo.stash_drop(index)).await
 }
 
 #[tauri::command]
-pub async fn stash_list(state: State<'_, AppState>) -> Result<Vec<StashEntry>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn stash_list(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<StashEntry>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.stash_list().await
 }
 
 #[tauri::command]
-pub async fn stash_clear(state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.stash_clear().await
+pub async fn stash_clear(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let action = repo.capture_stash_clear().await;
+    journaled(&state, &repo, "Clear stash".into(), action, repo.stash_clear()).await
 }
@@ -1,40 +1,53 @@
-use tauri::State;
+use tauri::{AppHandle, State};
 
+use crate::commands::operation::{progress_emitter, run_operation};
 use crate::error::AppError;
 use crate::git::repository::SubmoduleInfo;
 use crate::state::AppState;
 
 #[tauri::command]
-pub async fn get_submodules(state: State<'_, AppState>) -> Result<Vec<SubmoduleInfo>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn get_submodules(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SubmoduleInfo>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.submodules().await
 }
 
 #[tauri::command]
-pub async fn submodule_init(state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn submodule_init(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.submodule_init().await
 }
 
 #[tauri::command]
 pub async fn submodule_update(
     recursive: bool,
+    operation_id: Option<String>,
+    repo_id: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.submodule_update(recursive).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let on_progress = progress_emitter(app, operation_id.clone());
+    run_operation(
+        &state,
+        operation_id,
+        repo.submodule_update(recursive, &on_progress),
+    )
+    .await
 }
 
 #[tauri::command]
 pub async fn submodule_add(
     url: String,
     path: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.submodule_add(&url, &path).await
 }
@@ -1,13 +1,17 @@
-use tauri::State;
+use tauri::{AppHandle, State};
 
+use crate::commands::journal::journaled;
+use crate::commands::operation::{progress_emitter, run_operation};
 use crate::error::AppError;
 use crate::git::repository::TagInfo;
 use crate::state::AppState;
 
 #[tauri::command]
-pub async fn get_tags(state: State<'_, AppState>) -> Result<Vec<TagInfo>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn get_tags(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<TagInfo>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.tags().await
 }
 
@@ -16,37 +20,55 @@ pub async fn create_tag(
     name: String,
     message: Option<String>,
     commit: Option<String>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.create_tag(&name, message.as_deref(), commit.as_deref()).await
 }
 
 #[tauri::command]
-pub async fn delete_tag(name: String, state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.delete_tag(&name).await
+pub async fn delete_tag(
+    name: String,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let action = repo.capture_ref_deletion(&format!("refs/tags/{}", name)).await;
+    let description = format!("Delete tag {}", name);
+    journaled(&state, &repo, description, action, repo.delete_tag(&name)).await
 }
 
 #[tauri::command]
 pub async fn push_tag(
-    remote: String,
+    remote: Option<String>,
     tag: String,
+    operation_id: Option<String>,
+    repo_id: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.push_tag(&remote, &tag).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let on_progress = progress_emitter(app, operation_id.clone());
+    let remote = repo.settings.lock().await.remote(remote);
+    run_operation(
+        &state,
+        operation_id,
+        repo.push_tag(&remote, &tag, &on_progress),
+    )
+    .await
 }
 
 #[tauri::command]
 pub async fn push_all_tags(
-    remote: String,
+    remote: Option<String>,
+    operation_id: Option<String>,
+    repo_id: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.push_all_tags(&remote).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let on_progress = progress_emitter(app, operation_id.clone());
+    let remote = repo.settings.lock().await.remote(remote);
+    run_operation(&state, operation_id, repo.push_all_tags(&remote, &on_progress)).await
 }
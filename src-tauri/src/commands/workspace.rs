@@ -1,33 +1,40 @@
 use tauri::State;
 
+use crate::commands::journal::journaled;
 use crate::error::AppError;
-use crate::git::diff::DiffOutput;
+use crate::git::diff::{DiffOptions, DiffOutput};
+use crate::git::patch::PatchTarget;
 use crate::git::repository::{
     BlameLine, ConflictFile, ConflictVersions, FileStatus, LineChange,
 };
 use crate::state::AppState;
 
 #[tauri::command]
-pub async fn get_status(state: State<'_, AppState>) -> Result<Vec<FileStatus>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn get_status(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<FileStatus>, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.status().await
 }
 
 #[tauri::command]
-pub async fn stage_files(paths: Vec<String>, state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn stage_files(
+    paths: Vec<String>,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.stage(&paths).await
 }
 
 #[tauri::command]
 pub async fn unstage_files(
     paths: Vec<String>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.unstage(&paths).await
 }
 
@@ -35,18 +42,22 @@ pub async fn unstage_files(
 pub async fn get_diff(
     path: String,
     staged: bool,
+    options: Option<DiffOptions>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<DiffOutput, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.diff_file(&path, staged).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.diff_file(&path, staged, &options.unwrap_or_default()).await
 }
 
 #[tauri::command]
-pub async fn get_staged_diff(state: State<'_, AppState>) -> Result<DiffOutput, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.diff_staged().await
+pub async fn get_staged_diff(
+    options: Option<DiffOptions>,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DiffOutput, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    repo.diff_staged(&options.unwrap_or_default()).await
 }
 
 // ==================== Phase 1: Discard ====================
@@ -54,18 +65,26 @@ pub async fn get_staged_diff(state: State<'_, AppState>) -> Result<DiffOutput, A
 #[tauri::command]
 pub async fn discard_changes(
     paths: Vec<String>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.discard_files(&paths).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let description = match paths.as_slice() {
+        [path] => format!("Discard changes in {}", path),
+        _ => format!("Discard changes in {} files", paths.len()),
+    };
+    let action = repo.capture_discard(Some(&paths)).await;
+    journaled(&state, &repo, description, action, repo.discard_files(&paths)).await
 }
 
 #[tauri::command]
-pub async fn discard_all_changes(state: State<'_, AppState>) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-    repo.discard_all().await
+pub async fn discard_all_changes(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let action = repo.capture_discard(None).await;
+    journaled(&state, &repo, "Discard all changes".into(), action, repo.discard_all()).await
 }
 
 // ==================== Phase 3: Hunk stage/unstage ====================
@@ -74,99 +93,138 @@ pub async fn discard_all_changes(state: State<'_, AppState>) -> Result<(), AppEr
 pub async fn stage_hunk(
     path: String,
     hunk_index: usize,
+    fingerprint: String,
+    options: Option<DiffOptions>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-
-    let diff = repo.diff_file(&path, false).await?;
-    let file = diff.files.first().ok_or(AppError::General("No diff found for file".into()))?;
-    let hunk = file.hunks.get(hunk_index).ok_or(AppError::General("Hunk index out of range".into()))?;
-
-    let patch = crate::git::patch::generate_hunk_patch(&path, hunk, false);
-    repo.apply_patch(&patch, true, false).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let options = options.unwrap_or_default();
+    let target = PatchTarget::Stage;
+    repo.apply_hunk_selection(&path, hunk_index, &fingerprint, None, target, &options)
+        .await
 }
 
 #[tauri::command]
 pub async fn unstage_hunk(
     path: String,
     hunk_index: usize,
+    fingerprint: String,
+    options: Option<DiffOptions>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-
-    let diff = repo.diff_file(&path, true).await?;
-    let file = diff.files.first().ok_or(AppError::General("No diff found for file".into()))?;
-    let hunk = file.hunks.get(hunk_index).ok_or(AppError::General("Hunk index out of range".into()))?;
-
-    let patch = crate::git::patch::generate_hunk_patch(&path, hunk, true);
-    repo.apply_patch(&patch, true, true).await
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let options = options.unwrap_or_default();
+    let target = PatchTarget::Unstage;
+    repo.apply_hunk_selection(&path, hunk_index, &fingerprint, None, target, &options)
+        .await
 }
 
 #[tauri::command]
 pub async fn stage_lines(
     path: String,
     hunk_index: usize,
+    fingerprint: String,
     line_indices: Vec<usize>,
+    options: Option<DiffOptions>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-
-    let diff = repo.diff_file(&path, false).await?;
-    let file = diff.files.first().ok_or(AppError::General("No diff found for file".into()))?;
-    let hunk = file.hunks.get(hunk_index).ok_or(AppError::General("Hunk index out of range".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let lines = Some(line_indices.as_slice());
+    let options = options.unwrap_or_default();
+    let target = PatchTarget::Stage;
+    repo.apply_hunk_selection(&path, hunk_index, &fingerprint, lines, target, &options)
+        .await
+}
 
-    let patch = crate::git::patch::generate_line_patch(&path, hunk, &line_indices, false);
-    repo.apply_patch(&patch, true, false).await
+#[tauri::command]
+pub async fn unstage_lines(
+    path: String,
+    hunk_index: usize,
+    fingerprint: String,
+    line_indices: Vec<usize>,
+    options: Option<DiffOptions>,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let lines = Some(line_indices.as_slice());
+    let options = options.unwrap_or_default();
+    let target = PatchTarget::Unstage;
+    repo.apply_hunk_selection(&path, hunk_index, &fingerprint, lines, target, &options)
+        .await
 }
 
 #[tauri::command]
 pub async fn discard_hunk(
     path: String,
     hunk_index: usize,
+    fingerprint: String,
+    options: Option<DiffOptions>,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
-
-    let diff = repo.diff_file(&path, false).await?;
-    let file = diff.files.first().ok_or(AppError::General("No diff found for file".into()))?;
-    let hunk = file.hunks.get(hunk_index).ok_or(AppError::General("Hunk index out of range".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let action = repo.capture_discard(Some(std::slice::from_ref(&path))).await;
+    let description = format!("Discard hunk in {}", path);
+    let options = options.unwrap_or_default();
+    let target = PatchTarget::Discard;
+    let discard =
+        repo.apply_hunk_selection(&path, hunk_index, &fingerprint, None, target, &options);
+    journaled(&state, &repo, description, action, discard).await
+}
 
-    let patch = crate::git::patch::generate_hunk_patch(&path, hunk, true);
-    repo.apply_patch(&patch, false, true).await
+#[tauri::command]
+pub async fn discard_lines(
+    path: String,
+    hunk_index: usize,
+    fingerprint: String,
+    line_indices: Vec<usize>,
+    options: Option<DiffOptions>,
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
+    let action = repo.capture_discard(Some(std::slice::from_ref(&path))).await;
+    let description = format!("Discard lines in {}", path);
+    let lines = Some(line_indices.as_slice());
+    let options = options.unwrap_or_default();
+    let target = PatchTarget::Discard;
+    let discard =
+        repo.apply_hunk_selection(&path, hunk_index, &fingerprint, lines, target, &options);
+    journaled(&state, &repo, description, action, discard).await
 }
 
 // ==================== Phase 3: Conflict resolution ====================
 
 #[tauri::command]
 pub async fn get_conflict_files(
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Vec<ConflictFile>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.conflict_files().await
 }
 
 #[tauri::command]
 pub async fn get_conflict_versions(
     path: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<ConflictVersions, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.read_conflict_versions(&path).await
 }
 
 #[tauri::command]
 pub async fn mark_resolved(
     path: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.mark_resolved(&path).await
 }
 
@@ -174,10 +232,10 @@ pub async fn mark_resolved(
 pub async fn write_merge_result(
     path: String,
     content: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.write_merge_result(&path, &content).await
 }
 
@@ -186,10 +244,10 @@ pub async fn write_merge_result(
 #[tauri::command]
 pub async fn get_blame(
     path: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Vec<BlameLine>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.blame(&path).await
 }
 
@@ -198,19 +256,19 @@ pub async fn get_blame(
 #[tauri::command]
 pub async fn get_line_changes(
     path: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Vec<LineChange>, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.line_changes(&path).await
 }
 
 #[tauri::command]
 pub async fn add_to_gitignore(
     pattern: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.add_to_gitignore(&pattern).await
 }
@@ -7,26 +7,28 @@ use crate::state::AppState;
 pub async fn worktree_add(
     path: String,
     branch: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.worktree_add(&path, &branch).await
 }
 
 #[tauri::command]
 pub async fn worktree_remove(
     path: String,
+    repo_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.worktree_remove(&path).await
 }
 
 #[tauri::command]
-pub async fn worktree_list(state: State<'_, AppState>) -> Result<String, AppError> {
-    let repo = state.current_repo.lock().await;
-    let repo = repo.as_ref().ok_or(AppError::General("No repository opened".into()))?;
+pub async fn worktree_list(
+    repo_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let repo = state.repo(repo_id.as_deref()).await?;
     repo.worktree_list().await
 }
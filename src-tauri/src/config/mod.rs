@@ -1,13 +1,20 @@
+pub mod repo;
+
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
 use crate::ai::provider::ProviderKind;
+use crate::error::{AppError, AppResult};
 
 /// Application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppConfig {
     pub theme: ThemeMode,
     pub locale: String,
     pub ai: AiConfig,
+    pub git: GitConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,11 +25,108 @@ pub enum ThemeMode {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitConfig {
+    /// Seconds after which fetch/pull/push/clone are killed; 0 disables the limit
+    pub network_timeout_secs: u64,
+}
+
+impl GitConfig {
+    pub fn network_timeout(&self) -> Option<Duration> {
+        (self.network_timeout_secs > 0).then(|| Duration::from_secs(self.network_timeout_secs))
+    }
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            network_timeout_secs: 600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AiConfig {
     pub enabled: bool,
     pub provider: ProviderKind,
     pub model: String,
     pub base_url: Option<String>,
+    /// Max tokens of diff content sent per prompt; `None` picks a per-model default
+    pub token_budget: Option<usize>,
+}
+
+impl AiConfig {
+    /// Token budget for diff content in prompts
+    pub fn diff_token_budget(&self) -> usize {
+        if let Some(budget) = self.token_budget {
+            return budget;
+        }
+        let model = self.model.to_lowercase();
+        if self.provider == ProviderKind::Ollama {
+            // Local models usually run with a small context window
+            4_000
+        } else if model.contains("gpt-3.5") {
+            8_000
+        } else {
+            24_000
+        }
+    }
+}
+
+impl AppConfig {
+    /// Reject settings that would leave the app in a broken state
+    pub fn validate(&self) -> AppResult<()> {
+        if self.locale.trim().is_empty() {
+            return Err(AppError::Config("Locale must not be empty".into()));
+        }
+        self.ai.validate()
+    }
+}
+
+impl AiConfig {
+    fn validate(&self) -> AppResult<()> {
+        if self.model.trim().is_empty() {
+            return Err(AppError::Config("AI model must not be empty".into()));
+        }
+        if self.token_budget == Some(0) {
+            return Err(AppError::Config("Token budget must be greater than zero".into()));
+        }
+
+        match self.base_url.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+            Some(base_url) => {
+                let url = reqwest::Url::parse(base_url).map_err(|e| {
+                    AppError::Config(format!("Invalid base URL '{}': {}", base_url, e))
+                })?;
+                if !matches!(url.scheme(), "http" | "https") {
+                    return Err(AppError::Config(format!(
+                        "Base URL must use http or https: {}",
+                        base_url
+                    )));
+                }
+            }
+            None if self.provider.default_base_url().is_none() => {
+                return Err(AppError::Config(format!(
+                    "{} requires a base URL",
+                    self.provider.display_name()
+                )));
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+impl Default for AiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            provider: ProviderKind::OpenAI,
+            model: "gpt-4o".to_string(),
+            base_url: None,
+            token_budget: None,
+        }
+    }
 }
 
 impl Default for AppConfig {
@@ -30,12 +134,8 @@ impl Default for AppConfig {
         Self {
             theme: ThemeMode::System,
             locale: "zh-CN".to_string(),
-            ai: AiConfig {
-                enabled: true,
-                provider: ProviderKind::OpenAI,
-                model: "gpt-4o".to_string(),
-                base_url: None,
-            },
+            ai: AiConfig::default(),
+            git: GitConfig::default(),
         }
     }
 }
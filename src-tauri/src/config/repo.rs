@@ -0,0 +1,98 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::redact::RedactionConfig;
+use crate::config::AiConfig;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::git::repository::PullStrategy;
+
+/// Remote used when neither the caller nor the repo settings name one
+const FALLBACK_REMOTE: &str = "origin";
+
+/// Per-repository settings, stored in the app database keyed by work tree
+/// path so they are never committed or pushed.
+///
+/// Every `Option` field overrides the matching global `AppConfig` value when set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepoSettings {
+    pub redaction: RedactionConfig,
+    /// Model used for this repo instead of `AppConfig.ai.model`
+    pub ai_model: Option<String>,
+    /// Commit message template handed to the AI prompt (e.g. Conventional Commits)
+    pub commit_template: Option<String>,
+    /// Remote used by fetch/pull/push when the caller doesn't name one
+    pub default_remote: Option<String>,
+    /// Branches that may not be deleted or renamed; `*` matches any suffix
+    pub protected_branches: Vec<String>,
+    pub pull_strategy: Option<PullStrategy>,
+}
+
+impl RepoSettings {
+    pub fn validate(&self) -> AppResult<()> {
+        if matches!(&self.ai_model, Some(model) if model.trim().is_empty()) {
+            return Err(AppError::Config("AI model override must not be empty".into()));
+        }
+        if matches!(&self.default_remote, Some(remote) if remote.trim().is_empty()) {
+            return Err(AppError::Config("Default remote must not be empty".into()));
+        }
+        Ok(())
+    }
+
+    /// Global AI config with this repo's overrides applied
+    pub fn ai_config(&self, global: &AiConfig) -> AiConfig {
+        let mut config = global.clone();
+        if let Some(model) = &self.ai_model {
+            config.model = model.clone();
+        }
+        config
+    }
+
+    /// `requested` if given, otherwise the repo's default remote
+    pub fn remote(&self, requested: Option<String>) -> String {
+        requested
+            .filter(|r| !r.is_empty())
+            .or_else(|| self.default_remote.clone())
+            .unwrap_or_else(|| FALLBACK_REMOTE.to_string())
+    }
+
+    pub fn pull_strategy(&self) -> PullStrategy {
+        self.pull_strategy.unwrap_or_default()
+    }
+
+    pub fn is_protected(&self, branch: &str) -> bool {
+        self.protected_branches.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => branch.starts_with(prefix),
+            None => branch == pattern,
+        })
+    }
+
+    /// Error out if `branch` is protected in this repo
+    pub fn check_not_protected(&self, branch: &str) -> AppResult<()> {
+        if self.is_protected(branch) {
+            return Err(AppError::InvalidInput(format!(
+                "Branch '{}' is protected in this repository",
+                branch
+            )));
+        }
+        Ok(())
+    }
+}
+
+pub async fn load_repo_settings(db: &Database, repo_path: &Path) -> AppResult<RepoSettings> {
+    match db.repo_settings_json(repo_path).await? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(RepoSettings::default()),
+    }
+}
+
+pub async fn save_repo_settings(
+    db: &Database,
+    repo_path: &Path,
+    settings: &RepoSettings,
+) -> AppResult<()> {
+    db.set_repo_settings_json(repo_path, &serde_json::to_string(settings)?)
+        .await
+}
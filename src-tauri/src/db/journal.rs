@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+
+use crate::db::{unix_now, Database};
+use crate::error::AppResult;
+use crate::git::journal::{JournalAction, JournalEntry};
+
+/// Number of journal entries kept per repository
+const MAX_JOURNAL_ENTRIES: i64 = 100;
+
+fn journal_entry(row: SqliteRow) -> AppResult<JournalEntry> {
+    let action: String = row.get("action");
+    Ok(JournalEntry {
+        id: row.get("id"),
+        description: row.get("description"),
+        action: serde_json::from_str::<JournalAction>(&action)?,
+        created_at: row.get("created_at"),
+        undone: row.get::<Option<i64>, _>("undone_at").is_some(),
+    })
+}
+
+impl Database {
+    /// Record a destructive operation and return the entry id
+    pub async fn add_journal_entry(
+        &self,
+        repo_path: &Path,
+        description: &str,
+        action: &JournalAction,
+    ) -> AppResult<i64> {
+        let id = sqlx::query_scalar(
+            "INSERT INTO operation_journal (repo_path, description, action, created_at)
+             VALUES (?, ?, ?, ?) RETURNING id",
+        )
+        .bind(repo_path.to_string_lossy().to_string())
+        .bind(description)
+        .bind(serde_json::to_string(action)?)
+        .bind(unix_now())
+        .fetch_one(self.pool())
+        .await?;
+        Ok(id)
+    }
+
+    /// Journal entries of a repository, newest first
+    pub async fn journal_entries(&self, repo_path: &Path) -> AppResult<Vec<JournalEntry>> {
+        let rows = sqlx::query(
+            "SELECT id, description, action, created_at, undone_at FROM operation_journal
+             WHERE repo_path = ? ORDER BY id DESC",
+        )
+        .bind(repo_path.to_string_lossy().to_string())
+        .fetch_all(self.pool())
+        .await?;
+        rows.into_iter().map(journal_entry).collect()
+    }
+
+    pub async fn journal_entry(&self, repo_path: &Path, id: i64) -> AppResult<Option<JournalEntry>> {
+        let row = sqlx::query(
+            "SELECT id, description, action, created_at, undone_at FROM operation_journal
+             WHERE repo_path = ? AND id = ?",
+        )
+        .bind(repo_path.to_string_lossy().to_string())
+        .bind(id)
+        .fetch_optional(self.pool())
+        .await?;
+        row.map(journal_entry).transpose()
+    }
+
+    pub async fn mark_journal_entry_undone(&self, id: i64) -> AppResult<()> {
+        sqlx::query("UPDATE operation_journal SET undone_at = ? WHERE id = ?")
+            .bind(unix_now())
+            .bind(id)
+            .execute(self.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Delete all but the newest entries of a repository, returning the
+    /// deleted ids so their anchored objects can be released
+    pub async fn prune_journal(&self, repo_path: &Path) -> AppResult<Vec<i64>> {
+        let ids = sqlx::query_scalar(
+            "DELETE FROM operation_journal WHERE repo_path = ?1 AND id NOT IN
+             (SELECT id FROM operation_journal WHERE repo_path = ?1 ORDER BY id DESC LIMIT ?2)
+             RETURNING id",
+        )
+        .bind(repo_path.to_string_lossy().to_string())
+        .bind(MAX_JOURNAL_ENTRIES)
+        .fetch_all(self.pool())
+        .await?;
+        Ok(ids)
+    }
+}
@@ -1,8 +1,64 @@
-// Database module for SQLite persistence
-// Will be implemented to store:
-// - Recent repositories
-// - User settings
-// - AI conversation history cache
-// - Repository metadata cache
-
-// TODO: Implement SQLite schema and operations
+//! SQLite persistence
+//!
+//! A single database file in the app data directory holds:
+//! - Recent repositories
+//! - Per-repository settings
+//! - AI response and repository metadata caches (schema only: no command
+//!   reads or writes them yet)
+//! - Operation journal (undo of destructive operations)
+//!
+//! The schema lives in `src-tauri/migrations` and is embedded at compile time;
+//! pending migrations run every time the database is opened.
+
+pub mod journal;
+pub mod recent_repos;
+pub mod repo_settings;
+
+use std::path::Path;
+use std::time::Duration;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+
+use crate::error::{AppError, AppResult};
+
+const DATABASE_FILE: &str = "gitsage.db";
+
+/// Connection pool to the application database
+#[derive(Clone)]
+pub struct Database {
+    pool: SqlitePool,
+}
+
+impl Database {
+    /// Open (creating if needed) the database in `app_data_dir` and run migrations
+    pub async fn open(app_data_dir: &Path) -> AppResult<Self> {
+        std::fs::create_dir_all(app_data_dir)?;
+        let path = app_data_dir.join(DATABASE_FILE);
+        let options = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(Duration::from_secs(5));
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await?;
+
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .map_err(|e| AppError::Database(e.into()))?;
+
+        Ok(Self { pool })
+    }
+
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+}
+
+/// Current time as unix seconds, the timestamp format used by cache tables
+fn unix_now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
@@ -0,0 +1,76 @@
+use std::path::{Path, PathBuf};
+
+use sqlx::Row;
+
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::persistence;
+use crate::state::RepoEntry;
+
+/// Number of recent repositories kept
+const MAX_RECENT_REPOS: i64 = 20;
+
+impl Database {
+    pub async fn recent_repos(&self) -> AppResult<Vec<RepoEntry>> {
+        let rows = sqlx::query(
+            "SELECT path, name, last_opened FROM recent_repos ORDER BY last_opened DESC LIMIT ?",
+        )
+        .bind(MAX_RECENT_REPOS)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let last_opened: String = row.get("last_opened");
+                let last_opened = chrono::DateTime::parse_from_rfc3339(&last_opened)
+                    .ok()?
+                    .with_timezone(&chrono::Utc);
+                Some(RepoEntry {
+                    path: PathBuf::from(row.get::<String, _>("path")),
+                    name: row.get("name"),
+                    last_opened,
+                })
+            })
+            .collect())
+    }
+
+    /// Record that a repository was opened and trim the list to its maximum size
+    pub async fn touch_recent_repo(&self, entry: &RepoEntry) -> AppResult<()> {
+        let mut tx = self.pool().begin().await?;
+        sqlx::query(
+            "INSERT INTO recent_repos (path, name, last_opened) VALUES (?, ?, ?)
+             ON CONFLICT(path) DO UPDATE SET name = excluded.name, last_opened = excluded.last_opened",
+        )
+        .bind(entry.path.to_string_lossy().to_string())
+        .bind(&entry.name)
+        .bind(entry.last_opened.to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "DELETE FROM recent_repos WHERE path NOT IN
+             (SELECT path FROM recent_repos ORDER BY last_opened DESC LIMIT ?)",
+        )
+        .bind(MAX_RECENT_REPOS)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// One-time import of the `recent_repos.json` file used before the database
+    /// existed. The file is renamed afterwards so the import never runs twice.
+    pub async fn import_legacy_recent_repos(&self, app_data_dir: &Path) -> AppResult<()> {
+        let Some(path) = persistence::legacy_recent_repos_path(app_data_dir) else {
+            return Ok(());
+        };
+
+        let repos = persistence::load_recent_repos(app_data_dir)?;
+        for entry in &repos {
+            self.touch_recent_repo(entry).await?;
+        }
+        std::fs::rename(&path, path.with_extension("json.bak"))?;
+        tracing::info!("Imported {} recent repos into the database", repos.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use std::path::Path;
+
+use crate::db::{unix_now, Database};
+use crate::error::AppResult;
+
+impl Database {
+    /// Raw settings JSON stored for a repository, if any
+    pub async fn repo_settings_json(&self, repo_path: &Path) -> AppResult<Option<String>> {
+        let settings = sqlx::query_scalar("SELECT settings FROM repo_settings WHERE repo_path = ?")
+            .bind(repo_path.to_string_lossy().to_string())
+            .fetch_optional(self.pool())
+            .await?;
+        Ok(settings)
+    }
+
+    pub async fn set_repo_settings_json(&self, repo_path: &Path, settings: &str) -> AppResult<()> {
+        sqlx::query(
+            "INSERT INTO repo_settings (repo_path, settings, updated_at) VALUES (?, ?, ?)
+             ON CONFLICT(repo_path) DO UPDATE SET settings = excluded.settings, updated_at = excluded.updated_at",
+        )
+        .bind(repo_path.to_string_lossy().to_string())
+        .bind(settings)
+        .bind(unix_now())
+        .execute(self.pool())
+        .await?;
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 use serde::Serialize;
 
+use crate::git::classify::classify_git_output;
+
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
     #[error("Git error: {0}")]
@@ -23,6 +25,12 @@ pub enum AppError {
     #[error("AI provider error: {0}")]
     AiProvider(String),
 
+    #[error("GitHub API error ({status}): {message}")]
+    GitHub { status: u16, message: String },
+
+    #[error("Not authenticated with {0}, connect your account first")]
+    Unauthenticated(String),
+
     #[error("Configuration error: {0}")]
     Config(String),
 
@@ -38,16 +46,220 @@ pub enum AppError {
     #[error("Invalid input: {0}")]
     InvalidInput(String),
 
+    #[error("The diff of {0} changed since it was shown; refresh and try again")]
+    StaleDiff(String),
+
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    #[error("Operation timed out after {0}s")]
+    Timeout(u64),
+
     #[error("{0}")]
     General(String),
 }
 
+/// Broad area an error belongs to, for choosing how the UI presents it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    Git,
+    Network,
+    Auth,
+    Io,
+    Storage,
+    Ai,
+    Config,
+    Input,
+    Operation,
+    Internal,
+}
+
+/// Stable, machine-readable error code. The frontend keys its translations
+/// on it, so existing codes must not be renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    // Git
+    MergeConflict,
+    UncommittedChanges,
+    NothingToCommit,
+    NotARepository,
+    NotFound,
+    AlreadyExists,
+    BranchNotFullyMerged,
+    Locked,
+    RepoNotFound,
+    RepoState,
+    StaleDiff,
+    GitFailed,
+    // Remotes and network
+    NonFastForward,
+    PushRejected,
+    RemoteNotFound,
+    NetworkUnreachable,
+    HttpFailed,
+    // Authentication
+    AuthenticationFailed,
+    Unauthenticated,
+    // Long-running operations
+    Cancelled,
+    Timeout,
+    // Everything else
+    Io,
+    Database,
+    AiProvider,
+    Config,
+    InvalidInput,
+    PathValidation,
+    Serialization,
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn category(self) -> ErrorCategory {
+        use ErrorCode::*;
+        match self {
+            MergeConflict | UncommittedChanges | NothingToCommit | NotARepository | NotFound
+            | AlreadyExists | BranchNotFullyMerged | Locked | RepoNotFound | RepoState
+            | StaleDiff | GitFailed => ErrorCategory::Git,
+            NonFastForward | PushRejected | RemoteNotFound | NetworkUnreachable | HttpFailed => {
+                ErrorCategory::Network
+            }
+            AuthenticationFailed | Unauthenticated => ErrorCategory::Auth,
+            Cancelled | Timeout => ErrorCategory::Operation,
+            Io => ErrorCategory::Io,
+            Database => ErrorCategory::Storage,
+            AiProvider => ErrorCategory::Ai,
+            Config => ErrorCategory::Config,
+            InvalidInput | PathValidation => ErrorCategory::Input,
+            Serialization | Internal => ErrorCategory::Internal,
+        }
+    }
+}
+
+/// A ref a push was refused for
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RejectedRef {
+    pub source: String,
+    pub destination: String,
+    /// e.g. "non-fast-forward", "fetch first", "protected branch hook declined"
+    pub reason: Option<String>,
+}
+
+/// Structured data extracted from an error, when there is any
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ErrorDetails {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicted_paths: Vec<String>,
+    /// Local changes an operation refused to overwrite
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overwritten_paths: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rejected_refs: Vec<RejectedRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+}
+
+impl ErrorDetails {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl AppError {
+    /// Error code and details, classifying git output where needed
+    pub fn classify(&self) -> (ErrorCode, ErrorDetails) {
+        let code = match self {
+            AppError::Git(e) => git2_code(e),
+            AppError::GitCli(output) => return classify_git_output(output),
+            AppError::Io(_) => ErrorCode::Io,
+            AppError::Database(_) => ErrorCode::Database,
+            AppError::Http(e) => {
+                let details = ErrorDetails {
+                    http_status: e.status().map(|s| s.as_u16()),
+                    ..Default::default()
+                };
+                let code = match e.status().map(|s| s.as_u16()) {
+                    Some(401) => ErrorCode::AuthenticationFailed,
+                    _ if e.is_connect() || e.is_timeout() => ErrorCode::NetworkUnreachable,
+                    _ => ErrorCode::HttpFailed,
+                };
+                return (code, details);
+            }
+            AppError::GitHub { status, .. } => {
+                let details = ErrorDetails {
+                    http_status: Some(*status),
+                    ..Default::default()
+                };
+                let code = match status {
+                    401 => ErrorCode::AuthenticationFailed,
+                    404 => ErrorCode::NotFound,
+                    _ => ErrorCode::HttpFailed,
+                };
+                return (code, details);
+            }
+            AppError::Serialization(_) => ErrorCode::Serialization,
+            AppError::AiProvider(_) => ErrorCode::AiProvider,
+            AppError::Unauthenticated(_) => ErrorCode::Unauthenticated,
+            AppError::Config(_) => ErrorCode::Config,
+            AppError::RepoNotFound(_) => ErrorCode::RepoNotFound,
+            AppError::PathValidation(_) => ErrorCode::PathValidation,
+            AppError::RepoState(_) => ErrorCode::RepoState,
+            AppError::InvalidInput(_) => ErrorCode::InvalidInput,
+            AppError::StaleDiff(_) => ErrorCode::StaleDiff,
+            AppError::Cancelled => ErrorCode::Cancelled,
+            AppError::Timeout(_) => ErrorCode::Timeout,
+            AppError::General(_) => ErrorCode::Internal,
+        };
+        (code, ErrorDetails::default())
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.classify().0
+    }
+}
+
+fn git2_code(e: &git2::Error) -> ErrorCode {
+    use git2::ErrorCode as C;
+    match e.code() {
+        C::Conflict | C::MergeConflict => ErrorCode::MergeConflict,
+        C::NotFound => ErrorCode::NotFound,
+        C::Exists => ErrorCode::AlreadyExists,
+        C::NotFastForward => ErrorCode::NonFastForward,
+        C::Locked => ErrorCode::Locked,
+        C::Uncommitted | C::Modified => ErrorCode::UncommittedChanges,
+        C::Auth | C::Certificate => ErrorCode::AuthenticationFailed,
+        _ => match e.class() {
+            git2::ErrorClass::Net | git2::ErrorClass::Http | git2::ErrorClass::Ssh => {
+                ErrorCode::NetworkUnreachable
+            }
+            _ => ErrorCode::GitFailed,
+        },
+    }
+}
+
+/// Errors reach the frontend as
+/// `{ code, category, message, details? }`: `code` is what the UI branches
+/// on and translates, `message` is the English text for logs and fallback.
 impl Serialize for AppError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        use serde::ser::SerializeStruct;
+
+        let (code, details) = self.classify();
+        let mut s = serializer.serialize_struct("AppError", 4)?;
+        s.serialize_field("code", &code)?;
+        s.serialize_field("category", &code.category())?;
+        s.serialize_field("message", &self.to_string())?;
+        if details.is_empty() {
+            s.skip_field("details")?;
+        } else {
+            s.serialize_field("details", &details)?;
+        }
+        s.end()
     }
 }
 
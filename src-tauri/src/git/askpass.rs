@@ -0,0 +1,672 @@
+//! Askpass bridge: answers git's credential and SSH prompts from the UI
+//!
+//! git runs with `GIT_TERMINAL_PROMPT=0`, so it never waits on a terminal.
+//! Instead the app binary registers itself as `GIT_ASKPASS`, `SSH_ASKPASS`
+//! and as a credential helper. When git or ssh runs it, the helper process
+//! connects back to the app over a loopback socket, the app emits
+//! `git:credential-request`, and the answer given through
+//! `respond_credential_request` is handed back to git.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::{BufRead, Write};
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, OnceLock};
+use std::time::{Duration, Instant};
+
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::process::Command;
+use tokio::sync::{oneshot, Mutex};
+use tracing::{error, warn};
+
+use crate::error::{AppError, AppResult};
+use crate::git::credentials::{self, HostCredential};
+use crate::state::AppState;
+
+const PORT_ENV: &str = "GITSAGE_ASKPASS_PORT";
+const SECRET_ENV: &str = "GITSAGE_ASKPASS_SECRET";
+/// First argument when the binary runs as a credential helper
+const CREDENTIAL_HELPER_ARG: &str = "credential";
+/// How long a prompt waits for an answer before it counts as cancelled
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(300);
+/// A saved passphrase asked for again this soon by the same ssh process was
+/// rejected
+const RETRY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Payload of `git:credential-request`
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialRequest {
+    pub request_id: String,
+    pub kind: CredentialKind,
+    /// The prompt as git or ssh wrote it
+    pub prompt: String,
+    /// Remote URL or host, or the key file for passphrases
+    pub target: Option<String>,
+    /// Username to prefill, when git already knows it
+    pub username: Option<String>,
+}
+
+/// Payload of `git:credential-request-closed`, sent when a request ends
+/// without an answer (timeout or the git process went away)
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialRequestClosed {
+    pub request_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialKind {
+    /// Username and password for an HTTPS remote
+    UserPassword,
+    Username,
+    Password,
+    /// Passphrase of an SSH key
+    Passphrase,
+    /// Yes/no question, e.g. accepting an unknown SSH host key
+    Confirm,
+}
+
+/// Answer to a `CredentialRequest`
+#[derive(Debug, Clone, Deserialize)]
+pub struct CredentialResponse {
+    pub username: Option<String>,
+    /// Password, passphrase, or "yes"/"no" for confirmations
+    pub secret: String,
+    /// Save in the keyring once git accepts it
+    #[serde(default)]
+    pub remember: bool,
+}
+
+/// What git commands need to reach the bridge
+struct HelperEnv {
+    exe: PathBuf,
+    port: u16,
+    secret: String,
+}
+
+static HELPER_ENV: OnceLock<HelperEnv> = OnceLock::new();
+
+/// Keep a git command off the terminal and, once the bridge runs, route its
+/// prompts to the app
+pub(crate) fn configure(cmd: &mut Command) {
+    cmd.env("GIT_TERMINAL_PROMPT", "0");
+    let Some(env) = HELPER_ENV.get() else {
+        return;
+    };
+    // Appended after the user's own helpers, which are asked first
+    let helper = format!(
+        "credential.helper=!\"{}\" {}",
+        env.exe.display(),
+        CREDENTIAL_HELPER_ARG
+    );
+    cmd.arg("-c")
+        .arg(helper)
+        .env("GIT_ASKPASS", &env.exe)
+        .env("SSH_ASKPASS", &env.exe)
+        .env("SSH_ASKPASS_REQUIRE", "force")
+        .env(PORT_ENV, env.port.to_string())
+        .env(SECRET_ENV, &env.secret);
+}
+
+// ---------------------------------------------------------------------------
+// Helper process <-> app protocol: one JSON line each way
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize, Deserialize)]
+struct HelperMessage {
+    secret: String,
+    request: HelperRequest,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum HelperRequest {
+    /// `GIT_ASKPASS` / `SSH_ASKPASS` invocation
+    Prompt {
+        prompt: String,
+        /// Process that ran the helper (ssh for passphrases), where known
+        #[serde(default)]
+        parent_pid: Option<u32>,
+    },
+    /// Credential helper actions
+    Get(CredentialFields),
+    Store(CredentialFields),
+    Erase(CredentialFields),
+}
+
+/// Attributes git passes to credential helpers
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CredentialFields {
+    protocol: Option<String>,
+    host: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl CredentialFields {
+    fn parse(input: impl BufRead) -> Self {
+        let mut fields = Self::default();
+        for line in input.lines().map_while(Result::ok) {
+            if line.is_empty() {
+                break;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = Some(value.to_string());
+            match key {
+                "protocol" => fields.protocol = value,
+                "host" => fields.host = value,
+                "username" => fields.username = value,
+                "password" => fields.password = value,
+                _ => {}
+            }
+        }
+        fields
+    }
+
+    /// `(protocol, host)`, which credentials are saved under
+    fn remote(&self) -> Option<(&str, &str)> {
+        Some((self.protocol.as_deref()?, self.host.as_deref()?))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum HelperReply {
+    Answer { value: String },
+    Credential { username: String, password: String },
+    /// The user declined; git should stop asking
+    Cancel,
+    /// Nothing to say, e.g. no saved credential
+    Empty,
+}
+
+// ---------------------------------------------------------------------------
+// Helper side, running in a short-lived process spawned by git or ssh
+// ---------------------------------------------------------------------------
+
+/// Run as askpass or credential helper if git started this process as one.
+///
+/// Returns the exit code, or `None` for a normal app launch. Called first
+/// thing in `main`.
+pub fn run_helper() -> Option<i32> {
+    let port: u16 = std::env::var(PORT_ENV).ok()?.parse().ok()?;
+    let secret = std::env::var(SECRET_ENV).ok()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let request = match args.as_slice() {
+        [mode, action] if mode == CREDENTIAL_HELPER_ARG => {
+            let fields = CredentialFields::parse(std::io::stdin().lock());
+            match action.as_str() {
+                "get" => HelperRequest::Get(fields),
+                "store" => HelperRequest::Store(fields),
+                "erase" => HelperRequest::Erase(fields),
+                // Helpers must ignore actions they don't know
+                _ => return Some(0),
+            }
+        }
+        [prompt] => HelperRequest::Prompt {
+            prompt: prompt.clone(),
+            parent_pid: parent_pid(),
+        },
+        _ => return Some(1),
+    };
+    let is_prompt = matches!(request, HelperRequest::Prompt { .. });
+
+    let reply = match exchange(port, &HelperMessage { secret, request }) {
+        Ok(reply) => reply,
+        Err(e) => {
+            eprintln!("gitsage: askpass bridge unavailable: {}", e);
+            return Some(1);
+        }
+    };
+
+    let mut out = std::io::stdout().lock();
+    let written = match reply {
+        HelperReply::Answer { value } => writeln!(out, "{}", value),
+        HelperReply::Credential { username, password } => {
+            if !is_protocol_value(&username) || !is_protocol_value(&password) {
+                eprintln!("gitsage: credentials must not contain line breaks");
+                return Some(1);
+            }
+            writeln!(out, "username={}\npassword={}", username, password)
+        }
+        // Askpass signals a declined prompt through its exit code
+        HelperReply::Cancel | HelperReply::Empty if is_prompt => return Some(1),
+        HelperReply::Cancel => writeln!(out, "quit=1"),
+        HelperReply::Empty => Ok(()),
+    };
+    Some(if written.is_ok() { 0 } else { 1 })
+}
+
+/// Whether a value can be written as one `key=value` line of the credential
+/// protocol without adding lines of its own
+fn is_protocol_value(value: &str) -> bool {
+    !value.contains(['\n', '\r', '\0'])
+}
+
+#[cfg(unix)]
+fn parent_pid() -> Option<u32> {
+    Some(std::os::unix::process::parent_id())
+}
+
+#[cfg(not(unix))]
+fn parent_pid() -> Option<u32> {
+    None
+}
+
+fn exchange(port: u16, message: &HelperMessage) -> std::io::Result<HelperReply> {
+    let mut stream = std::net::TcpStream::connect((Ipv4Addr::LOCALHOST, port))?;
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut reply = String::new();
+    std::io::BufReader::new(stream).read_line(&mut reply)?;
+    Ok(serde_json::from_str(&reply)?)
+}
+
+// ---------------------------------------------------------------------------
+// App side
+// ---------------------------------------------------------------------------
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// `Username for 'https://github.com': `, `Password for 'https://me@github.com': `,
+/// `Enter passphrase for key '/home/me/.ssh/id_ed25519': `
+static PROMPT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:(?P<user>Username)|(?P<pass>Password)|Enter (?P<key>passphrase)) for (?:key )?'(?P<target>.+)'")
+        .expect("prompt regex must compile")
+});
+
+fn parse_prompt(prompt: &str) -> (CredentialKind, Option<String>) {
+    if let Some(caps) = PROMPT.captures(prompt.trim()) {
+        let kind = if caps.name("user").is_some() {
+            CredentialKind::Username
+        } else if caps.name("pass").is_some() {
+            CredentialKind::Password
+        } else {
+            CredentialKind::Passphrase
+        };
+        return (kind, Some(caps["target"].to_string()));
+    }
+    if prompt.contains("(yes/no") {
+        return (CredentialKind::Confirm, None);
+    }
+    // Anything else (ssh passwords, PINs) is answered like a password
+    (CredentialKind::Password, None)
+}
+
+/// A saved passphrase handed to ssh without asking
+struct AutoAnswer {
+    at: Instant,
+    parent_pid: Option<u32>,
+}
+
+impl AutoAnswer {
+    fn is_recent(&self) -> bool {
+        self.at.elapsed() < RETRY_WINDOW
+    }
+
+    /// Whether a new prompt for the key from `parent_pid` means ssh rejected
+    /// this answer. ssh only retries within one process; another process
+    /// asking (the push after a pull, the next submodule) is a new connection.
+    fn rejected_by(&self, parent_pid: Option<u32>) -> bool {
+        self.is_recent() && parent_pid.is_some() && parent_pid == self.parent_pid
+    }
+}
+
+struct Bridge {
+    app: AppHandle,
+    secret: String,
+    /// Credentials entered with "remember", saved once git reports them accepted
+    entered: Mutex<HashMap<String, HostCredential>>,
+    /// Keys whose saved passphrase was last answered without asking
+    auto_answered: Mutex<HashMap<String, AutoAnswer>>,
+}
+
+/// Start the bridge; git commands run afterwards send their prompts to it
+pub async fn start(app: AppHandle) -> std::io::Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+    let env = HelperEnv {
+        exe: std::env::current_exe()?,
+        port: listener.local_addr()?.port(),
+        secret: secret.clone(),
+    };
+    if HELPER_ENV.set(env).is_err() {
+        return Ok(());
+    }
+
+    let bridge = Arc::new(Bridge {
+        app,
+        secret,
+        entered: Mutex::new(HashMap::new()),
+        auto_answered: Mutex::new(HashMap::new()),
+    });
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    error!("Askpass bridge stopped: {}", e);
+                    break;
+                }
+            };
+            let bridge = bridge.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = bridge.serve(stream).await {
+                    warn!("Askpass request failed: {}", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+impl Bridge {
+    async fn serve(&self, stream: tokio::net::TcpStream) -> AppResult<()> {
+        let (read, mut write) = stream.into_split();
+        let mut reader = BufReader::new(read);
+        let mut line = String::new();
+        reader.read_line(&mut line).await?;
+
+        let message: HelperMessage = serde_json::from_str(&line)?;
+        if message.secret != self.secret {
+            return Err(AppError::InvalidInput(
+                "Askpass request with a wrong secret".into(),
+            ));
+        }
+
+        // The helper sends nothing more, so EOF means git or ssh went away
+        let closed = async move {
+            let mut buf = [0u8; 1];
+            let _ = reader.read(&mut buf).await;
+        };
+        let reply = match message.request {
+            HelperRequest::Prompt { prompt, parent_pid } => {
+                self.prompt(prompt, parent_pid, closed).await
+            }
+            HelperRequest::Get(fields) => self.get(fields, closed).await,
+            HelperRequest::Store(fields) => self.store(fields).await,
+            HelperRequest::Erase(fields) => self.erase(fields).await,
+        };
+
+        let mut json = serde_json::to_string(&reply)?;
+        json.push('\n');
+        write.write_all(json.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Ask the frontend and wait for its answer; `None` if it declined, did
+    /// not answer in time, or the asking process exited
+    async fn ask(
+        &self,
+        kind: CredentialKind,
+        prompt: String,
+        target: Option<String>,
+        username: Option<String>,
+        closed: impl Future<Output = ()>,
+    ) -> Option<CredentialResponse> {
+        let state = self.app.state::<AppState>();
+        let request_id = format!(
+            "credential-{}",
+            NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+        );
+        let (tx, rx) = oneshot::channel();
+        state
+            .credential_requests
+            .lock()
+            .await
+            .insert(request_id.clone(), tx);
+
+        let request = CredentialRequest {
+            request_id: request_id.clone(),
+            kind,
+            prompt,
+            target,
+            username,
+        };
+        if let Err(e) = self.app.emit("git:credential-request", request) {
+            error!("Failed to emit git:credential-request event: {}", e);
+        }
+
+        let answer = tokio::select! {
+            answer = tokio::time::timeout(PROMPT_TIMEOUT, rx) => answer.ok().and_then(Result::ok),
+            () = closed => None,
+        };
+        // Still registered when nobody answered
+        if state
+            .credential_requests
+            .lock()
+            .await
+            .remove(&request_id)
+            .is_some()
+        {
+            let closed = CredentialRequestClosed { request_id };
+            if let Err(e) = self.app.emit("git:credential-request-closed", closed) {
+                error!("Failed to emit git:credential-request-closed event: {}", e);
+            }
+        }
+        answer.flatten()
+    }
+
+    async fn prompt(
+        &self,
+        prompt: String,
+        parent_pid: Option<u32>,
+        closed: impl Future<Output = ()>,
+    ) -> HelperReply {
+        let (kind, target) = parse_prompt(&prompt);
+
+        if let (CredentialKind::Passphrase, Some(key)) = (kind, target.as_deref()) {
+            if let Some(passphrase) = self.saved_passphrase(key, parent_pid).await {
+                return HelperReply::Answer { value: passphrase };
+            }
+        }
+
+        let Some(response) = self.ask(kind, prompt, target.clone(), None, closed).await else {
+            return HelperReply::Cancel;
+        };
+        if let (CredentialKind::Passphrase, Some(key), true) = (kind, &target, response.remember) {
+            if let Err(e) = credentials::store_passphrase(key, &response.secret) {
+                warn!("Failed to save passphrase: {}", e);
+            }
+        }
+        let value = match kind {
+            CredentialKind::Username => response.username.unwrap_or(response.secret),
+            _ => response.secret,
+        };
+        HelperReply::Answer { value }
+    }
+
+    /// The saved passphrase for a key, unless the same ssh process is asking
+    /// again right after it was used, in which case it was wrong and is dropped
+    async fn saved_passphrase(&self, key: &str, parent_pid: Option<u32>) -> Option<String> {
+        let mut auto_answered = self.auto_answered.lock().await;
+        if let Some(previous) = auto_answered.remove(key) {
+            if previous.rejected_by(parent_pid) {
+                if let Err(e) = credentials::delete_passphrase(key) {
+                    warn!("Failed to delete passphrase: {}", e);
+                }
+                return None;
+            }
+            // Without the process a retry looks like a new connection: ask
+            // the user rather than answer a wrong passphrase over and over
+            if parent_pid.is_none() && previous.is_recent() {
+                return None;
+            }
+        }
+        let passphrase = credentials::load_passphrase(key).ok().flatten()?;
+        let answer = AutoAnswer {
+            at: Instant::now(),
+            parent_pid,
+        };
+        auto_answered.insert(key.to_string(), answer);
+        Some(passphrase)
+    }
+
+    async fn get(&self, fields: CredentialFields, closed: impl Future<Output = ()>) -> HelperReply {
+        let Some((protocol, host)) = fields.remote() else {
+            return HelperReply::Empty;
+        };
+
+        if let Ok(Some(saved)) = credentials::load_host_credential(protocol, host) {
+            if fields.username.as_ref().is_none_or(|u| *u == saved.username) {
+                return HelperReply::Credential {
+                    username: saved.username,
+                    password: saved.password,
+                };
+            }
+        }
+
+        let url = format!("{}://{}", protocol, host);
+        let prompt = format!("Credentials for {}", url);
+        let ask = self.ask(
+            CredentialKind::UserPassword,
+            prompt,
+            Some(url.clone()),
+            fields.username.clone(),
+            closed,
+        );
+        let Some(response) = ask.await else {
+            return HelperReply::Cancel;
+        };
+
+        let credential = HostCredential {
+            username: response.username.or(fields.username).unwrap_or_default(),
+            password: response.secret,
+        };
+        if !is_protocol_value(&credential.username) || !is_protocol_value(&credential.password) {
+            warn!("Credential for {} contains a line break, not passed to git", url);
+            return HelperReply::Cancel;
+        }
+        if response.remember {
+            self.entered.lock().await.insert(url, credential.clone());
+        }
+        HelperReply::Credential {
+            username: credential.username,
+            password: credential.password,
+        }
+    }
+
+    /// git accepted a credential: save it if it was entered with "remember"
+    async fn store(&self, fields: CredentialFields) -> HelperReply {
+        let Some((protocol, host)) = fields.remote() else {
+            return HelperReply::Empty;
+        };
+        let url = format!("{}://{}", protocol, host);
+        let Some(entered) = self.entered.lock().await.remove(&url) else {
+            return HelperReply::Empty;
+        };
+        let accepted = fields.username.as_deref() == Some(entered.username.as_str())
+            && fields.password.as_deref() == Some(entered.password.as_str());
+        if accepted {
+            if let Err(e) = credentials::store_host_credential(protocol, host, &entered) {
+                warn!("Failed to save credential for {}: {}", url, e);
+            }
+        }
+        HelperReply::Empty
+    }
+
+    /// git rejected a credential: forget it
+    async fn erase(&self, fields: CredentialFields) -> HelperReply {
+        let Some((protocol, host)) = fields.remote() else {
+            return HelperReply::Empty;
+        };
+        self.entered
+            .lock()
+            .await
+            .remove(&format!("{}://{}", protocol, host));
+
+        if let Ok(Some(saved)) = credentials::load_host_credential(protocol, host) {
+            if fields.username.as_ref().is_none_or(|u| *u == saved.username) {
+                if let Err(e) = credentials::delete_host_credential(protocol, host) {
+                    warn!("Failed to delete credential for {}: {}", host, e);
+                }
+            }
+        }
+        HelperReply::Empty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn credential_fields() {
+        let input = "protocol=https\nhost=github.com\nusername=me\npath=a/b\n\nhost=ignored\n";
+        let fields = CredentialFields::parse(input.as_bytes());
+        assert_eq!(fields.remote(), Some(("https", "github.com")));
+        assert_eq!(fields.username.as_deref(), Some("me"));
+        assert_eq!(fields.password, None);
+        assert_eq!(CredentialFields::parse("host=x\n".as_bytes()).remote(), None);
+    }
+
+    #[test]
+    fn protocol_values() {
+        assert!(is_protocol_value("p@ss=word with spaces"));
+        assert!(!is_protocol_value("secret\nhost=evil.example.com"));
+        assert!(!is_protocol_value("secret\r"));
+        assert!(!is_protocol_value("a\0b"));
+    }
+
+    #[test]
+    fn prompts() {
+        assert_eq!(
+            parse_prompt("Username for 'https://github.com': "),
+            (CredentialKind::Username, Some("https://github.com".into()))
+        );
+        assert_eq!(
+            parse_prompt("Password for 'https://me@github.com': "),
+            (CredentialKind::Password, Some("https://me@github.com".into()))
+        );
+        assert_eq!(
+            parse_prompt("Enter passphrase for key '/home/me/.ssh/id_ed25519': "),
+            (CredentialKind::Passphrase, Some("/home/me/.ssh/id_ed25519".into()))
+        );
+        assert_eq!(
+            parse_prompt("Are you sure you want to continue connecting (yes/no/[fingerprint])? "),
+            (CredentialKind::Confirm, None)
+        );
+        assert_eq!(parse_prompt("Enter PIN for authenticator: "), (CredentialKind::Password, None));
+    }
+
+    #[test]
+    fn only_the_same_ssh_process_rejects_a_passphrase() {
+        let answer = AutoAnswer {
+            at: Instant::now(),
+            parent_pid: Some(100),
+        };
+        assert!(answer.rejected_by(Some(100)));
+        // The push of a sync, or the next submodule
+        assert!(!answer.rejected_by(Some(101)));
+        assert!(!answer.rejected_by(None));
+
+        let old = AutoAnswer {
+            at: Instant::now() - RETRY_WINDOW - Duration::from_secs(1),
+            parent_pid: Some(100),
+        };
+        assert!(!old.rejected_by(Some(100)));
+
+        let unknown = AutoAnswer {
+            at: Instant::now(),
+            parent_pid: None,
+        };
+        assert!(!unknown.rejected_by(None));
+    }
+}
@@ -0,0 +1,292 @@
+//! Classification of git CLI failures
+//!
+//! git reports most failures only as human-readable text. The patterns below
+//! cover the failures the UI reacts to; anything else stays `GitFailed` and
+//! the raw text is shown as-is.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::error::{ErrorCode, ErrorDetails, RejectedRef};
+
+/// `CONFLICT (content): Merge conflict in src/main.rs`,
+/// `CONFLICT (modify/delete): a.txt deleted in HEAD and modified in topic.`
+static CONFLICT_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^CONFLICT \([^)]*\): (?:Merge conflict in (?P<path>.+?)|(?P<first>\S+) .*)\s*$")
+        .expect("conflict regex must compile")
+});
+
+/// ` ! [rejected]        main -> main (non-fast-forward)`,
+/// ` ! [remote rejected] main -> main (protected branch hook declined)`
+static REJECTED_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^\s*! \[(?:remote )?rejected\]\s+(?P<source>\S+) -> (?P<destination>\S+)(?: \((?P<reason>[^)]*)\))?")
+        .expect("rejected ref regex must compile")
+});
+
+const AUTH_PATTERNS: &[&str] = &[
+    "authentication failed",
+    "could not read username",
+    "could not read password",
+    "permission denied (publickey",
+    "terminal prompts disabled",
+    "invalid username or password",
+    "http basic: access denied",
+    "the requested url returned error: 401",
+    "the requested url returned error: 403",
+];
+
+const REMOTE_NOT_FOUND_PATTERNS: &[&str] = &[
+    "repository not found",
+    "does not appear to be a git repository",
+    "no such remote",
+    "the requested url returned error: 404",
+];
+
+const NETWORK_PATTERNS: &[&str] = &[
+    "could not resolve host",
+    "could not resolve hostname",
+    "connection refused",
+    "connection timed out",
+    "operation timed out",
+    "network is unreachable",
+    "the remote end hung up unexpectedly",
+    "could not read from remote repository",
+    "unable to access",
+];
+
+const UNCOMMITTED_PATTERNS: &[&str] = &[
+    "would be overwritten by",
+    "your local changes to the following files",
+    "please commit your changes or stash them",
+    "you have unstaged changes",
+    "your index contains uncommitted changes",
+    "contains modified or untracked files",
+];
+
+const NOT_FOUND_PATTERNS: &[&str] = &[
+    "did not match any",
+    "unknown revision",
+    "couldn't find remote ref",
+    "not a valid object name",
+    "invalid reference",
+    "bad revision",
+    "not found",
+];
+
+/// Map the output of a failed git command to an error code and the details
+/// that can be extracted from it
+pub fn classify_git_output(output: &str) -> (ErrorCode, ErrorDetails) {
+    let lower = output.to_lowercase();
+    let contains_any = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+    let mut details = ErrorDetails::default();
+
+    let code = if output.contains("CONFLICT (")
+        || lower.contains("automatic merge failed")
+        || lower.contains("could not apply")
+        || lower.contains("after resolving the conflicts")
+    {
+        details.conflicted_paths = conflicted_paths(output);
+        ErrorCode::MergeConflict
+    } else if REJECTED_LINE.is_match(output) || lower.contains("updates were rejected") {
+        details.rejected_refs = rejected_refs(output);
+        let non_fast_forward = lower.contains("non-fast-forward")
+            || lower.contains("fetch first")
+            || lower.contains("tip of your current branch is behind");
+        if non_fast_forward {
+            ErrorCode::NonFastForward
+        } else {
+            ErrorCode::PushRejected
+        }
+    } else if lower.contains("not possible to fast-forward") {
+        ErrorCode::NonFastForward
+    } else if contains_any(AUTH_PATTERNS) {
+        ErrorCode::AuthenticationFailed
+    } else if contains_any(REMOTE_NOT_FOUND_PATTERNS) {
+        ErrorCode::RemoteNotFound
+    } else if contains_any(NETWORK_PATTERNS) {
+        ErrorCode::NetworkUnreachable
+    } else if contains_any(UNCOMMITTED_PATTERNS) {
+        details.overwritten_paths = indented_paths(output);
+        ErrorCode::UncommittedChanges
+    } else if lower.contains("nothing to commit") || lower.contains("no changes added to commit")
+    {
+        ErrorCode::NothingToCommit
+    } else if lower.contains("not a git repository") {
+        ErrorCode::NotARepository
+    } else if lower.contains(".lock': file exists") || lower.contains("another git process") {
+        ErrorCode::Locked
+    } else if lower.contains("is not fully merged") {
+        ErrorCode::BranchNotFullyMerged
+    } else if lower.contains("already exists") {
+        ErrorCode::AlreadyExists
+    } else if contains_any(NOT_FOUND_PATTERNS) {
+        ErrorCode::NotFound
+    } else {
+        ErrorCode::GitFailed
+    };
+    (code, details)
+}
+
+fn conflicted_paths(output: &str) -> Vec<String> {
+    let mut paths: Vec<String> = CONFLICT_LINE
+        .captures_iter(output)
+        .filter_map(|caps| caps.name("path").or_else(|| caps.name("first")))
+        .map(|m| m.as_str().to_string())
+        .collect();
+    paths.dedup();
+    paths
+}
+
+fn rejected_refs(output: &str) -> Vec<RejectedRef> {
+    REJECTED_LINE
+        .captures_iter(output)
+        .map(|caps| RejectedRef {
+            source: caps["source"].to_string(),
+            destination: caps["destination"].to_string(),
+            reason: caps.name("reason").map(|m| m.as_str().to_string()),
+        })
+        .collect()
+}
+
+/// Files git lists under "would be overwritten by ...", one per tab-indented line
+fn indented_paths(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| line.starts_with('\t'))
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_of(output: &str) -> ErrorCode {
+        classify_git_output(output).0
+    }
+
+    #[test]
+    fn merge_conflicts_with_paths() {
+        let output = "Auto-merging src/main.rs\n\
+            CONFLICT (content): Merge conflict in src/main.rs\n\
+            CONFLICT (modify/delete): a.txt deleted in HEAD and modified in topic.\n\
+            Automatic merge failed; fix conflicts and then commit the result.\n";
+        let (code, details) = classify_git_output(output);
+        assert_eq!(code, ErrorCode::MergeConflict);
+        assert_eq!(details.conflicted_paths, ["src/main.rs", "a.txt"]);
+
+        let rebase = "error: could not apply 1a2b3c4... Change things\n\
+            hint: Resolve all conflicts manually, mark them as resolved with\n";
+        assert_eq!(code_of(rebase), ErrorCode::MergeConflict);
+    }
+
+    #[test]
+    fn rejected_pushes() {
+        let output = "To github.com:owner/repo.git\n \
+            ! [rejected]        main -> main (non-fast-forward)\n\
+            error: failed to push some refs to 'github.com:owner/repo.git'\n\
+            hint: Updates were rejected because the tip of your current branch is behind\n";
+        let (code, details) = classify_git_output(output);
+        assert_eq!(code, ErrorCode::NonFastForward);
+        assert_eq!(
+            details.rejected_refs,
+            [RejectedRef {
+                source: "main".to_string(),
+                destination: "main".to_string(),
+                reason: Some("non-fast-forward".to_string()),
+            }]
+        );
+
+        let output = " ! [remote rejected] main -> main (protected branch hook declined)\n";
+        let (code, details) = classify_git_output(output);
+        assert_eq!(code, ErrorCode::PushRejected);
+        assert_eq!(
+            details.rejected_refs[0].reason.as_deref(),
+            Some("protected branch hook declined")
+        );
+
+        let pull = "fatal: Not possible to fast-forward, aborting.\n";
+        assert_eq!(code_of(pull), ErrorCode::NonFastForward);
+    }
+
+    #[test]
+    fn remote_failures() {
+        assert_eq!(
+            code_of("remote: Invalid username or password.\nfatal: Authentication failed for 'https://github.com/o/r.git/'"),
+            ErrorCode::AuthenticationFailed
+        );
+        assert_eq!(
+            code_of("git@github.com: Permission denied (publickey).\nfatal: Could not read from remote repository."),
+            ErrorCode::AuthenticationFailed
+        );
+        assert_eq!(
+            code_of("remote: Repository not found.\nfatal: repository 'https://github.com/o/r.git/' not found"),
+            ErrorCode::RemoteNotFound
+        );
+        assert_eq!(
+            code_of("fatal: 'upstream' does not appear to be a git repository"),
+            ErrorCode::RemoteNotFound
+        );
+        assert_eq!(
+            code_of("fatal: unable to access 'https://github.com/o/r.git/': Could not resolve host: github.com"),
+            ErrorCode::NetworkUnreachable
+        );
+        assert_eq!(
+            code_of("ssh: connect to host github.com port 22: Connection refused"),
+            ErrorCode::NetworkUnreachable
+        );
+    }
+
+    #[test]
+    fn local_changes_in_the_way() {
+        let output = "error: Your local changes to the following files would be overwritten by checkout:\n\
+            \tsrc/lib.rs\n\
+            \tREADME.md\n\
+            Please commit your changes or stash them before you switch branches.\n\
+            Aborting\n";
+        let (code, details) = classify_git_output(output);
+        assert_eq!(code, ErrorCode::UncommittedChanges);
+        assert_eq!(details.overwritten_paths, ["src/lib.rs", "README.md"]);
+    }
+
+    #[test]
+    fn repository_state() {
+        assert_eq!(
+            code_of("On branch main\nnothing to commit, working tree clean\n"),
+            ErrorCode::NothingToCommit
+        );
+        assert_eq!(
+            code_of("fatal: not a git repository (or any of the parent directories): .git"),
+            ErrorCode::NotARepository
+        );
+        assert_eq!(
+            code_of("fatal: Unable to create '/repo/.git/index.lock': File exists.\n\nAnother git process seems to be running"),
+            ErrorCode::Locked
+        );
+        assert_eq!(
+            code_of("error: The branch 'topic' is not fully merged."),
+            ErrorCode::BranchNotFullyMerged
+        );
+        assert_eq!(
+            code_of("fatal: a branch named 'topic' already exists"),
+            ErrorCode::AlreadyExists
+        );
+        assert_eq!(
+            code_of("error: pathspec 'nope' did not match any file(s) known to git"),
+            ErrorCode::NotFound
+        );
+        assert_eq!(
+            code_of("fatal: ambiguous argument 'nope': unknown revision or path not in the working tree."),
+            ErrorCode::NotFound
+        );
+    }
+
+    #[test]
+    fn unknown_output_is_generic() {
+        let (code, details) = classify_git_output("fatal: something unexpected happened");
+        assert_eq!(code, ErrorCode::GitFailed);
+        assert!(details.is_empty());
+    }
+}
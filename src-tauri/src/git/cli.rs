@@ -1,25 +1,141 @@
 use std::path::Path;
-use std::process::{Command, Stdio};
-use std::io::Write as IoWrite;
+use std::process::Stdio;
 
-use crate::error::{AppError, AppResult};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
 
-/// Execute a git CLI command and return stdout
-fn run_git(repo_path: &Path, args: &[&str]) -> AppResult<String> {
-    let output = Command::new("git")
-        .current_dir(repo_path)
-        .args(args)
+use crate::error::{AppError, AppResult};
+use crate::git::askpass;
+use crate::git::diff::DiffOptions;
+use crate::git::progress::{parse_progress_line, ProgressCallback};
+use crate::git::rebase::{shell_quote, FixupKind, HistoryRewrite};
+
+/// A `git` command.
+///
+/// The child is killed when the future driving it is dropped, which is how
+/// cancellation and timeouts (see `commands::operation`) stop a running git.
+/// It never prompts on a terminal; credential and passphrase prompts go to
+/// the UI through the askpass bridge.
+fn git() -> Command {
+    let mut cmd = Command::new("git");
+    cmd.stdin(Stdio::null()).kill_on_drop(true);
+    askpass::configure(&mut cmd);
+    cmd
+}
+
+/// A `git` command running in `dir`
+fn git_command(dir: &Path) -> Command {
+    let mut cmd = git();
+    cmd.current_dir(dir);
+    cmd
+}
+
+/// Error for a failed git command. Some failures, such as merge conflicts and
+/// "nothing to commit", are only described on stdout, so it is kept after stderr.
+fn command_failed(stdout: &[u8], stderr: &str) -> AppError {
+    let stdout = String::from_utf8_lossy(stdout);
+    let message = [stderr.trim(), stdout.trim()]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    AppError::GitCli(message)
+}
+
+/// Wait for a git command and return its stdout, or its output as the error
+async fn output_of(cmd: &mut Command) -> AppResult<String> {
+    let output = cmd
         .output()
+        .await
         .map_err(|e| AppError::GitCli(format!("Failed to execute git: {}", e)))?;
 
     if output.status.success() {
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
     } else {
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        Err(AppError::GitCli(stderr))
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(command_failed(&output.stdout, &stderr))
     }
 }
 
+/// Execute a git CLI command and return stdout
+async fn run_git(repo_path: &Path, args: &[&str]) -> AppResult<String> {
+    output_of(git_command(repo_path).args(args)).await
+}
+
+/// Like `output_of`, but for commands run with `--progress`: progress lines on
+/// stderr are parsed and reported as they arrive, everything else on stderr is
+/// kept for the error message
+async fn output_with_progress(
+    cmd: &mut Command,
+    on_progress: &ProgressCallback,
+) -> AppResult<String> {
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AppError::GitCli(format!("Failed to execute git: {}", e)))?;
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+
+    let read_stdout = async {
+        let mut buf = Vec::new();
+        stdout.read_to_end(&mut buf).await.map(|_| buf)
+    };
+    let read_stderr = async {
+        let mut messages = String::new();
+        let mut handle_line = |line: &[u8]| {
+            let line = String::from_utf8_lossy(line);
+            match parse_progress_line(&line) {
+                Some(progress) => on_progress(progress),
+                None if !line.trim().is_empty() => {
+                    messages.push_str(line.trim_end());
+                    messages.push('\n');
+                }
+                None => {}
+            }
+        };
+
+        // Progress is redrawn with `\r`, so both `\r` and `\n` end a line
+        let mut pending = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let n = stderr.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            pending.extend_from_slice(&chunk[..n]);
+            while let Some(end) = pending.iter().position(|&b| b == b'\r' || b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                handle_line(&line[..end]);
+            }
+        }
+        handle_line(&pending);
+        Ok::<_, std::io::Error>(messages)
+    };
+
+    let (stdout, messages) = tokio::try_join!(read_stdout, read_stderr)
+        .map_err(|e| AppError::GitCli(format!("Failed to read git output: {}", e)))?;
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| AppError::GitCli(format!("Failed to wait for git: {}", e)))?;
+
+    if status.success() {
+        Ok(String::from_utf8_lossy(&stdout).to_string())
+    } else {
+        Err(command_failed(&stdout, &messages))
+    }
+}
+
+/// Execute a git network command with `--progress` output reported to `on_progress`
+async fn run_git_progress(
+    repo_path: &Path,
+    args: &[&str],
+    on_progress: &ProgressCallback,
+) -> AppResult<String> {
+    output_with_progress(git_command(repo_path).args(args), on_progress).await
+}
+
 // ---------------------------------------------------------------------------
 // Existing functions
 // ---------------------------------------------------------------------------
@@ -29,7 +145,7 @@ pub async fn stage(repo_path: &Path, paths: &[String]) -> AppResult<()> {
     let path_refs: Vec<&str> = paths.iter().map(|s| s.as_str()).collect();
     let mut args = vec!["add", "--"];
     args.extend(path_refs);
-    run_git(repo_path, &args)?;
+    run_git(repo_path, &args).await?;
     Ok(())
 }
 
@@ -38,7 +154,7 @@ pub async fn unstage(repo_path: &Path, paths: &[String]) -> AppResult<()> {
     let path_refs: Vec<&str> = paths.iter().map(|s| s.as_str()).collect();
     let mut args = vec!["restore", "--staged", "--"];
     args.extend(path_refs);
-    run_git(repo_path, &args)?;
+    run_git(repo_path, &args).await?;
     Ok(())
 }
 
@@ -48,59 +164,75 @@ pub async fn commit(repo_path: &Path, message: &str, amend: bool) -> AppResult<S
     if amend {
         args.push("--amend");
     }
-    let output = run_git(repo_path, &args)?;
+    let output = run_git(repo_path, &args).await?;
     Ok(output)
 }
 
 /// Create a new branch
 pub async fn create_branch(repo_path: &Path, name: &str) -> AppResult<()> {
-    run_git(repo_path, &["branch", name])?;
+    run_git(repo_path, &["branch", name]).await?;
     Ok(())
 }
 
 /// Switch to a branch
 pub async fn checkout_branch(repo_path: &Path, name: &str) -> AppResult<()> {
-    run_git(repo_path, &["checkout", name])?;
+    run_git(repo_path, &["checkout", name]).await?;
     Ok(())
 }
 
 /// Delete a branch
 pub async fn delete_branch(repo_path: &Path, name: &str) -> AppResult<()> {
-    run_git(repo_path, &["branch", "-d", name])?;
+    run_git(repo_path, &["branch", "-d", name]).await?;
     Ok(())
 }
 
 /// Fetch from remote
-pub async fn fetch(repo_path: &Path, remote: &str) -> AppResult<()> {
-    run_git(repo_path, &["fetch", remote])?;
+pub async fn fetch(
+    repo_path: &Path,
+    remote: &str,
+    on_progress: &ProgressCallback,
+) -> AppResult<()> {
+    run_git_progress(repo_path, &["fetch", "--progress", remote], on_progress).await?;
     Ok(())
 }
 
 /// Pull from remote
-pub async fn pull(repo_path: &Path, remote: &str, branch: &str) -> AppResult<()> {
-    run_git(repo_path, &["pull", remote, branch])?;
+pub async fn pull(
+    repo_path: &Path,
+    remote: &str,
+    branch: &str,
+    on_progress: &ProgressCallback,
+) -> AppResult<()> {
+    run_git_progress(
+        repo_path,
+        &["pull", "--progress", remote, branch],
+        on_progress,
+    )
+    .await?;
     Ok(())
 }
 
 /// Push to remote
-pub async fn push(repo_path: &Path, remote: &str, branch: &str) -> AppResult<()> {
-    run_git(repo_path, &["push", remote, branch])?;
+pub async fn push(
+    repo_path: &Path,
+    remote: &str,
+    branch: &str,
+    on_progress: &ProgressCallback,
+) -> AppResult<()> {
+    run_git_progress(
+        repo_path,
+        &["push", "--progress", remote, branch],
+        on_progress,
+    )
+    .await?;
     Ok(())
 }
 
 /// Clone a repository
-pub async fn clone_repo(url: &str, dest: &Path) -> AppResult<()> {
-    let output = Command::new("git")
-        .args(["clone", url, &dest.to_string_lossy()])
-        .output()
-        .map_err(|e| AppError::GitCli(format!("Failed to execute git clone: {}", e)))?;
-
-    if output.status.success() {
-        Ok(())
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        Err(AppError::GitCli(stderr))
-    }
+pub async fn clone_repo(url: &str, dest: &Path, on_progress: &ProgressCallback) -> AppResult<()> {
+    let dest = dest.to_string_lossy();
+    output_with_progress(git().args(["clone", "--progress", url, &dest]), on_progress).await?;
+    Ok(())
 }
 
 // ---------------------------------------------------------------------------
@@ -115,30 +247,30 @@ pub async fn discard_files(repo_path: &Path, paths: &[String]) -> AppResult<()>
     let mut checkout_args = vec!["checkout", "--"];
     checkout_args.extend(path_refs.iter());
     // Ignore errors for checkout (file might be untracked)
-    let _ = run_git(repo_path, &checkout_args);
+    let _ = run_git(repo_path, &checkout_args).await;
 
     // Remove untracked files
     let mut clean_args = vec!["clean", "-f", "--"];
     clean_args.extend(path_refs.iter());
     // Ignore errors for clean (file might be tracked)
-    let _ = run_git(repo_path, &clean_args);
+    let _ = run_git(repo_path, &clean_args).await;
 
     Ok(())
 }
 
 /// Discard all changes (tracked and untracked)
 pub async fn discard_all(repo_path: &Path) -> AppResult<()> {
-    run_git(repo_path, &["checkout", "--", "."])?;
-    run_git(repo_path, &["clean", "-fd"])?;
+    run_git(repo_path, &["checkout", "--", "."]).await?;
+    run_git(repo_path, &["clean", "-fd"]).await?;
     Ok(())
 }
 
 /// Undo the last commit (soft: keep changes staged, mixed: keep changes unstaged)
 pub async fn undo_last_commit(repo_path: &Path, soft: bool) -> AppResult<()> {
     if soft {
-        run_git(repo_path, &["reset", "--soft", "HEAD~1"])?;
+        run_git(repo_path, &["reset", "--soft", "HEAD~1"]).await?;
     } else {
-        run_git(repo_path, &["reset", "HEAD~1"])?;
+        run_git(repo_path, &["reset", "HEAD~1"]).await?;
     }
     Ok(())
 }
@@ -157,7 +289,7 @@ pub async fn stash_push(
     if include_untracked {
         args.push("-u");
     }
-    run_git(repo_path, &args)?;
+    run_git(repo_path, &args).await?;
     Ok(())
 }
 
@@ -168,7 +300,7 @@ pub async fn stash_pop(repo_path: &Path, index: Option<usize>) -> AppResult<()>
     if let Some(ref r) = stash_ref {
         args.push(r.as_str());
     }
-    run_git(repo_path, &args)?;
+    run_git(repo_path, &args).await?;
     Ok(())
 }
 
@@ -179,7 +311,7 @@ pub async fn stash_apply(repo_path: &Path, index: Option<usize>) -> AppResult<()
     if let Some(ref r) = stash_ref {
         args.push(r.as_str());
     }
-    run_git(repo_path, &args)?;
+    run_git(repo_path, &args).await?;
     Ok(())
 }
 
@@ -190,13 +322,13 @@ pub async fn stash_drop(repo_path: &Path, index: Option<usize>) -> AppResult<()>
     if let Some(ref r) = stash_ref {
         args.push(r.as_str());
     }
-    run_git(repo_path, &args)?;
+    run_git(repo_path, &args).await?;
     Ok(())
 }
 
 /// Clear all stash entries
 pub async fn stash_clear(repo_path: &Path) -> AppResult<()> {
-    run_git(repo_path, &["stash", "clear"])?;
+    run_git(repo_path, &["stash", "clear"]).await?;
     Ok(())
 }
 
@@ -207,26 +339,26 @@ pub async fn merge(repo_path: &Path, branch: &str, no_ff: bool) -> AppResult<Str
         args.push("--no-ff");
     }
     args.push(branch);
-    let output = run_git(repo_path, &args)?;
+    let output = run_git(repo_path, &args).await?;
     Ok(output)
 }
 
 /// Abort an in-progress merge
 pub async fn merge_abort(repo_path: &Path) -> AppResult<()> {
-    run_git(repo_path, &["merge", "--abort"])?;
+    run_git(repo_path, &["merge", "--abort"]).await?;
     Ok(())
 }
 
 /// Continue an in-progress merge
 pub async fn merge_continue(repo_path: &Path) -> AppResult<()> {
-    run_git(repo_path, &["merge", "--continue"])?;
+    run_git(repo_path, &["merge", "--continue"]).await?;
     Ok(())
 }
 
 /// Reset the current HEAD to a specific commit
 pub async fn reset(repo_path: &Path, commit: &str, mode: &str) -> AppResult<()> {
     let mode_flag = format!("--{}", mode);
-    run_git(repo_path, &["reset", &mode_flag, commit])?;
+    run_git(repo_path, &["reset", &mode_flag, commit]).await?;
     Ok(())
 }
 
@@ -236,7 +368,7 @@ pub async fn reset(repo_path: &Path, commit: &str, mode: &str) -> AppResult<()>
 
 /// Rename a branch
 pub async fn rename_branch(repo_path: &Path, old_name: &str, new_name: &str) -> AppResult<()> {
-    run_git(repo_path, &["branch", "-m", old_name, new_name])?;
+    run_git(repo_path, &["branch", "-m", old_name, new_name]).await?;
     Ok(())
 }
 
@@ -257,128 +389,247 @@ pub async fn create_tag(
     if let Some(c) = commit {
         args.push(c);
     }
-    run_git(repo_path, &args)?;
+    run_git(repo_path, &args).await?;
     Ok(())
 }
 
 /// Delete a tag
 pub async fn delete_tag(repo_path: &Path, name: &str) -> AppResult<()> {
-    run_git(repo_path, &["tag", "-d", name])?;
+    run_git(repo_path, &["tag", "-d", name]).await?;
     Ok(())
 }
 
 /// Push a single tag to remote
-pub async fn push_tag(repo_path: &Path, remote: &str, tag: &str) -> AppResult<()> {
-    run_git(repo_path, &["push", remote, tag])?;
+pub async fn push_tag(
+    repo_path: &Path,
+    remote: &str,
+    tag: &str,
+    on_progress: &ProgressCallback,
+) -> AppResult<()> {
+    run_git_progress(repo_path, &["push", "--progress", remote, tag], on_progress).await?;
     Ok(())
 }
 
 /// Push all tags to remote
-pub async fn push_all_tags(repo_path: &Path, remote: &str) -> AppResult<()> {
-    run_git(repo_path, &["push", remote, "--tags"])?;
+pub async fn push_all_tags(
+    repo_path: &Path,
+    remote: &str,
+    on_progress: &ProgressCallback,
+) -> AppResult<()> {
+    run_git_progress(repo_path, &["push", "--progress", remote, "--tags"], on_progress).await?;
     Ok(())
 }
 
 /// Cherry-pick a commit
 pub async fn cherry_pick(repo_path: &Path, commit_id: &str) -> AppResult<()> {
-    run_git(repo_path, &["cherry-pick", commit_id])?;
+    run_git(repo_path, &["cherry-pick", commit_id]).await?;
     Ok(())
 }
 
 /// Abort an in-progress cherry-pick
 pub async fn cherry_pick_abort(repo_path: &Path) -> AppResult<()> {
-    run_git(repo_path, &["cherry-pick", "--abort"])?;
+    run_git(repo_path, &["cherry-pick", "--abort"]).await?;
     Ok(())
 }
 
 /// Continue an in-progress cherry-pick
 pub async fn cherry_pick_continue(repo_path: &Path) -> AppResult<()> {
-    run_git(repo_path, &["cherry-pick", "--continue"])?;
+    run_git(repo_path, &["cherry-pick", "--continue"]).await?;
     Ok(())
 }
 
 /// Revert a commit
 pub async fn revert(repo_path: &Path, commit_id: &str) -> AppResult<()> {
-    run_git(repo_path, &["revert", commit_id])?;
+    run_git(repo_path, &["revert", commit_id]).await?;
     Ok(())
 }
 
 /// Abort an in-progress revert
 pub async fn revert_abort(repo_path: &Path) -> AppResult<()> {
-    run_git(repo_path, &["revert", "--abort"])?;
+    run_git(repo_path, &["revert", "--abort"]).await?;
     Ok(())
 }
 
 /// Continue an in-progress revert
 pub async fn revert_continue(repo_path: &Path) -> AppResult<()> {
-    run_git(repo_path, &["revert", "--continue"])?;
+    run_git(repo_path, &["revert", "--continue"]).await?;
     Ok(())
 }
 
 /// Rebase onto a target branch or commit
 pub async fn rebase(repo_path: &Path, onto: &str) -> AppResult<()> {
-    run_git(repo_path, &["rebase", onto])?;
+    run_git(repo_path, &["rebase", onto]).await?;
     Ok(())
 }
 
 /// Abort an in-progress rebase
 pub async fn rebase_abort(repo_path: &Path) -> AppResult<()> {
-    run_git(repo_path, &["rebase", "--abort"])?;
+    run_git(repo_path, &["rebase", "--abort"]).await?;
     Ok(())
 }
 
-/// Continue an in-progress rebase
+/// Continue an in-progress rebase, keeping the messages git proposes
 pub async fn rebase_continue(repo_path: &Path) -> AppResult<()> {
-    run_git(repo_path, &["rebase", "--continue"])?;
+    output_of(git_command(repo_path).args(["rebase", "--continue"]).env("GIT_EDITOR", ":"))
+        .await?;
     Ok(())
 }
 
 /// Skip the current patch during a rebase
 pub async fn rebase_skip(repo_path: &Path) -> AppResult<()> {
-    run_git(repo_path, &["rebase", "--skip"])?;
+    run_git(repo_path, &["rebase", "--skip"]).await?;
+    Ok(())
+}
+
+/// Interactive rebase onto `base`, running the todo list at `todo_path`
+/// instead of opening an editor. Returns once the rebase finishes or stops at
+/// an `edit` step. Local changes are stashed around the rebase.
+pub async fn rebase_interactive(repo_path: &Path, base: &str, todo_path: &Path) -> AppResult<()> {
+    let sequence_editor = format!("cp {}", shell_quote(&todo_path.to_string_lossy()));
+    output_of(
+        git_command(repo_path)
+            // The plan is explicit, so config must not reorder or reject it
+            .args(["-c", "rebase.missingCommitsCheck=ignore"])
+            .args(["rebase", "--interactive", "--no-autosquash", "--autostash", base])
+            .env("GIT_SEQUENCE_EDITOR", sequence_editor)
+            .env("GIT_EDITOR", ":"),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Move HEAD back to `target`, leaving the changes since in the work tree.
+/// Files added since are kept as intent-to-add so they show up as changes
+/// rather than as untracked files.
+pub async fn reset_intent_to_add(repo_path: &Path, target: &str) -> AppResult<()> {
+    run_git(repo_path, &["reset", "--mixed", "-N", target]).await?;
+    Ok(())
+}
+
+/// Commit every change to tracked files, including intent-to-add ones
+pub async fn commit_all(repo_path: &Path, message: &str) -> AppResult<()> {
+    run_git(repo_path, &["commit", "--all", "-m", message]).await?;
+    Ok(())
+}
+
+/// Commit the staged changes as a `fixup!`, `squash!` or `amend!` commit for
+/// `target`, with `message` as its body. The subject names the target by id:
+/// `--fixup` would name it by subject, which autosquash matches against the
+/// oldest commit that has it.
+pub async fn commit_fixup(
+    repo_path: &Path,
+    kind: FixupKind,
+    target: &str,
+    message: Option<&str>,
+) -> AppResult<()> {
+    let prefix = match kind {
+        FixupKind::Fixup => "fixup!",
+        FixupKind::Squash => "squash!",
+        FixupKind::Amend => "amend!",
+    };
+    let mut full_message = format!("{} {}", prefix, target);
+    if let Some(message) = message {
+        full_message.push_str("\n\n");
+        full_message.push_str(message);
+    }
+    let mut args = vec!["commit", "-m", &full_message];
+    // An amend! may change nothing but the message
+    if kind == FixupKind::Amend {
+        args.push("--allow-empty");
+    }
+    output_of(git_command(repo_path).args(&args).env("GIT_EDITOR", ":")).await?;
+    Ok(())
+}
+
+/// Rebase onto `base`, or the root when `None`, melding `fixup!`, `squash!`
+/// and `amend!` commits into their targets. Local changes are stashed around
+/// the rebase.
+pub async fn rebase_autosquash(repo_path: &Path, base: Option<&str>) -> AppResult<()> {
+    output_of(
+        git_command(repo_path)
+            .args(["rebase", "--interactive", "--autosquash", "--autostash"])
+            .arg(base.unwrap_or("--root"))
+            .env("GIT_SEQUENCE_EDITOR", ":")
+            .env("GIT_EDITOR", ":"),
+    )
+    .await?;
     Ok(())
 }
 
 /// Pull with rebase strategy
-pub async fn pull_rebase(repo_path: &Path, remote: &str, branch: &str) -> AppResult<()> {
-    run_git(repo_path, &["pull", "--rebase", remote, branch])?;
+pub async fn pull_rebase(
+    repo_path: &Path,
+    remote: &str,
+    branch: &str,
+    on_progress: &ProgressCallback,
+) -> AppResult<()> {
+    let args = ["pull", "--rebase", "--progress", remote, branch];
+    run_git_progress(repo_path, &args, on_progress).await?;
+    Ok(())
+}
+
+/// Pull, refusing to create a merge commit
+pub async fn pull_ff_only(
+    repo_path: &Path,
+    remote: &str,
+    branch: &str,
+    on_progress: &ProgressCallback,
+) -> AppResult<()> {
+    let args = ["pull", "--ff-only", "--progress", remote, branch];
+    run_git_progress(repo_path, &args, on_progress).await?;
     Ok(())
 }
 
 /// Add a remote
 pub async fn add_remote(repo_path: &Path, name: &str, url: &str) -> AppResult<()> {
-    run_git(repo_path, &["remote", "add", name, url])?;
+    run_git(repo_path, &["remote", "add", name, url]).await?;
     Ok(())
 }
 
 /// Remove a remote
 pub async fn remove_remote(repo_path: &Path, name: &str) -> AppResult<()> {
-    run_git(repo_path, &["remote", "remove", name])?;
+    run_git(repo_path, &["remote", "remove", name]).await?;
     Ok(())
 }
 
 /// Rename a remote
 pub async fn rename_remote(repo_path: &Path, old: &str, new: &str) -> AppResult<()> {
-    run_git(repo_path, &["remote", "rename", old, new])?;
+    run_git(repo_path, &["remote", "rename", old, new]).await?;
     Ok(())
 }
 
 /// Fetch from remote with pruning of deleted remote branches
-pub async fn fetch_prune(repo_path: &Path, remote: &str) -> AppResult<()> {
-    run_git(repo_path, &["fetch", "--prune", remote])?;
+pub async fn fetch_prune(
+    repo_path: &Path,
+    remote: &str,
+    on_progress: &ProgressCallback,
+) -> AppResult<()> {
+    let args = ["fetch", "--prune", "--progress", remote];
+    run_git_progress(repo_path, &args, on_progress).await?;
     Ok(())
 }
 
 /// Push and set upstream tracking branch
-pub async fn push_set_upstream(repo_path: &Path, remote: &str, branch: &str) -> AppResult<()> {
-    run_git(repo_path, &["push", "-u", remote, branch])?;
+pub async fn push_set_upstream(
+    repo_path: &Path,
+    remote: &str,
+    branch: &str,
+    on_progress: &ProgressCallback,
+) -> AppResult<()> {
+    let args = ["push", "-u", "--progress", remote, branch];
+    run_git_progress(repo_path, &args, on_progress).await?;
     Ok(())
 }
 
 /// Sync: pull then push (convenience wrapper)
-pub async fn sync(repo_path: &Path, remote: &str, branch: &str) -> AppResult<()> {
-    run_git(repo_path, &["pull", remote, branch])?;
-    run_git(repo_path, &["push", remote, branch])?;
+pub async fn sync(
+    repo_path: &Path,
+    remote: &str,
+    branch: &str,
+    on_progress: &ProgressCallback,
+) -> AppResult<()> {
+    pull(repo_path, remote, branch, on_progress).await?;
+    push(repo_path, remote, branch, on_progress).await?;
     Ok(())
 }
 
@@ -393,7 +644,9 @@ pub async fn apply_patch(
     cached: bool,
     reverse: bool,
 ) -> AppResult<()> {
-    let mut args = vec!["apply"];
+    // Generated line patches only get approximate line counts, and CRLF
+    // files must not be rejected as whitespace errors
+    let mut args = vec!["apply", "--recount", "--whitespace=nowarn"];
     if cached {
         args.push("--cached");
     }
@@ -401,37 +654,42 @@ pub async fn apply_patch(
         args.push("--reverse");
     }
     args.push("-");
+    run_git_with_input(repo_path, &args, patch).await
+}
 
-    let mut child = Command::new("git")
-        .current_dir(repo_path)
-        .args(&args)
+/// Run a git command with `input` on its stdin
+async fn run_git_with_input(repo_path: &Path, args: &[&str], input: &str) -> AppResult<()> {
+    let mut child = git_command(repo_path)
+        .args(args)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
-        .map_err(|e| AppError::GitCli(format!("Failed to execute git apply: {}", e)))?;
+        .map_err(|e| AppError::GitCli(format!("Failed to execute git {}: {}", args[0], e)))?;
 
     if let Some(ref mut stdin) = child.stdin {
         stdin
-            .write_all(patch.as_bytes())
-            .map_err(|e| AppError::GitCli(format!("Failed to write patch to stdin: {}", e)))?;
+            .write_all(input.as_bytes())
+            .await
+            .map_err(|e| AppError::GitCli(format!("Failed to write to git stdin: {}", e)))?;
     }
 
     let output = child
         .wait_with_output()
-        .map_err(|e| AppError::GitCli(format!("Failed to wait for git apply: {}", e)))?;
+        .await
+        .map_err(|e| AppError::GitCli(format!("Failed to wait for git {}: {}", args[0], e)))?;
 
     if output.status.success() {
         Ok(())
     } else {
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        Err(AppError::GitCli(stderr))
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(command_failed(&output.stdout, &stderr))
     }
 }
 
 /// Mark a conflicted file as resolved by staging it
 pub async fn mark_resolved(repo_path: &Path, path: &str) -> AppResult<()> {
-    run_git(repo_path, &["add", path])?;
+    run_git(repo_path, &["add", path]).await?;
     Ok(())
 }
 
@@ -468,7 +726,7 @@ pub async fn write_merge_result(
     std::fs::write(&file_path, content).map_err(|e| {
         AppError::GitCli(format!("Failed to write merge result to {}: {}", path, e))
     })?;
-    run_git(repo_path, &["add", path])?;
+    run_git(repo_path, &["add", path]).await?;
     Ok(())
 }
 
@@ -479,6 +737,7 @@ pub async fn write_merge_result(
 /// Append a pattern to .gitignore
 pub async fn add_to_gitignore(repo_path: &Path, pattern: &str) -> AppResult<()> {
     use std::fs::OpenOptions;
+    use std::io::Write;
 
     let gitignore_path = repo_path.join(".gitignore");
 
@@ -513,40 +772,148 @@ pub async fn add_to_gitignore(repo_path: &Path, pattern: &str) -> AppResult<()>
 
 /// Initialize submodules
 pub async fn submodule_init(repo_path: &Path) -> AppResult<()> {
-    run_git(repo_path, &["submodule", "init"])?;
+    run_git(repo_path, &["submodule", "init"]).await?;
     Ok(())
 }
 
 /// Update submodules
-pub async fn submodule_update(repo_path: &Path, recursive: bool) -> AppResult<()> {
-    let mut args = vec!["submodule", "update"];
+pub async fn submodule_update(
+    repo_path: &Path,
+    recursive: bool,
+    on_progress: &ProgressCallback,
+) -> AppResult<()> {
+    let mut args = vec!["submodule", "update", "--progress"];
     if recursive {
         args.push("--recursive");
     }
-    run_git(repo_path, &args)?;
+    run_git_progress(repo_path, &args, on_progress).await?;
     Ok(())
 }
 
 /// Add a submodule
 pub async fn submodule_add(repo_path: &Path, url: &str, path: &str) -> AppResult<()> {
-    run_git(repo_path, &["submodule", "add", url, path])?;
+    run_git(repo_path, &["submodule", "add", url, path]).await?;
     Ok(())
 }
 
 /// Add a worktree
 pub async fn worktree_add(repo_path: &Path, path: &str, branch: &str) -> AppResult<()> {
-    run_git(repo_path, &["worktree", "add", path, branch])?;
+    run_git(repo_path, &["worktree", "add", path, branch]).await?;
     Ok(())
 }
 
 /// Remove a worktree
 pub async fn worktree_remove(repo_path: &Path, path: &str) -> AppResult<()> {
-    run_git(repo_path, &["worktree", "remove", path])?;
+    run_git(repo_path, &["worktree", "remove", path]).await?;
     Ok(())
 }
 
 /// List all worktrees
 pub async fn worktree_list(repo_path: &Path) -> AppResult<String> {
-    let output = run_git(repo_path, &["worktree", "list", "--porcelain"])?;
+    let output = run_git(repo_path, &["worktree", "list", "--porcelain"]).await?;
     Ok(output)
 }
+
+// ---------------------------------------------------------------------------
+// Combined diffs
+// ---------------------------------------------------------------------------
+
+/// `git diff-tree --cc` output for a merge commit, see `git::combined`
+pub async fn combined_diff(
+    repo_path: &Path,
+    commit: &str,
+    options: &DiffOptions,
+) -> AppResult<String> {
+    let mut cmd = git_command(repo_path);
+    cmd.args(["-c", "core.quotePath=false", "diff-tree", "--cc", "--no-commit-id"])
+        .args(["--no-color", "--no-ext-diff"])
+        .args(options.cli_args())
+        .arg(commit);
+    output_of(&mut cmd).await
+}
+
+// ---------------------------------------------------------------------------
+// Reflog & recovery
+// ---------------------------------------------------------------------------
+
+/// Ids of dangling commits: commits no ref reaches, ignoring reflogs, that
+/// no other unreachable commit points to
+pub async fn dangling_commits(repo_path: &Path) -> AppResult<Vec<String>> {
+    let output = run_git(repo_path, &["fsck", "--dangling", "--no-reflogs", "--no-progress"]).await?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.strip_prefix("dangling commit "))
+        .map(|id| id.trim().to_string())
+        .collect())
+}
+
+/// Create a branch pointing at `start`
+pub async fn create_branch_at(repo_path: &Path, name: &str, start: &str) -> AppResult<()> {
+    run_git(repo_path, &["branch", name, start]).await?;
+    Ok(())
+}
+
+/// Move a branch that is not checked out to `target`
+pub async fn force_branch(repo_path: &Path, name: &str, target: &str) -> AppResult<()> {
+    run_git(repo_path, &["branch", "-f", name, target]).await?;
+    Ok(())
+}
+
+/// Move the current branch to `target`, keeping local changes; fails rather
+/// than overwrite them
+pub async fn reset_keep(repo_path: &Path, target: &str) -> AppResult<()> {
+    run_git(repo_path, &["reset", "--keep", target]).await?;
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Operation journal: restoring state
+// ---------------------------------------------------------------------------
+
+/// Overwrite work tree files with their content in `source`, leaving the index alone
+pub async fn restore_worktree(repo_path: &Path, source: &str, paths: &[String]) -> AppResult<()> {
+    let source_arg = format!("--source={}", source);
+    let mut args = vec!["--literal-pathspecs", "restore", &source_arg, "--worktree", "--"];
+    args.extend(paths.iter().map(|s| s.as_str()));
+    run_git(repo_path, &args).await?;
+    Ok(())
+}
+
+/// Replace the index with the tree of `tree_ish`
+pub async fn read_tree(repo_path: &Path, tree_ish: &str) -> AppResult<()> {
+    run_git(repo_path, &["read-tree", tree_ish]).await?;
+    Ok(())
+}
+
+/// Create a ref, failing if it already exists
+pub async fn create_ref(repo_path: &Path, refname: &str, target: &str) -> AppResult<()> {
+    run_git(repo_path, &["update-ref", refname, target, ""]).await?;
+    Ok(())
+}
+
+/// Move refs to rewritten history in one transaction: none move if any of
+/// them no longer points at its old head
+pub async fn move_refs(
+    repo_path: &Path,
+    rewrites: &[HistoryRewrite],
+    reason: &str,
+) -> AppResult<()> {
+    let commands: String = rewrites
+        .iter()
+        .map(|r| format!("update {} {} {}\n", r.refname, r.new_head, r.old_head))
+        .collect();
+    run_git_with_input(repo_path, &["update-ref", "-m", reason, "--stdin"], &commands).await
+}
+
+/// Set the upstream of a local branch
+pub async fn set_upstream(repo_path: &Path, branch: &str, upstream: &str) -> AppResult<()> {
+    let upstream_arg = format!("--set-upstream-to={}", upstream);
+    run_git(repo_path, &["branch", &upstream_arg, branch]).await?;
+    Ok(())
+}
+
+/// Add an existing stash commit back to the stash list
+pub async fn stash_store(repo_path: &Path, commit: &str, message: &str) -> AppResult<()> {
+    run_git(repo_path, &["stash", "store", "-m", message, commit]).await?;
+    Ok(())
+}
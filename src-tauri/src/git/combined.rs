@@ -0,0 +1,384 @@
+//! Combined diffs of merge commits
+//!
+//! libgit2 only diffs two trees, so a merge is diffed against all of its
+//! parents at once by `git diff-tree --cc`, and its output is parsed here.
+//! As with `--cc`, only hunks where the merge result differs from every
+//! parent are kept: what the merge resolution itself changed.
+
+use serde::Serialize;
+
+use crate::git::diff::DiffLineType;
+
+/// Diff of a merge commit against each of its parents at once
+#[derive(Debug, Clone, Serialize)]
+pub struct CombinedDiff {
+    pub commit_id: String,
+    pub parent_ids: Vec<String>,
+    pub files: Vec<CombinedFile>,
+    /// Whether the merge introduced changes found in none of its parents
+    pub is_evil: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CombinedFile {
+    pub path: String,
+    pub is_binary: bool,
+    pub hunks: Vec<CombinedHunk>,
+    pub is_evil: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CombinedHunk {
+    pub header: String,
+    /// Range of the hunk in the merge result
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<CombinedLine>,
+}
+
+/// A line of a combined hunk
+#[derive(Debug, Clone, Serialize)]
+pub struct CombinedLine {
+    /// How the line differs from each parent, in parent order: `Addition`
+    /// when the result has it and the parent does not, `Deletion` when the
+    /// parent has it and the result does not
+    pub origins: Vec<DiffLineType>,
+    pub content: String,
+    /// Line number in the merge result, `None` for deleted lines
+    pub new_lineno: Option<u32>,
+    /// Added or deleted against every parent, so taken from none of them
+    pub is_evil: bool,
+}
+
+impl CombinedLine {
+    fn new(columns: &str, content: String, new_lineno: Option<u32>) -> Self {
+        let origins: Vec<DiffLineType> = columns
+            .chars()
+            .map(|c| match c {
+                '+' => DiffLineType::Addition,
+                '-' => DiffLineType::Deletion,
+                _ => DiffLineType::Context,
+            })
+            .collect();
+        let is_evil = [DiffLineType::Addition, DiffLineType::Deletion]
+            .iter()
+            .any(|kind| origins.iter().all(|origin| origin == kind));
+        Self {
+            origins,
+            content,
+            new_lineno,
+            is_evil,
+        }
+    }
+}
+
+/// Parse `git diff-tree --cc` output for a merge with `parent_ids`
+pub fn parse(commit_id: String, parent_ids: Vec<String>, output: &str) -> CombinedDiff {
+    let columns = parent_ids.len();
+    let hunk_marker = "@".repeat(columns + 1);
+    let mut files: Vec<CombinedFile> = Vec::new();
+    // Next line number in the result while inside a hunk
+    let mut new_lineno: Option<u32> = None;
+
+    for line in output.split_inclusive('\n') {
+        if let Some(path) = line
+            .strip_prefix("diff --cc ")
+            .or_else(|| line.strip_prefix("diff --combined "))
+        {
+            files.push(CombinedFile {
+                path: unquote_path(path.trim_end_matches('\n')),
+                is_binary: false,
+                hunks: Vec::new(),
+                is_evil: false,
+            });
+            new_lineno = None;
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        if line.starts_with(&hunk_marker) && line[hunk_marker.len()..].starts_with(' ') {
+            let (new_start, new_lines) = new_range(line);
+            file.hunks.push(CombinedHunk {
+                header: line.to_string(),
+                new_start,
+                new_lines,
+                lines: Vec::new(),
+            });
+            new_lineno = Some(new_start);
+            continue;
+        }
+        let (Some(lineno), Some(hunk)) = (new_lineno, file.hunks.last_mut()) else {
+            if line.starts_with("Binary files") {
+                file.is_binary = true;
+            }
+            continue;
+        };
+
+        if line.starts_with('\\') {
+            // "\ No newline at end of file" ends the line before it
+            if let Some(last) = hunk.lines.last_mut() {
+                if last.content.ends_with('\n') {
+                    last.content.pop();
+                }
+            }
+            continue;
+        }
+        let Some(line_columns) = line.get(..columns) else {
+            continue;
+        };
+        let deleted = line_columns.contains('-');
+        let line = CombinedLine::new(
+            line_columns,
+            line[columns..].to_string(),
+            (!deleted).then_some(lineno),
+        );
+        if !deleted {
+            new_lineno = Some(lineno + 1);
+        }
+        file.is_evil |= line.is_evil;
+        hunk.lines.push(line);
+    }
+
+    CombinedDiff {
+        commit_id,
+        parent_ids,
+        is_evil: files.iter().any(|file| file.is_evil),
+        files,
+    }
+}
+
+/// Start and length of the result's range in a `@@@ -a,b -c,d +e,f @@@` header
+fn new_range(header: &str) -> (u32, u32) {
+    let range = header
+        .split_whitespace()
+        .find_map(|part| part.strip_prefix('+'))
+        .unwrap_or("");
+    let mut parts = range.splitn(2, ',');
+    let start = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+    let lines = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+    (start, lines)
+}
+
+/// Undo git's C-style quoting of a path containing special characters
+fn unquote_path(path: &str) -> String {
+    let Some(quoted) = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) else {
+        return path.to_string();
+    };
+    let mut bytes = Vec::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('r') => bytes.push(b'\r'),
+            Some('a') => bytes.push(0x07),
+            Some('b') => bytes.push(0x08),
+            Some('f') => bytes.push(0x0c),
+            Some('v') => bytes.push(0x0b),
+            Some(d @ '0'..='7') => {
+                let octal: String = std::iter::once(d).chain(chars.by_ref().take(2)).collect();
+                bytes.push(u8::from_str_radix(&octal, 8).unwrap_or(b'?'));
+            }
+            Some(other) => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+            None => {}
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use DiffLineType::{Addition as Add, Context as Ctx, Deletion as Del};
+
+    /// `git diff-tree --cc` of a two-parent merge. `café.txt` keeps both
+    /// sides' lines, a clean resolution; `f.txt` replaces both sides' line 2
+    /// with one of its own, an evil change, and keeps both sides' line 18.
+    const TWO_PARENTS: &str = r#"diff --cc "caf\303\251.txt"
+index 27602be,d8f6cb2..88d2584
+--- "a/caf\303\251.txt"
++++ "b/caf\303\251.txt"
+@@@ -1,3 -1,3 +1,4 @@@
+  alpha
+ +beta main
++ beta side
+  gamma
+diff --cc f.txt
+index 193f004,5f4f3dc..529e709
+--- a/f.txt
++++ b/f.txt
+@@@ -1,5 -1,5 +1,5 @@@
+  line 1
+- line 2 main
+ -line 2 side
+++line 2 merged
+  line 3
+  line 4
+  line 5
+@@@ -15,6 -15,6 +15,7 @@@ line 1
+  line 15
+  line 16
+  line 17
+ +line 18 main
++ line 18 side
+  line 19
+  line 20
+"#;
+
+    /// An octopus merge of three parents that rewrites the first parent's
+    /// change to row 5
+    const THREE_PARENTS: &str = "diff --cc o.txt
+index 4981665,ab38b70,d3863a2..b57e420
+--- a/o.txt
++++ b/o.txt
+@@@@ -1,9 -1,9 -1,9 +1,9 @@@@
+   row 1
+- -row 2
++ +row 2 a
+   row 3
+   row 4
+-  row 5 main
+ --row 5
++++row 5 octopus
+   row 6
+   row 7
+-- row 8
+++ row 8 b
+   row 9
+";
+
+    fn parents(count: usize) -> Vec<String> {
+        (1..=count).map(|i| format!("parent{}", i)).collect()
+    }
+
+    fn summary(hunk: &CombinedHunk) -> Vec<(Vec<DiffLineType>, &str, Option<u32>, bool)> {
+        hunk.lines
+            .iter()
+            .map(|l| (l.origins.clone(), l.content.as_str(), l.new_lineno, l.is_evil))
+            .collect()
+    }
+
+    #[test]
+    fn clean_resolution_is_not_evil() {
+        let diff = parse("merge".into(), parents(2), TWO_PARENTS);
+        assert_eq!(diff.commit_id, "merge");
+        assert_eq!(diff.files.len(), 2);
+
+        let file = &diff.files[0];
+        assert_eq!(file.path, "café.txt");
+        assert!(!file.is_binary);
+        assert!(!file.is_evil);
+        assert_eq!(file.hunks.len(), 1);
+        let hunk = &file.hunks[0];
+        assert_eq!(hunk.header, "@@@ -1,3 -1,3 +1,4 @@@\n");
+        assert_eq!((hunk.new_start, hunk.new_lines), (1, 4));
+        assert_eq!(
+            summary(hunk),
+            [
+                (vec![Ctx, Ctx], "alpha\n", Some(1), false),
+                (vec![Ctx, Add], "beta main\n", Some(2), false),
+                (vec![Add, Ctx], "beta side\n", Some(3), false),
+                (vec![Ctx, Ctx], "gamma\n", Some(4), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn evil_hunk_is_flagged() {
+        let diff = parse("merge".into(), parents(2), TWO_PARENTS);
+        assert!(diff.is_evil);
+
+        let file = &diff.files[1];
+        assert_eq!(file.path, "f.txt");
+        assert!(file.is_evil);
+        assert_eq!(file.hunks.len(), 2);
+
+        let evil = &file.hunks[0];
+        assert_eq!((evil.new_start, evil.new_lines), (1, 5));
+        assert_eq!(
+            summary(evil)[..4],
+            [
+                (vec![Ctx, Ctx], "line 1\n", Some(1), false),
+                (vec![Del, Ctx], "line 2 main\n", None, false),
+                (vec![Ctx, Del], "line 2 side\n", None, false),
+                (vec![Add, Add], "line 2 merged\n", Some(2), true),
+            ]
+        );
+
+        // Both sides kept: every line comes from a parent
+        let clean = &file.hunks[1];
+        assert_eq!(clean.header, "@@@ -15,6 -15,6 +15,7 @@@ line 1\n");
+        assert_eq!((clean.new_start, clean.new_lines), (15, 7));
+        assert!(clean.lines.iter().all(|line| !line.is_evil));
+        assert_eq!(clean.lines[4].content, "line 18 side\n");
+        assert_eq!(clean.lines[4].new_lineno, Some(19));
+        assert_eq!(clean.lines.last().unwrap().new_lineno, Some(21));
+    }
+
+    #[test]
+    fn octopus_merge() {
+        let diff = parse("octopus".into(), parents(3), THREE_PARENTS);
+        assert!(diff.is_evil);
+        let hunk = &diff.files[0].hunks[0];
+        assert_eq!((hunk.new_start, hunk.new_lines), (1, 9));
+
+        let evil: Vec<_> = hunk.lines.iter().filter(|l| l.is_evil).collect();
+        assert_eq!(evil.len(), 1);
+        assert_eq!(evil[0].content, "row 5 octopus\n");
+        assert_eq!(evil[0].origins, [Add, Add, Add]);
+        assert_eq!(evil[0].new_lineno, Some(5));
+
+        let row_8 = hunk.lines.iter().find(|l| l.content == "row 8 b\n").unwrap();
+        assert_eq!(row_8.origins, [Add, Add, Ctx]);
+        assert_eq!(row_8.new_lineno, Some(8));
+        assert_eq!(hunk.lines.last().unwrap().new_lineno, Some(9));
+    }
+
+    #[test]
+    fn line_evilness() {
+        let evil = |columns: &str| CombinedLine::new(columns, String::new(), None).is_evil;
+        assert!(evil("++"));
+        assert!(evil("--"));
+        assert!(evil("+++"));
+        assert!(!evil("+ "));
+        assert!(!evil(" -"));
+        assert!(!evil("+-"));
+        assert!(!evil("  "));
+        assert!(!evil("-- "));
+    }
+
+    #[test]
+    fn clean_merge_has_no_files() {
+        let diff = parse("merge".into(), parents(2), "");
+        assert!(diff.files.is_empty());
+        assert!(!diff.is_evil);
+    }
+
+    #[test]
+    fn binary_files() {
+        let output = "diff --cc logo.png\nindex 1111111,2222222..3333333\nBinary files differ\n";
+        let diff = parse("merge".into(), parents(2), output);
+        assert_eq!(diff.files.len(), 1);
+        assert!(diff.files[0].is_binary);
+        assert!(diff.files[0].hunks.is_empty());
+        assert!(!diff.is_evil);
+    }
+
+    #[test]
+    fn quoted_paths() {
+        assert_eq!(unquote_path("plain.txt"), "plain.txt");
+        assert_eq!(unquote_path(r#""caf\303\251.txt""#), "café.txt");
+        assert_eq!(unquote_path(r#""tab\there \"q\"""#), "tab\there \"q\"");
+    }
+}
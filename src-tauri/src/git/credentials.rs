@@ -0,0 +1,77 @@
+//! Git credentials saved in the system keyring, next to the GitHub token
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+/// Username and password for one remote host
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostCredential {
+    pub username: String,
+    pub password: String,
+}
+
+fn entry(account: &str) -> AppResult<keyring::Entry> {
+    keyring::Entry::new("gitsage", account)
+        .map_err(|e| AppError::General(format!("Keyring error: {}", e)))
+}
+
+fn host_account(protocol: &str, host: &str) -> String {
+    format!("git-credential:{}://{}", protocol, host)
+}
+
+fn passphrase_account(key_path: &str) -> String {
+    format!("ssh-passphrase:{}", key_path)
+}
+
+fn load(account: &str) -> AppResult<Option<String>> {
+    match entry(account)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(AppError::General(format!("Failed to load credential: {}", e))),
+    }
+}
+
+fn store(account: &str, secret: &str) -> AppResult<()> {
+    entry(account)?
+        .set_password(secret)
+        .map_err(|e| AppError::General(format!("Failed to store credential: {}", e)))
+}
+
+fn delete(account: &str) -> AppResult<()> {
+    match entry(account)?.delete_credential() {
+        Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(AppError::General(format!("Failed to delete credential: {}", e))),
+    }
+}
+
+pub fn load_host_credential(protocol: &str, host: &str) -> AppResult<Option<HostCredential>> {
+    match load(&host_account(protocol, host))? {
+        Some(json) => Ok(serde_json::from_str(&json).ok()),
+        None => Ok(None),
+    }
+}
+
+pub fn store_host_credential(
+    protocol: &str,
+    host: &str,
+    credential: &HostCredential,
+) -> AppResult<()> {
+    store(&host_account(protocol, host), &serde_json::to_string(credential)?)
+}
+
+pub fn delete_host_credential(protocol: &str, host: &str) -> AppResult<()> {
+    delete(&host_account(protocol, host))
+}
+
+pub fn load_passphrase(key_path: &str) -> AppResult<Option<String>> {
+    load(&passphrase_account(key_path))
+}
+
+pub fn store_passphrase(key_path: &str, passphrase: &str) -> AppResult<()> {
+    store(&passphrase_account(key_path), passphrase)
+}
+
+pub fn delete_passphrase(key_path: &str) -> AppResult<()> {
+    delete(&passphrase_account(key_path))
+}
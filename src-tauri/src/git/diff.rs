@@ -1,4 +1,118 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+/// Follows the last line of a file that has no trailing newline, ending the
+/// line it follows
+pub const NO_NEWLINE_MARKER: &str = "\n\\ No newline at end of file\n";
+
+/// Word diff is skipped for line pairs whose token counts multiply past this,
+/// to bound the quadratic comparison on very long lines
+const WORD_DIFF_MAX_CELLS: usize = 250_000;
+
+/// How a diff is computed and annotated. Every field has a default, so
+/// callers only send what they change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffOptions {
+    pub whitespace: WhitespaceMode,
+    /// Unchanged lines shown around each change
+    pub context_lines: u32,
+    /// Unchanged lines between two changes below which their hunks merge
+    pub interhunk_lines: u32,
+    pub detect_renames: bool,
+    /// Similarity in percent for a deleted and an added file to be a rename
+    pub rename_threshold: u16,
+    /// Also look for added files copied from modified ones
+    pub detect_copies: bool,
+    /// Similarity in percent for an added file to be a copy
+    pub copy_threshold: u16,
+    /// Mark the changed words of modified lines, see `DiffLine::changes`
+    pub word_diff: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            whitespace: WhitespaceMode::None,
+            context_lines: 3,
+            interhunk_lines: 0,
+            detect_renames: true,
+            rename_threshold: 50,
+            detect_copies: false,
+            copy_threshold: 50,
+            word_diff: false,
+        }
+    }
+}
+
+/// Whitespace differences to ignore, as `git diff` flags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WhitespaceMode {
+    #[default]
+    None,
+    /// `--ignore-space-at-eol`
+    IgnoreAtEol,
+    /// `-b`: ignore changes in the amount of whitespace
+    IgnoreChange,
+    /// `-w`: ignore all whitespace
+    IgnoreAll,
+}
+
+impl DiffOptions {
+    /// libgit2 options for computing a diff with these settings
+    pub fn to_git2(&self) -> git2::DiffOptions {
+        let mut opts = git2::DiffOptions::new();
+        opts.context_lines(self.context_lines)
+            .interhunk_lines(self.interhunk_lines)
+            .ignore_whitespace_eol(self.whitespace == WhitespaceMode::IgnoreAtEol)
+            .ignore_whitespace_change(self.whitespace == WhitespaceMode::IgnoreChange)
+            .ignore_whitespace(self.whitespace == WhitespaceMode::IgnoreAll);
+        opts
+    }
+
+    /// Pair up renamed and copied files in a computed diff
+    pub fn find_similar(&self, diff: &mut git2::Diff) -> Result<(), git2::Error> {
+        if !self.detect_renames && !self.detect_copies {
+            return Ok(());
+        }
+        let mut opts = git2::DiffFindOptions::new();
+        opts.renames(self.detect_renames)
+            .rename_threshold(self.rename_threshold)
+            .copies(self.detect_copies)
+            .copy_threshold(self.copy_threshold)
+            // Match git: whitespace settings also apply to similarity
+            .ignore_whitespace(self.whitespace != WhitespaceMode::None);
+        diff.find_similar(Some(&mut opts))
+    }
+
+    /// The same settings as `git diff` flags; word diff has no equivalent
+    pub fn cli_args(&self) -> Vec<String> {
+        let mut args = vec![
+            format!("-U{}", self.context_lines),
+            format!("--inter-hunk-context={}", self.interhunk_lines),
+        ];
+        match self.whitespace {
+            WhitespaceMode::None => {}
+            WhitespaceMode::IgnoreAtEol => args.push("--ignore-space-at-eol".to_string()),
+            WhitespaceMode::IgnoreChange => args.push("-b".to_string()),
+            WhitespaceMode::IgnoreAll => args.push("-w".to_string()),
+        }
+        if self.detect_renames {
+            args.push(format!("-M{}%", self.rename_threshold));
+        } else {
+            args.push("--no-renames".to_string());
+        }
+        if self.detect_copies {
+            args.push(format!("-C{}%", self.copy_threshold));
+        }
+        args
+    }
+
+    /// Whether the diff hides some changes, so its hunks cannot be turned
+    /// back into patches
+    pub fn ignores_whitespace(&self) -> bool {
+        self.whitespace != WhitespaceMode::None
+    }
+}
 
 /// A diff hunk
 #[derive(Debug, Clone, Serialize)]
@@ -9,6 +123,10 @@ pub struct DiffHunk {
     pub new_lines: u32,
     pub header: String,
     pub lines: Vec<DiffLine>,
+    /// Hash of the hunk's lines, to check a hunk the UI shows is still the
+    /// one in the current diff before acting on it. Line numbers are left
+    /// out, so edits elsewhere in the file don't invalidate it.
+    pub fingerprint: String,
 }
 
 /// A single line in a diff
@@ -18,6 +136,17 @@ pub struct DiffLine {
     pub content: String,
     pub old_lineno: Option<u32>,
     pub new_lineno: Option<u32>,
+    /// With word diff, the parts of a deleted or added line that differ from
+    /// the line it was replaced by or replaces. Empty for other lines and for
+    /// lines with no counterpart, which changed as a whole.
+    pub changes: Vec<ChangeRange>,
+}
+
+/// A span of a line's content, in characters
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub struct ChangeRange {
+    pub start: usize,
+    pub end: usize,
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq)]
@@ -39,8 +168,46 @@ pub struct DiffOutput {
 pub struct DiffFile {
     pub old_path: Option<String>,
     pub new_path: Option<String>,
+    pub status: DiffFileStatus,
     pub hunks: Vec<DiffHunk>,
     pub is_binary: bool,
+    /// File modes (e.g. 0o100644), 0 where the file does not exist; needed
+    /// to write patches that create or delete files
+    #[serde(skip)]
+    pub old_mode: u32,
+    #[serde(skip)]
+    pub new_mode: u32,
+}
+
+/// How a file changed between the two sides of a diff
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub enum DiffFileStatus {
+    /// New, including untracked files
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Copied,
+    TypeChanged,
+}
+
+/// Diff summary without the changed lines, for ranges too large to show in
+/// full
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffStatOutput {
+    pub files: Vec<DiffFileStat>,
+    pub stats: DiffStats,
+}
+
+/// Line counts of one file in a `DiffStatOutput`
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffFileStat {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub status: DiffFileStatus,
+    pub is_binary: bool,
+    pub insertions: usize,
+    pub deletions: usize,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -61,4 +228,436 @@ impl DiffOutput {
             },
         }
     }
+
+    /// Render as unified diff text (as `git diff` would print it)
+    pub fn to_unified(&self) -> String {
+        self.files.iter().map(DiffFile::to_unified).collect()
+    }
+}
+
+impl DiffFile {
+    /// Path shown for the file: the new path, or the old path for deletions
+    pub fn display_path(&self) -> &str {
+        self.new_path
+            .as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or("")
+    }
+
+    /// Number of added and deleted lines across all hunks
+    pub fn line_counts(&self) -> (usize, usize) {
+        self.hunks.iter().fold((0, 0), |(ins, del), hunk| {
+            let (i, d) = hunk.line_counts();
+            (ins + i, del + d)
+        })
+    }
+
+    /// Render the `diff --git` / `---` / `+++` header lines
+    pub fn header_text(&self) -> String {
+        let old_path = self.old_path.as_deref().unwrap_or(self.display_path());
+        let new_path = self.display_path();
+        let mut out = format!("diff --git a/{} b/{}\n", old_path, new_path);
+        if self.is_binary {
+            out.push_str("Binary files differ\n");
+        } else {
+            out.push_str(&format!("--- a/{}\n+++ b/{}\n", old_path, new_path));
+        }
+        out
+    }
+
+    /// Render header and all hunks as unified diff text
+    pub fn to_unified(&self) -> String {
+        let mut out = self.header_text();
+        if !self.is_binary {
+            for hunk in &self.hunks {
+                out.push_str(&hunk.to_unified());
+            }
+        }
+        out
+    }
+}
+
+impl DiffHunk {
+    /// Fingerprint of a hunk with these lines
+    pub fn fingerprint_of(lines: &[DiffLine]) -> String {
+        let mut text = String::new();
+        for line in lines {
+            let prefix = match line.origin {
+                DiffLineType::Addition => '+',
+                DiffLineType::Deletion => '-',
+                DiffLineType::Context => ' ',
+                DiffLineType::Header => continue,
+            };
+            text.push(prefix);
+            text.push_str(&line.content);
+            if !line.content.ends_with('\n') {
+                text.push_str(NO_NEWLINE_MARKER);
+            }
+        }
+        // Any stable hash will do; git's object hash needs no extra dependency
+        let hash = git2::Oid::hash_object(git2::ObjectType::Blob, text.as_bytes())
+            .map(|oid| oid.to_string())
+            .unwrap_or_default();
+        hash[..hash.len().min(16)].to_string()
+    }
+
+    /// Fill in `DiffLine::changes`. Within each run of deletions followed by
+    /// additions the lines are paired in order, as the replacement of one
+    /// another; surplus lines on either side are left unannotated.
+    pub fn annotate_word_changes(&mut self) {
+        let lines = &mut self.lines;
+        let mut i = 0;
+        while i < lines.len() {
+            let deletions = i;
+            while i < lines.len() && lines[i].origin == DiffLineType::Deletion {
+                i += 1;
+            }
+            let additions = i;
+            while i < lines.len() && lines[i].origin == DiffLineType::Addition {
+                i += 1;
+            }
+            if i == deletions {
+                i += 1;
+                continue;
+            }
+            let pairs = (additions - deletions).min(i - additions);
+            for k in 0..pairs {
+                let (old, new) = word_changes(
+                    &lines[deletions + k].content,
+                    &lines[additions + k].content,
+                );
+                lines[deletions + k].changes = old;
+                lines[additions + k].changes = new;
+            }
+        }
+    }
+
+    /// Number of added and deleted lines in this hunk
+    pub fn line_counts(&self) -> (usize, usize) {
+        self.lines.iter().fold((0, 0), |(ins, del), line| match line.origin {
+            DiffLineType::Addition => (ins + 1, del),
+            DiffLineType::Deletion => (ins, del + 1),
+            _ => (ins, del),
+        })
+    }
+
+    /// Render the `@@` header and lines as unified diff text
+    pub fn to_unified(&self) -> String {
+        let mut out = self.header.clone();
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        for line in &self.lines {
+            let prefix = match line.origin {
+                DiffLineType::Addition => '+',
+                DiffLineType::Deletion => '-',
+                DiffLineType::Context => ' ',
+                DiffLineType::Header => continue,
+            };
+            out.push(prefix);
+            out.push_str(&line.content);
+            if !line.content.ends_with('\n') {
+                out.push_str(NO_NEWLINE_MARKER);
+            }
+        }
+        out
+    }
+}
+
+/// A word, a run of whitespace, or a single other character, with its span in
+/// characters
+struct Token<'a> {
+    text: &'a str,
+    range: ChangeRange,
+}
+
+fn tokenize(line: &str) -> Vec<Token<'_>> {
+    fn class(c: char) -> u8 {
+        if c.is_alphanumeric() || c == '_' {
+            0
+        } else if c.is_whitespace() {
+            1
+        } else {
+            2
+        }
+    }
+
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut chars = 0;
+    let mut start_byte = 0;
+    let mut start_char = 0;
+    let mut prev: Option<u8> = None;
+    for (byte, c) in line.char_indices() {
+        let cls = class(c);
+        // Punctuation never joins its neighbours
+        if prev.is_some() && (prev != Some(cls) || cls == 2) {
+            tokens.push(Token {
+                text: &line[start_byte..byte],
+                range: ChangeRange { start: start_char, end: chars },
+            });
+            start_byte = byte;
+            start_char = chars;
+        }
+        prev = Some(cls);
+        chars += 1;
+    }
+    if prev.is_some() {
+        tokens.push(Token {
+            text: &line[start_byte..],
+            range: ChangeRange { start: start_char, end: chars },
+        });
+    }
+    tokens
+}
+
+/// Changed ranges of an old and a new line: the tokens outside their longest
+/// common subsequence, with adjacent tokens merged
+fn word_changes(old: &str, new: &str) -> (Vec<ChangeRange>, Vec<ChangeRange>) {
+    let strip = |line: &str| line.trim_end_matches(['\n', '\r']).to_string();
+    let (old, new) = (strip(old), strip(new));
+    let (a, b) = (tokenize(&old), tokenize(&new));
+    if a.len() * b.len() > WORD_DIFF_MAX_CELLS {
+        return (Vec::new(), Vec::new());
+    }
+
+    // lcs[i][j]: length of the common subsequence of a[i..] and b[j..]
+    let (n, m) = (a.len(), b.len());
+    let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[at(i, j)] = if a[i].text == b[j].text {
+                lcs[at(i + 1, j + 1)] + 1
+            } else {
+                lcs[at(i + 1, j)].max(lcs[at(i, j + 1)])
+            };
+        }
+    }
+
+    let (mut old_changes, mut new_changes) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && a[i].text == b[j].text {
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lcs[at(i + 1, j)] >= lcs[at(i, j + 1)]) {
+            push_range(&mut old_changes, a[i].range);
+            i += 1;
+        } else {
+            push_range(&mut new_changes, b[j].range);
+            j += 1;
+        }
+    }
+    (old_changes, new_changes)
+}
+
+fn push_range(ranges: &mut Vec<ChangeRange>, range: ChangeRange) {
+    match ranges.last_mut() {
+        Some(last) if last.end == range.start => last.end = range.end,
+        _ => ranges.push(range),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: usize, end: usize) -> ChangeRange {
+        ChangeRange { start, end }
+    }
+
+    fn line(origin: DiffLineType, content: &str) -> DiffLine {
+        DiffLine {
+            origin,
+            content: content.to_string(),
+            old_lineno: None,
+            new_lineno: None,
+            changes: Vec::new(),
+        }
+    }
+
+    /// `count` words separated by spaces, with word `changed` replaced
+    fn words(count: usize, changed: Option<usize>) -> String {
+        (0..count)
+            .map(|i| if Some(i) == changed { "changed".to_string() } else { format!("w{}", i) })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn tokens_split_words_spaces_and_punctuation() {
+        let tokens: Vec<&str> = tokenize("let x_1 = f(a,  b);").iter().map(|t| t.text).collect();
+        assert_eq!(
+            tokens,
+            ["let", " ", "x_1", " ", "=", " ", "f", "(", "a", ",", "  ", "b", ")", ";"]
+        );
+        assert!(tokenize("").is_empty());
+    }
+
+    #[test]
+    fn changed_word() {
+        let (old, new) = word_changes("let value = compute(a);\n", "let value = compute(b);\n");
+        assert_eq!(old, [range(20, 21)]);
+        assert_eq!(new, [range(20, 21)]);
+    }
+
+    #[test]
+    fn adjacent_tokens_merge_into_one_range() {
+        let (old, new) = word_changes("foo(bar)", "foo.baz()");
+        // "foo", "(" and ")" are kept: "bar" became ".baz"
+        assert_eq!(old, [range(4, 7)]);
+        assert_eq!(new, [range(3, 7)]);
+    }
+
+    #[test]
+    fn insertion_only_marks_the_new_side() {
+        let (old, new) = word_changes("a b", "a new b");
+        assert!(old.is_empty());
+        assert_eq!(new, [range(2, 6)]);
+    }
+
+    #[test]
+    fn ranges_count_characters_not_bytes() {
+        let (old, new) = word_changes("naïve café", "naïve thé");
+        assert_eq!(old, [range(6, 10)]);
+        assert_eq!(new, [range(6, 9)]);
+    }
+
+    #[test]
+    fn line_endings_are_not_changes() {
+        let (old, new) = word_changes("same text\r\n", "same text\n");
+        assert!(old.is_empty() && new.is_empty());
+    }
+
+    #[test]
+    fn long_lines_fall_back_to_whole_line_changes() {
+        // 250 words and 249 spaces: 499 × 499 cells, under the cap
+        let (old, new) = word_changes(&words(250, None), &words(250, Some(100)));
+        assert_eq!(old.len(), 1);
+        assert_eq!(new.len(), 1);
+
+        // 300 words: 599 × 599 cells, over the cap
+        let (old, new) = word_changes(&words(300, None), &words(300, Some(100)));
+        assert!(old.is_empty() && new.is_empty());
+    }
+
+    #[test]
+    fn annotation_pairs_deletions_with_additions() {
+        let mut hunk = DiffHunk {
+            old_start: 1,
+            old_lines: 4,
+            new_start: 1,
+            new_lines: 3,
+            header: "@@ -1,4 +1,3 @@\n".to_string(),
+            lines: vec![
+                line(DiffLineType::Context, "fn main() {\n"),
+                line(DiffLineType::Deletion, "    let a = 1;\n"),
+                line(DiffLineType::Deletion, "    let b = 2;\n"),
+                line(DiffLineType::Addition, "    let a = 10;\n"),
+                line(DiffLineType::Context, "}\n"),
+                line(DiffLineType::Addition, "// end\n"),
+            ],
+            fingerprint: String::new(),
+        };
+        hunk.annotate_word_changes();
+
+        let changes: Vec<_> = hunk.lines.iter().map(|l| l.changes.clone()).collect();
+        assert_eq!(
+            changes,
+            [
+                vec![],
+                vec![range(12, 13)],
+                // Surplus deletion and addition without a partner
+                vec![],
+                vec![range(12, 14)],
+                vec![],
+                vec![],
+            ]
+        );
+    }
+
+    /// Hunks of diffing `old` against `new` with `options`, as
+    /// `(old_lines, new_lines)` per hunk
+    fn hunks(options: &DiffOptions, old: &str, new: &str) -> Vec<(u32, u32)> {
+        let mut opts = options.to_git2();
+        let patch =
+            git2::Patch::from_buffers(old.as_bytes(), None, new.as_bytes(), None, Some(&mut opts))
+                .unwrap();
+        (0..patch.num_hunks())
+            .map(|i| {
+                let (hunk, _) = patch.hunk(i).unwrap();
+                (hunk.old_lines(), hunk.new_lines())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn whitespace_modes() {
+        let with = |whitespace| DiffOptions {
+            whitespace,
+            ..Default::default()
+        };
+        let cases = [
+            // old, new, and whether each mode still sees a change:
+            // None, IgnoreAtEol, IgnoreChange, IgnoreAll
+            ("a b\n", "a b  \n", [true, false, false, false]),
+            ("a b\n", "a   b\n", [true, true, false, false]),
+            ("ab\n", "a b\n", [true, true, true, false]),
+            ("a b\n", "a c\n", [true, true, true, true]),
+        ];
+        let modes = [
+            WhitespaceMode::None,
+            WhitespaceMode::IgnoreAtEol,
+            WhitespaceMode::IgnoreChange,
+            WhitespaceMode::IgnoreAll,
+        ];
+        for (old, new, expected) in cases {
+            for (mode, changed) in modes.into_iter().zip(expected) {
+                let found = !hunks(&with(mode), old, new).is_empty();
+                assert_eq!(found, changed, "{:?} {:?} -> {:?}", mode, old, new);
+            }
+        }
+    }
+
+    #[test]
+    fn context_and_interhunk_lines() {
+        let old: String = (1..=30).map(|i| format!("line {}\n", i)).collect();
+        let new = old.replace("line 10\n", "line ten\n").replace("line 16\n", "line sixteen\n");
+
+        let options = |context_lines, interhunk_lines| DiffOptions {
+            context_lines,
+            interhunk_lines,
+            ..Default::default()
+        };
+        // Three lines around each change merge the two hunks (lines 7-19)
+        assert_eq!(hunks(&options(3, 0), &old, &new), [(13, 13)]);
+        assert_eq!(hunks(&options(0, 0), &old, &new), [(1, 1), (1, 1)]);
+        assert_eq!(hunks(&options(1, 0), &old, &new), [(3, 3), (3, 3)]);
+        // Five unchanged lines between them
+        assert_eq!(hunks(&options(0, 5), &old, &new), [(7, 7)]);
+        assert_eq!(hunks(&options(0, 4), &old, &new).len(), 2);
+    }
+
+    #[test]
+    fn cli_args_match_options() {
+        assert_eq!(
+            DiffOptions::default().cli_args(),
+            ["-U3", "--inter-hunk-context=0", "-M50%"]
+        );
+        let options = DiffOptions {
+            whitespace: WhitespaceMode::IgnoreChange,
+            context_lines: 1,
+            interhunk_lines: 2,
+            detect_renames: false,
+            detect_copies: true,
+            copy_threshold: 75,
+            ..Default::default()
+        };
+        assert_eq!(
+            options.cli_args(),
+            ["-U1", "--inter-hunk-context=2", "-b", "--no-renames", "-C75%"]
+        );
+        assert!(options.ignores_whitespace());
+        assert!(!DiffOptions::default().ignores_whitespace());
+    }
 }
@@ -0,0 +1,107 @@
+//! Operation journal: state captured before destructive operations so they
+//! can be undone
+//!
+//! Objects an entry needs (snapshot commits, dropped stashes, deleted ref
+//! targets) are kept reachable under `refs/gitsage/journal/<entry id>/` so
+//! `git gc` never prunes them while the entry exists.
+//!
+//! These are ordinary refs, so `git for-each-ref` lists them, `git log --all`
+//! shows the snapshot commits and `git push --mirror` pushes them; git has no
+//! namespace under `refs/` that a mirror push leaves out. They only live as
+//! long as their entries, which undoing or pruning removes. To leave them out
+//! of the history, use `git log --exclude='refs/gitsage/*' --all`.
+
+use serde::{Deserialize, Serialize};
+
+/// Ref namespace anchoring the objects of journal entries
+pub const ANCHOR_PREFIX: &str = "refs/gitsage/journal";
+
+/// Previous content of some work tree files, stored as a commit on top of HEAD
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkdirSnapshot {
+    pub commit: String,
+    /// Files present in the snapshot
+    pub paths: Vec<String>,
+    /// Files that did not exist in the work tree
+    pub removed: Vec<String>,
+}
+
+/// A stash entry removed by `stash drop` or `stash clear`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DroppedStash {
+    pub commit: String,
+    pub message: String,
+}
+
+/// What a destructive operation destroyed, enough to restore it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalAction {
+    /// Work tree changes were discarded
+    Discard { snapshot: WorkdirSnapshot },
+    /// HEAD was moved by a reset or by undoing a commit
+    Reset {
+        /// Branch HEAD was on, `None` when detached
+        branch: Option<String>,
+        previous_head: String,
+        /// Commit whose tree is the index before the reset
+        index: String,
+        /// Work tree changes, for hard resets
+        worktree: Option<WorkdirSnapshot>,
+    },
+    /// Stash entries were dropped, newest first
+    DropStashes { stashes: Vec<DroppedStash> },
+    /// A branch or tag was deleted
+    DeleteRef {
+        refname: String,
+        target: String,
+        /// Upstream of a deleted branch, e.g. "origin/main"
+        upstream: Option<String>,
+    },
+}
+
+impl JournalAction {
+    /// Nothing was captured, so there is nothing to undo
+    pub fn is_empty(&self) -> bool {
+        match self {
+            JournalAction::Discard { snapshot } => {
+                snapshot.paths.is_empty() && snapshot.removed.is_empty()
+            }
+            JournalAction::DropStashes { stashes } => stashes.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Objects that must stay reachable for the undo to work
+    pub fn objects(&self) -> Vec<String> {
+        match self {
+            JournalAction::Discard { snapshot } => vec![snapshot.commit.clone()],
+            JournalAction::Reset {
+                previous_head,
+                index,
+                worktree,
+                ..
+            } => {
+                let mut objects = vec![previous_head.clone(), index.clone()];
+                objects.extend(worktree.iter().map(|s| s.commit.clone()));
+                objects
+            }
+            JournalAction::DropStashes { stashes } => {
+                stashes.iter().map(|s| s.commit.clone()).collect()
+            }
+            JournalAction::DeleteRef { target, .. } => vec![target.clone()],
+        }
+    }
+}
+
+/// A recorded destructive operation
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalEntry {
+    pub id: i64,
+    /// Human-readable summary, e.g. "Delete branch feature/login"
+    pub description: String,
+    pub action: JournalAction,
+    /// Unix seconds
+    pub created_at: i64,
+    pub undone: bool,
+}
@@ -1,11 +1,15 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
-use git2::{DiffOptions, Repository, StatusOptions};
+use git2::{Repository, StatusOptions};
 
 use crate::error::{AppError, AppResult};
 use crate::git::diff::*;
+use crate::git::journal::{DroppedStash, WorkdirSnapshot, ANCHOR_PREFIX};
+use crate::git::rebase::{
+    self, HistoryRewrite, RebaseAction, RebaseStatus, RebaseStep, RewriteTarget,
+};
 use crate::git::repository::*;
 
 /// Git operations implemented via git2-rs (libgit2)
@@ -40,6 +44,12 @@ impl LibGitOps {
             .map_err(|e| AppError::General(format!("Failed to lock repository: {}", e)))
     }
 
+    /// Path of the `.git` directory (or the worktree's private git dir)
+    pub fn git_dir(&self) -> AppResult<PathBuf> {
+        let repo = self.lock_repo()?;
+        Ok(repo.path().to_path_buf())
+    }
+
     pub fn current_branch(&self) -> AppResult<String> {
         let repo = self.lock_repo()?;
         let head = repo.head()?;
@@ -142,12 +152,17 @@ impl LibGitOps {
         Ok(result)
     }
 
-    pub fn diff_file(&self, path: &str, staged: bool) -> AppResult<DiffOutput> {
+    pub fn diff_file(
+        &self,
+        path: &str,
+        staged: bool,
+        options: &DiffOptions,
+    ) -> AppResult<DiffOutput> {
         let repo = self.lock_repo()?;
-        let mut opts = DiffOptions::new();
+        let mut opts = options.to_git2();
         opts.pathspec(path);
 
-        let diff = if staged {
+        let mut diff = if staged {
             let head_tree = repo
                 .head()
                 .ok()
@@ -158,7 +173,7 @@ impl LibGitOps {
             let status = repo.status_file(std::path::Path::new(path))?;
             if status.contains(git2::Status::WT_NEW) {
                 // 新文件：用空树 vs 工作区做 diff，显示全部内容为新增
-                opts.include_untracked(true);
+                opts.include_untracked(true).show_untracked_content(true);
                 let empty_tree_oid = repo.treebuilder(None)?.write()?;
                 let empty_tree = repo.find_tree(empty_tree_oid)?;
                 repo.diff_tree_to_workdir(Some(&empty_tree), Some(&mut opts))?
@@ -166,36 +181,33 @@ impl LibGitOps {
                 repo.diff_index_to_workdir(None, Some(&mut opts))?
             }
         };
+        options.find_similar(&mut diff)?;
 
-        Self::parse_diff(&diff)
+        Self::parse_diff(&diff, options)
     }
 
-    pub fn diff_staged(&self) -> AppResult<DiffOutput> {
+    pub fn diff_staged(&self, options: &DiffOptions) -> AppResult<DiffOutput> {
         let repo = self.lock_repo()?;
         let head_tree = repo
             .head()
             .ok()
             .and_then(|h| h.peel_to_tree().ok());
 
-        let diff = repo.diff_tree_to_index(head_tree.as_ref(), None, None)?;
+        let mut diff =
+            repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut options.to_git2()))?;
+        options.find_similar(&mut diff)?;
 
-        Self::parse_diff(&diff)
+        Self::parse_diff(&diff, options)
     }
 
-    fn parse_diff(diff: &git2::Diff) -> AppResult<DiffOutput> {
+    fn parse_diff(diff: &git2::Diff, options: &DiffOptions) -> AppResult<DiffOutput> {
         let stats = diff.stats()?;
         let mut files = Vec::new();
 
         for (delta_idx, delta) in diff.deltas().enumerate() {
-            let old_path = delta
-                .old_file()
-                .path()
-                .map(|p| p.to_string_lossy().to_string());
-            let new_path = delta
-                .new_file()
-                .path()
-                .map(|p| p.to_string_lossy().to_string());
+            let (old_path, new_path) = delta_paths(&delta);
             let is_binary = delta.old_file().is_binary() || delta.new_file().is_binary();
+            let status = delta_status(&delta);
 
             let mut hunks = Vec::new();
             let patch = git2::Patch::from_diff(diff, delta_idx)?;
@@ -210,6 +222,9 @@ impl LibGitOps {
                         let origin = match line.origin() {
                             '+' => DiffLineType::Addition,
                             '-' => DiffLineType::Deletion,
+                            // "\ No newline at end of file" markers; the line
+                            // before them already lacks its trailing newline
+                            '=' | '>' | '<' => continue,
                             _ => DiffLineType::Context,
                         };
 
@@ -218,25 +233,34 @@ impl LibGitOps {
                             content: String::from_utf8_lossy(line.content()).to_string(),
                             old_lineno: line.old_lineno(),
                             new_lineno: line.new_lineno(),
+                            changes: Vec::new(),
                         });
                     }
 
-                    hunks.push(DiffHunk {
+                    let mut hunk = DiffHunk {
                         old_start: hunk.old_start(),
                         old_lines: hunk.old_lines(),
                         new_start: hunk.new_start(),
                         new_lines: hunk.new_lines(),
                         header: String::from_utf8_lossy(hunk.header()).to_string(),
+                        fingerprint: DiffHunk::fingerprint_of(&lines),
                         lines,
-                    });
+                    };
+                    if options.word_diff {
+                        hunk.annotate_word_changes();
+                    }
+                    hunks.push(hunk);
                 }
             }
 
             files.push(DiffFile {
                 old_path,
                 new_path,
+                status,
                 hunks,
                 is_binary,
+                old_mode: delta.old_file().mode().into(),
+                new_mode: delta.new_file().mode().into(),
             });
         }
 
@@ -552,24 +576,50 @@ impl LibGitOps {
     // Phase 4: Commit Diff, File Log, Blame, Search, Pagination
     // ========================================================================
 
-    /// Get the diff introduced by a specific commit.
-    pub fn commit_diff(&self, commit_id: &str) -> AppResult<DiffOutput> {
+    /// Get the diff introduced by a specific commit, against its parent at
+    /// `parent` (0-based, the first parent by default).
+    pub fn commit_diff(
+        &self,
+        commit_id: &str,
+        parent: Option<usize>,
+        options: &DiffOptions,
+    ) -> AppResult<DiffOutput> {
         let repo = self.lock_repo()?;
         let oid = git2::Oid::from_str(commit_id)
             .map_err(|e| AppError::General(format!("Invalid commit id '{}': {}", commit_id, e)))?;
         let commit = repo.find_commit(oid)?;
         let commit_tree = commit.tree()?;
 
-        let diff = if commit.parent_count() > 0 {
-            let parent = commit.parent(0)?;
-            let parent_tree = parent.tree()?;
-            repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit_tree), None)?
+        let mut opts = options.to_git2();
+
+        let parent = parent.unwrap_or(0);
+        let parent_tree = if commit.parent_count() > 0 || parent > 0 {
+            let parent = commit.parent(parent).map_err(|_| {
+                AppError::InvalidInput(format!(
+                    "Commit {} has {} parent(s), none at index {}",
+                    commit_id,
+                    commit.parent_count(),
+                    parent
+                ))
+            })?;
+            Some(parent.tree()?)
         } else {
             // Initial commit: diff empty tree to commit tree
-            repo.diff_tree_to_tree(None, Some(&commit_tree), None)?
+            None
         };
+        let mut diff =
+            repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit_tree), Some(&mut opts))?;
+        options.find_similar(&mut diff)?;
+
+        Self::parse_diff(&diff, options)
+    }
 
-        Self::parse_diff(&diff)
+    /// Full id and parent ids of a commit
+    pub fn commit_parents(&self, commit_id: &str) -> AppResult<(String, Vec<String>)> {
+        let repo = self.lock_repo()?;
+        let commit = repo.revparse_single(commit_id)?.peel_to_commit()?;
+        let parents = commit.parent_ids().map(|id| id.to_string()).collect();
+        Ok((commit.id().to_string(), parents))
     }
 
     /// Get the commit log for a specific file.
@@ -797,7 +847,7 @@ impl LibGitOps {
     /// Get line-level change information for a file (HEAD vs working directory).
     pub fn line_changes(&self, path: &str) -> AppResult<Vec<LineChange>> {
         let repo = self.lock_repo()?;
-        let mut opts = DiffOptions::new();
+        let mut opts = git2::DiffOptions::new();
         opts.pathspec(path);
 
         // Diff HEAD tree to workdir to capture both staged and unstaged changes
@@ -969,4 +1019,667 @@ impl LibGitOps {
 
         Ok(result)
     }
+
+    // ========================================================================
+    // Revision diffs
+    // ========================================================================
+
+    /// Diff between two revisions, limited to `pathspecs` when any are given.
+    ///
+    /// Without `to`, `from` is a range: `A..B` diffs A against B, and `A...B`
+    /// diffs B against its merge base with A, showing only what changed on B
+    /// since the two forked, as `git diff A...B` does.
+    pub fn diff_revisions(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        pathspecs: &[String],
+        options: &DiffOptions,
+    ) -> AppResult<DiffOutput> {
+        let repo = self.lock_repo()?;
+        let diff = revision_diff(&repo, from, to, pathspecs, options)?;
+        Self::parse_diff(&diff, options)
+    }
+
+    /// Per-file line counts of `diff_revisions`, without building the lines
+    pub fn diff_revisions_stats(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        pathspecs: &[String],
+        options: &DiffOptions,
+    ) -> AppResult<DiffStatOutput> {
+        let repo = self.lock_repo()?;
+        let diff = revision_diff(&repo, from, to, pathspecs, options)?;
+
+        let mut files = Vec::new();
+        for (delta_idx, delta) in diff.deltas().enumerate() {
+            let (old_path, new_path) = delta_paths(&delta);
+            let is_binary = delta.old_file().is_binary() || delta.new_file().is_binary();
+            let (insertions, deletions) = match git2::Patch::from_diff(&diff, delta_idx)? {
+                Some(patch) if !is_binary => {
+                    let (_, insertions, deletions) = patch.line_stats()?;
+                    (insertions, deletions)
+                }
+                _ => (0, 0),
+            };
+            files.push(DiffFileStat {
+                old_path,
+                new_path,
+                status: delta_status(&delta),
+                is_binary,
+                insertions,
+                deletions,
+            });
+        }
+
+        Ok(DiffStatOutput {
+            stats: DiffStats {
+                files_changed: files.len(),
+                insertions: files.iter().map(|f| f.insertions).sum(),
+                deletions: files.iter().map(|f| f.deletions).sum(),
+            },
+            files,
+        })
+    }
+
+    // ========================================================================
+    // Interactive rebase
+    // ========================================================================
+
+    /// Default plan for rebasing HEAD onto `base`: every non-merge commit in
+    /// `base..HEAD`, oldest first, picked
+    pub fn rebase_todo(&self, base: &str) -> AppResult<Vec<RebaseStep>> {
+        let repo = self.lock_repo()?;
+        let base = repo.revparse_single(base)?.peel_to_commit()?;
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push_head()?;
+        revwalk.hide(base.id())?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+
+        let mut steps = Vec::new();
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            steps.push(RebaseStep {
+                action: RebaseAction::Pick,
+                commit_id: commit.id().to_string(),
+                summary: commit.summary().unwrap_or("").to_string(),
+                message: None,
+            });
+        }
+        Ok(steps)
+    }
+
+    /// Resolve a commit of the current history to rewrite from; fails for
+    /// commits HEAD does not contain
+    pub fn rewrite_target(&self, commit_id: &str) -> AppResult<RewriteTarget> {
+        let repo = self.lock_repo()?;
+        let commit = repo.revparse_single(commit_id)?.peel_to_commit()?;
+        let head = repo.head()?.peel_to_commit()?;
+        if commit.id() != head.id() && !repo.graph_descendant_of(head.id(), commit.id())? {
+            return Err(AppError::InvalidInput(format!(
+                "Commit {} is not part of the current branch",
+                commit_id
+            )));
+        }
+        Ok(RewriteTarget {
+            id: commit.id().to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+            message: commit.message().unwrap_or("").to_string(),
+            parent: commit.parent_ids().next().map(|id| id.to_string()),
+            is_merge: commit.parent_count() > 1,
+        })
+    }
+
+    /// Branches other than the current one, local or remote-tracking, that
+    /// contain `commit_id` and so share history rewritten from it
+    pub fn dependent_branches(&self, commit_id: &str) -> AppResult<Vec<String>> {
+        let repo = self.lock_repo()?;
+        let commit = git2::Oid::from_str(commit_id)?;
+        let head = repo.head()?;
+        let current = if head.is_branch() { head.name().map(str::to_string) } else { None };
+
+        let mut branches = Vec::new();
+        for branch in repo.branches(None)? {
+            let (branch, _) = branch?;
+            let reference = branch.get();
+            if current.is_some() && reference.name() == current.as_deref() {
+                continue;
+            }
+            let (Some(name), Some(tip)) = (reference.shorthand(), reference.target()) else {
+                continue;
+            };
+            // Symbolic refs such as origin/HEAD resolve to a branch listed anyway
+            if reference.symbolic_target().is_some() || name.ends_with("/HEAD") {
+                continue;
+            }
+            if tip == commit || repo.graph_descendant_of(tip, commit)? {
+                branches.push(name.to_string());
+            }
+        }
+        Ok(branches)
+    }
+
+    /// Write a copy of the current branch's history with a new message for
+    /// `commit_id`, which must be on its first-parent chain and not yet on
+    /// its upstream. Trees are unchanged, so the branch can be moved to the
+    /// result without touching the index or work tree.
+    ///
+    /// Returns the current branch's rewrite first, then one for each other
+    /// local branch pointing at a rewritten commit, as `rebase --update-refs`
+    /// would move them.
+    pub fn reword_commit(&self, commit_id: &str, message: &str) -> AppResult<Vec<HistoryRewrite>> {
+        let repo = self.lock_repo()?;
+        let (refname, head) = current_branch_tip(&repo)?;
+        let target = repo.revparse_single(commit_id)?.peel_to_commit()?;
+        ensure_not_upstream(&repo, &refname, target.id())?;
+        let descendants = first_parent_descendants(&repo, head, target.id())?;
+
+        let message = git2::message_prettify(message, None)?;
+        if message.is_empty() {
+            return Err(AppError::InvalidInput("Commit message is empty".to_string()));
+        }
+        let parents: Vec<git2::Commit> = target.parents().collect();
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+        let reworded = repo.commit(
+            None,
+            &target.author(),
+            &rewrite_committer(&repo, &target),
+            &message,
+            &target.tree()?,
+            &parent_refs,
+        )?;
+        let copies = replay_commits(&repo, &descendants, reworded)?;
+
+        let mut rewritten: HashMap<git2::Oid, git2::Oid> =
+            descendants.iter().copied().zip(copies).collect();
+        rewritten.insert(target.id(), reworded);
+        let mut rewrites = vec![HistoryRewrite {
+            refname: refname.clone(),
+            old_head: head.to_string(),
+            new_head: rewritten[&head].to_string(),
+        }];
+        for branch in repo.branches(Some(git2::BranchType::Local))? {
+            let (branch, _) = branch?;
+            let reference = branch.get();
+            let (Some(name), Some(tip)) = (reference.name(), reference.target()) else {
+                continue;
+            };
+            if name == refname || reference.symbolic_target().is_some() {
+                continue;
+            }
+            if let Some(new_tip) = rewritten.get(&tip) {
+                rewrites.push(HistoryRewrite {
+                    refname: name.to_string(),
+                    old_head: tip.to_string(),
+                    new_head: new_tip.to_string(),
+                });
+            }
+        }
+        Ok(rewrites)
+    }
+
+    /// Whether `base..HEAD`, or all of HEAD's history when `base` is `None`,
+    /// contains merge commits, which a plain interactive rebase would flatten
+    pub fn range_has_merges(&self, base: Option<&str>) -> AppResult<bool> {
+        let repo = self.lock_repo()?;
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push_head()?;
+        if let Some(base) = base {
+            revwalk.hide(repo.revparse_single(base)?.peel_to_commit()?.id())?;
+        }
+        for oid in revwalk {
+            if repo.find_commit(oid?)?.parent_count() > 1 {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Progress of the rebase in progress, if any
+    pub fn rebase_status(&self) -> AppResult<Option<RebaseStatus>> {
+        let repo = self.lock_repo()?;
+        let mut index = repo.index()?;
+        index.read(false)?;
+        Ok(rebase::read_status(repo.path(), index.has_conflicts()))
+    }
+
+    // ========================================================================
+    // Reflog & recovery
+    // ========================================================================
+
+    /// Reflog of `refname` (e.g. "HEAD", "refs/heads/main"), newest first
+    pub fn reflog(&self, refname: &str) -> AppResult<Vec<ReflogEntry>> {
+        let repo = self.lock_repo()?;
+        let reflog = repo.reflog(refname)?;
+        Ok(reflog
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let committer = entry.committer();
+                ReflogEntry {
+                    index,
+                    old_id: entry.id_old().to_string(),
+                    new_id: entry.id_new().to_string(),
+                    committer_name: committer.name().unwrap_or("").to_string(),
+                    committer_email: committer.email().unwrap_or("").to_string(),
+                    timestamp: committer.when().seconds(),
+                    message: entry.message().unwrap_or("").to_string(),
+                }
+            })
+            .collect())
+    }
+
+    /// HEAD and the local branches that have a reflog
+    pub fn reflog_refs(&self) -> AppResult<Vec<String>> {
+        let repo = self.lock_repo()?;
+        let mut refs = vec!["HEAD".to_string()];
+        for reference in repo.references_glob("refs/heads/*")? {
+            let reference = reference?;
+            if let Some(name) = reference.name() {
+                if repo.reflog(name).map(|log| !log.is_empty()).unwrap_or(false) {
+                    refs.push(name.to_string());
+                }
+            }
+        }
+        Ok(refs)
+    }
+
+    /// Commit info for the given ids, skipping any that are not commits
+    pub fn dangling_commits(&self, ids: &[String]) -> AppResult<Vec<DanglingCommit>> {
+        let repo = self.lock_repo()?;
+        let mut commits = Vec::new();
+        for id in ids {
+            let Ok(commit) = git2::Oid::from_str(id).and_then(|oid| repo.find_commit(oid)) else {
+                continue;
+            };
+            let message = commit.message().unwrap_or("");
+            // Stash commits merge the index (and untracked files) into HEAD
+            let is_stash = commit.parent_count() >= 2
+                && (message.starts_with("WIP on ") || message.starts_with("On "));
+            commits.push(DanglingCommit {
+                commit: CommitInfo {
+                    id: commit.id().to_string(),
+                    summary: commit.summary().unwrap_or("").to_string(),
+                    message: message.to_string(),
+                    author_name: commit.author().name().unwrap_or("").to_string(),
+                    author_email: commit.author().email().unwrap_or("").to_string(),
+                    timestamp: commit.time().seconds(),
+                    parent_ids: commit.parent_ids().map(|id| id.to_string()).collect(),
+                },
+                is_stash,
+            });
+        }
+        commits.sort_by_key(|c| std::cmp::Reverse(c.commit.timestamp));
+        Ok(commits)
+    }
+
+    // ========================================================================
+    // Operation journal
+    //
+    // These only write objects and refs under `refs/gitsage/`, never the
+    // index, the work tree or refs the user sees.
+    // ========================================================================
+
+    /// Files whose work tree content differs from the index, including
+    /// untracked files: what a discard throws away
+    pub fn discardable_paths(&self) -> AppResult<Vec<String>> {
+        let repo = self.lock_repo()?;
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+
+        let changed = git2::Status::WT_NEW
+            | git2::Status::WT_MODIFIED
+            | git2::Status::WT_DELETED
+            | git2::Status::WT_TYPECHANGE
+            | git2::Status::WT_RENAMED;
+        let statuses = repo.statuses(Some(&mut opts))?;
+        Ok(statuses
+            .iter()
+            .filter(|entry| entry.status().intersects(changed))
+            .filter_map(|entry| entry.path().map(str::to_string))
+            .collect())
+    }
+
+    /// Tracked files whose index or work tree content differs from HEAD:
+    /// what a hard reset throws away
+    pub fn uncommitted_paths(&self) -> AppResult<Vec<String>> {
+        let repo = self.lock_repo()?;
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(false);
+
+        let statuses = repo.statuses(Some(&mut opts))?;
+        let mut paths = Vec::new();
+        for entry in statuses.iter() {
+            if entry.status().is_ignored() {
+                continue;
+            }
+            if let Some(path) = entry.path() {
+                paths.push(path.to_string());
+            }
+            // The old side of a staged rename is restored by the reset too
+            let old = entry
+                .head_to_index()
+                .and_then(|delta| delta.old_file().path().map(|p| p.to_path_buf()));
+            if let Some(old) = old {
+                paths.push(old.to_string_lossy().replace('\\', "/"));
+            }
+        }
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
+
+    /// Commit the work tree content of `paths` on top of HEAD. Files missing
+    /// from the work tree are left out of the snapshot and listed as removed.
+    pub fn snapshot_workdir(&self, paths: &[String]) -> AppResult<WorkdirSnapshot> {
+        let repo = self.lock_repo()?;
+        let workdir = repo
+            .workdir()
+            .ok_or_else(|| AppError::General("Repository has no working directory".to_string()))?
+            .to_path_buf();
+        let base = match repo.head().ok().and_then(|h| h.peel_to_tree().ok()) {
+            Some(tree) => tree,
+            None => repo.find_tree(repo.treebuilder(None)?.write()?)?,
+        };
+
+        let mut update = git2::build::TreeUpdateBuilder::new();
+        let mut snapshot = WorkdirSnapshot {
+            commit: String::new(),
+            paths: Vec::new(),
+            removed: Vec::new(),
+        };
+        for path in paths {
+            let full = workdir.join(path);
+            match std::fs::symlink_metadata(&full) {
+                Ok(meta) if meta.file_type().is_symlink() => {
+                    let target = std::fs::read_link(&full)?;
+                    let oid = repo.blob(target.to_string_lossy().as_bytes())?;
+                    update.upsert(path, oid, git2::FileMode::Link);
+                    snapshot.paths.push(path.clone());
+                }
+                Ok(meta) if meta.is_file() => {
+                    let oid = repo.blob_path(&full)?;
+                    update.upsert(path, oid, file_mode(&meta));
+                    snapshot.paths.push(path.clone());
+                }
+                _ => {
+                    if base.get_path(Path::new(path)).is_ok() {
+                        update.remove(path);
+                    }
+                    snapshot.removed.push(path.clone());
+                }
+            }
+        }
+
+        let tree = repo.find_tree(update.create_updated(&repo, &base)?)?;
+        snapshot.commit = snapshot_commit(&repo, &tree, "gitsage: work tree snapshot")?;
+        Ok(snapshot)
+    }
+
+    /// Commit the current index on top of HEAD
+    pub fn snapshot_index(&self) -> AppResult<String> {
+        let repo = self.lock_repo()?;
+        let mut index = repo.index()?;
+        // The handle caches the index; pick up changes made by the git CLI
+        index.read(false)?;
+        let tree_oid = index.write_tree()?;
+        let tree = repo.find_tree(tree_oid)?;
+        snapshot_commit(&repo, &tree, "gitsage: index snapshot")
+    }
+
+    /// The branch HEAD is on (`None` when detached) and the commit it points at
+    pub fn head_state(&self) -> AppResult<(Option<String>, String)> {
+        let repo = self.lock_repo()?;
+        let head = repo.head()?;
+        let branch = if head.is_branch() {
+            head.shorthand().map(str::to_string)
+        } else {
+            None
+        };
+        let commit = head.peel_to_commit()?.id().to_string();
+        Ok((branch, commit))
+    }
+
+    /// All stash entries, newest first
+    pub fn stash_commits(&self) -> AppResult<Vec<DroppedStash>> {
+        let mut repo = self.lock_repo()?;
+        let mut stashes = Vec::new();
+        repo.stash_foreach(|_index, message, oid| {
+            stashes.push(DroppedStash {
+                commit: oid.to_string(),
+                message: message.to_string(),
+            });
+            true
+        })?;
+        Ok(stashes)
+    }
+
+    /// Target of a ref, and the upstream of a local branch
+    pub fn ref_state(&self, refname: &str) -> AppResult<(String, Option<String>)> {
+        let repo = self.lock_repo()?;
+        let reference = repo.find_reference(refname)?;
+        let target = reference
+            .target()
+            .ok_or_else(|| AppError::General(format!("Reference '{}' has no target", refname)))?;
+
+        let upstream = if reference.is_branch() {
+            git2::Branch::wrap(reference)
+                .upstream()
+                .ok()
+                .and_then(|upstream| upstream.name().ok().flatten().map(str::to_string))
+        } else {
+            None
+        };
+        Ok((target.to_string(), upstream))
+    }
+
+    /// Keep the objects of a journal entry reachable
+    pub fn anchor_journal_objects(&self, entry_id: i64, objects: &[String]) -> AppResult<()> {
+        let repo = self.lock_repo()?;
+        for (n, object) in objects.iter().enumerate() {
+            let oid = git2::Oid::from_str(object)?;
+            let name = format!("{}/{}/{}", ANCHOR_PREFIX, entry_id, n);
+            repo.reference(&name, oid, true, "gitsage: journal entry")?;
+        }
+        Ok(())
+    }
+
+    /// Release the objects of journal entries
+    pub fn drop_journal_anchors(&self, entry_ids: &[i64]) -> AppResult<()> {
+        let repo = self.lock_repo()?;
+        for entry_id in entry_ids {
+            let glob = format!("{}/{}/*", ANCHOR_PREFIX, entry_id);
+            for reference in repo.references_glob(&glob)? {
+                reference?.delete()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Old and new path of a diff delta
+fn delta_paths(delta: &git2::DiffDelta) -> (Option<String>, Option<String>) {
+    let path = |file: git2::DiffFile| file.path().map(|p| p.to_string_lossy().to_string());
+    (path(delta.old_file()), path(delta.new_file()))
+}
+
+fn delta_status(delta: &git2::DiffDelta) -> DiffFileStatus {
+    match delta.status() {
+        git2::Delta::Added | git2::Delta::Untracked => DiffFileStatus::Added,
+        git2::Delta::Deleted => DiffFileStatus::Deleted,
+        git2::Delta::Renamed => DiffFileStatus::Renamed,
+        git2::Delta::Copied => DiffFileStatus::Copied,
+        git2::Delta::Typechange => DiffFileStatus::TypeChanged,
+        _ => DiffFileStatus::Modified,
+    }
+}
+
+/// Tree diff behind `LibGitOps::diff_revisions`
+fn revision_diff<'r>(
+    repo: &'r Repository,
+    from: &str,
+    to: Option<&str>,
+    pathspecs: &[String],
+    options: &DiffOptions,
+) -> AppResult<git2::Diff<'r>> {
+    let (old, new) = match to {
+        Some(to) => (revision_tree(repo, from)?, revision_tree(repo, to)?),
+        None => {
+            let spec = repo.revparse(from).map_err(|_| unknown_revision(from))?;
+            let (Some(old), Some(new)) = (spec.from(), spec.to()) else {
+                return Err(AppError::InvalidInput(format!(
+                    "Expected a range like A..B or A...B, got '{}'",
+                    from
+                )));
+            };
+            let new_commit = new.peel_to_commit()?;
+            let old_tree = if spec.mode().contains(git2::RevparseMode::MERGE_BASE) {
+                let base = repo
+                    .merge_base(old.peel_to_commit()?.id(), new_commit.id())
+                    .map_err(|_| {
+                        AppError::InvalidInput(format!("'{}' has no merge base", from))
+                    })?;
+                repo.find_commit(base)?.tree()?
+            } else {
+                old.peel_to_tree()?
+            };
+            (old_tree, new_commit.tree()?)
+        }
+    };
+
+    let mut opts = options.to_git2();
+    for pathspec in pathspecs {
+        opts.pathspec(pathspec);
+    }
+    let mut diff = repo.diff_tree_to_tree(Some(&old), Some(&new), Some(&mut opts))?;
+    options.find_similar(&mut diff)?;
+    Ok(diff)
+}
+
+/// Tree of any revision naming a commit, tag or tree
+fn revision_tree<'r>(repo: &'r Repository, rev: &str) -> AppResult<git2::Tree<'r>> {
+    let object = repo.revparse_single(rev).map_err(|_| unknown_revision(rev))?;
+    Ok(object.peel_to_tree()?)
+}
+
+fn unknown_revision(rev: &str) -> AppError {
+    AppError::InvalidInput(format!("Unknown revision '{}'", rev))
+}
+
+/// Full name and tip of the checked-out branch
+fn current_branch_tip(repo: &Repository) -> AppResult<(String, git2::Oid)> {
+    let head = repo.head()?;
+    if !head.is_branch() {
+        return Err(AppError::RepoState(
+            "HEAD is detached; check out a branch to rewrite its history".to_string(),
+        ));
+    }
+    let refname = head.name().unwrap_or("").to_string();
+    let tip = head
+        .target()
+        .ok_or_else(|| AppError::General(format!("Reference '{}' has no target", refname)))?;
+    Ok((refname, tip))
+}
+
+/// Refuse to rewrite a commit the branch's upstream already contains
+fn ensure_not_upstream(repo: &Repository, refname: &str, commit: git2::Oid) -> AppResult<()> {
+    let branch = git2::Branch::wrap(repo.find_reference(refname)?);
+    let Ok(upstream) = branch.upstream() else {
+        return Ok(());
+    };
+    if let Some(tip) = upstream.get().target() {
+        if tip == commit || repo.graph_descendant_of(tip, commit)? {
+            let name = upstream.name().ok().flatten().unwrap_or("the upstream").to_string();
+            return Err(AppError::RepoState(format!(
+                "Commit {} is already on {}; rewriting it would diverge from the remote",
+                commit, name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Commits on the first-parent chain from `head` down to, not including,
+/// `target`, oldest first
+fn first_parent_descendants(
+    repo: &Repository,
+    head: git2::Oid,
+    target: git2::Oid,
+) -> AppResult<Vec<git2::Oid>> {
+    let mut chain = Vec::new();
+    let mut current = head;
+    while current != target {
+        chain.push(current);
+        current = repo.find_commit(current)?.parent_id(0).map_err(|_| {
+            AppError::InvalidInput(format!(
+                "Commit {} is not in the first-parent history of the current branch",
+                target
+            ))
+        })?;
+    }
+    chain.reverse();
+    Ok(chain)
+}
+
+/// Recreate `commits`, oldest first, each on top of the copy of its first
+/// parent, starting from `base`. Trees, other parents, authors and messages
+/// are kept. Returns the copies in the same order.
+fn replay_commits(
+    repo: &Repository,
+    commits: &[git2::Oid],
+    base: git2::Oid,
+) -> AppResult<Vec<git2::Oid>> {
+    let mut copies = Vec::with_capacity(commits.len());
+    let mut tip = base;
+    for &oid in commits {
+        let commit = repo.find_commit(oid)?;
+        let mut parents = vec![repo.find_commit(tip)?];
+        parents.extend(commit.parents().skip(1));
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+        tip = repo.commit(
+            None,
+            &commit.author(),
+            &rewrite_committer(repo, &commit),
+            &String::from_utf8_lossy(commit.message_bytes()),
+            &commit.tree()?,
+            &parent_refs,
+        )?;
+        copies.push(tip);
+    }
+    Ok(copies)
+}
+
+/// Committer of a rewritten commit: the user, as git does when rebasing
+fn rewrite_committer(repo: &Repository, commit: &git2::Commit) -> git2::Signature<'static> {
+    repo.signature().unwrap_or_else(|_| commit.committer().to_owned())
+}
+
+/// Commit `tree` with HEAD (if any) as parent, without updating any ref
+fn snapshot_commit(repo: &Repository, tree: &git2::Tree, message: &str) -> AppResult<String> {
+    let signature = repo
+        .signature()
+        .or_else(|_| git2::Signature::now("GitSage", "gitsage@localhost"))?;
+    let head = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    let parents: Vec<&git2::Commit> = head.iter().collect();
+    let oid = repo.commit(None, &signature, &signature, message, tree, &parents)?;
+    Ok(oid.to_string())
+}
+
+#[cfg(unix)]
+fn file_mode(meta: &std::fs::Metadata) -> git2::FileMode {
+    use std::os::unix::fs::PermissionsExt;
+    if meta.permissions().mode() & 0o111 != 0 {
+        git2::FileMode::BlobExecutable
+    } else {
+        git2::FileMode::Blob
+    }
+}
+
+#[cfg(not(unix))]
+fn file_mode(_meta: &std::fs::Metadata) -> git2::FileMode {
+    git2::FileMode::Blob
 }
@@ -1,5 +1,12 @@
+pub mod askpass;
+pub mod classify;
 pub mod cli;
+pub mod combined;
+pub mod credentials;
 pub mod diff;
+pub mod journal;
 pub mod libgit;
 pub mod patch;
+pub mod progress;
+pub mod rebase;
 pub mod repository;
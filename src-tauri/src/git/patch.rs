@@ -1,123 +1,196 @@
-use crate::git::diff::{DiffHunk, DiffLineType};
+use crate::error::{AppError, AppResult};
+use crate::git::diff::{DiffFile, DiffFileStatus, DiffHunk, DiffLineType, NO_NEWLINE_MARKER};
 
-/// Generate a unified diff patch for a single hunk
-pub fn generate_hunk_patch(file_path: &str, hunk: &DiffHunk, reverse: bool) -> String {
-    let mut patch = String::new();
+/// What a hunk or line selection is applied to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchTarget {
+    /// Work tree changes into the index
+    Stage,
+    /// Staged changes back out of the index
+    Unstage,
+    /// Work tree changes thrown away
+    Discard,
+}
 
-    // Diff header
-    patch.push_str(&format!("--- a/{}\n", file_path));
-    patch.push_str(&format!("+++ b/{}\n", file_path));
+impl PatchTarget {
+    /// Whether the selection comes from the staged diff
+    pub fn staged(self) -> bool {
+        self == PatchTarget::Unstage
+    }
 
-    // Hunk header
-    if reverse {
-        patch.push_str(&format!(
-            "@@ -{},{} +{},{} @@\n",
-            hunk.new_start, hunk.new_lines, hunk.old_start, hunk.old_lines
-        ));
-    } else {
-        patch.push_str(&format!(
-            "@@ -{},{} +{},{} @@\n",
-            hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
-        ));
+    /// Whether the patch is applied to the index rather than the work tree
+    pub fn cached(self) -> bool {
+        self != PatchTarget::Discard
     }
 
-    // Lines
-    for line in &hunk.lines {
-        match (&line.origin, reverse) {
-            (DiffLineType::Context, _) => {
-                patch.push(' ');
-                patch.push_str(&line.content);
-            }
-            (DiffLineType::Addition, false) | (DiffLineType::Deletion, true) => {
-                patch.push('+');
-                patch.push_str(&line.content);
-            }
-            (DiffLineType::Deletion, false) | (DiffLineType::Addition, true) => {
-                patch.push('-');
-                patch.push_str(&line.content);
-            }
-            (DiffLineType::Header, _) => {}
-        }
-        if !line.content.ends_with('\n') {
-            patch.push('\n');
-        }
+    /// Whether the patch takes changes out rather than adding them
+    pub fn reverse(self) -> bool {
+        self != PatchTarget::Stage
     }
+}
 
-    patch
+/// Generate a patch for a whole hunk, see `generate_line_patch`
+pub fn generate_hunk_patch(file: &DiffFile, hunk: &DiffHunk, reverse: bool) -> AppResult<String> {
+    let all_lines: Vec<usize> = (0..hunk.lines.len()).collect();
+    generate_line_patch(file, hunk, &all_lines, reverse)
 }
 
-/// Generate a patch for selected lines within a hunk
+/// Generate a patch for selected lines within a hunk.
+///
+/// `reverse` says the patch will be applied with `--reverse`, to take the
+/// selected changes back out of the side of the diff that has them (unstaging,
+/// discarding). The patch always has to match the content it is applied to:
+///
+/// - forward, that is the old side, so unselected additions are omitted and
+///   unselected deletions become context;
+/// - reversed, that is the new side, so unselected additions become context
+///   and unselected deletions are omitted.
 pub fn generate_line_patch(
-    file_path: &str,
+    file: &DiffFile,
     hunk: &DiffHunk,
     selected_lines: &[usize],
     reverse: bool,
-) -> String {
-    let mut patch = String::new();
-
-    // Diff header
-    patch.push_str(&format!("--- a/{}\n", file_path));
-    patch.push_str(&format!("+++ b/{}\n", file_path));
+) -> AppResult<String> {
+    if file.is_binary {
+        return Err(AppError::InvalidInput(
+            "Binary files cannot be staged by line".to_string(),
+        ));
+    }
 
-    // Collect lines and compute new hunk ranges
     let mut old_count: u32 = 0;
     let mut new_count: u32 = 0;
-    let mut lines_output = Vec::new();
+    let mut has_changes = false;
+    // Set after a context line that ends its file without a newline
+    let mut at_end_of_file = false;
+    let mut body = String::new();
 
     for (idx, line) in hunk.lines.iter().enumerate() {
         let is_selected = selected_lines.contains(&idx);
-
-        match (&line.origin, reverse, is_selected) {
-            (DiffLineType::Context, _, _) => {
-                old_count += 1;
-                new_count += 1;
-                lines_output.push(format!(" {}", line.content));
-            }
-            (DiffLineType::Addition, false, true) | (DiffLineType::Deletion, true, true) => {
-                new_count += 1;
-                lines_output.push(format!("+{}", line.content));
+        let prefix = match (&line.origin, is_selected, reverse) {
+            (DiffLineType::Context, _, _) => ' ',
+            (DiffLineType::Addition, true, _) => '+',
+            (DiffLineType::Deletion, true, _) => '-',
+            (DiffLineType::Addition, false, false) | (DiffLineType::Deletion, false, true) => {
+                continue
             }
-            (DiffLineType::Addition, false, false) | (DiffLineType::Deletion, true, false) => {
-                // Not selected addition: treat as context in old (skip in new side)
-                // Actually for partial staging, unselected additions should be omitted
-                // and unselected deletions should be treated as context
-                old_count += 1;
-                new_count += 1;
-                lines_output.push(format!(" {}", line.content));
-            }
-            (DiffLineType::Deletion, false, true) | (DiffLineType::Addition, true, true) => {
-                old_count += 1;
-                lines_output.push(format!("-{}", line.content));
-            }
-            (DiffLineType::Deletion, false, false) | (DiffLineType::Addition, true, false) => {
-                // Not selected deletion: keep as context
+            (DiffLineType::Addition, false, true) | (DiffLineType::Deletion, false, false) => ' ',
+            (DiffLineType::Header, _, _) => continue,
+        };
+        if at_end_of_file {
+            // Only the change to the unterminated last line can be followed by
+            // more lines, so it has to be part of the selection
+            return Err(AppError::InvalidInput(
+                "The selection adds lines after a last line that has no trailing newline; \
+                 select the change to that line too"
+                    .to_string(),
+            ));
+        }
+        at_end_of_file = prefix == ' ' && !line.content.ends_with('\n');
+        match prefix {
+            '+' => new_count += 1,
+            '-' => old_count += 1,
+            _ => {
                 old_count += 1;
                 new_count += 1;
-                lines_output.push(format!(" {}", line.content));
             }
-            (DiffLineType::Header, _, _) => {}
+        }
+        has_changes |= prefix != ' ';
+        body.push(prefix);
+        body.push_str(&line.content);
+        if !line.content.ends_with('\n') {
+            body.push_str(NO_NEWLINE_MARKER);
         }
     }
 
-    // Hunk header with computed ranges
-    if reverse {
-        patch.push_str(&format!(
-            "@@ -{},{} +{},{} @@\n",
-            hunk.new_start, new_count, hunk.old_start, old_count
+    if !has_changes {
+        return Err(AppError::InvalidInput("No changed lines selected".to_string()));
+    }
+
+    // A file only exists on one side of the patch when the selection covers
+    // all of an added or deleted file
+    let creates = file.status == DiffFileStatus::Added && old_count == 0;
+    let deletes = file.status == DiffFileStatus::Deleted && new_count == 0;
+
+    // `git apply` locates the hunk by the start of the side it matches
+    // against: the old side, or the new side when reversed. Everything before
+    // the hunk is the same on both sides of the patch.
+    let start = if reverse { hunk.new_start } else { hunk.old_start };
+    let old_start = if creates { 0 } else { start };
+    let new_start = if deletes { 0 } else { start };
+
+    let mut patch = file_header(file, creates, deletes, reverse);
+    patch.push_str(&format!(
+        "@@ -{},{} +{},{} @@\n",
+        old_start, old_count, new_start, new_count
+    ));
+    patch.push_str(&body);
+    Ok(patch)
+}
+
+/// `diff --git` header for `file`, as a creation or deletion when the patch
+/// adds or removes the whole file.
+///
+/// A reversed patch only takes lines back out of the renamed or copied file,
+/// so it names the new path on both sides and leaves the rename in place.
+fn file_header(file: &DiffFile, creates: bool, deletes: bool, reverse: bool) -> String {
+    let new_path = file.display_path();
+    let old_path = match file.old_path.as_deref() {
+        Some(old_path) if !reverse => old_path,
+        _ => new_path,
+    };
+    let (old, new) = (quote_path("a/", old_path), quote_path("b/", new_path));
+
+    let mut header = format!("diff --git {} {}\n", old, new);
+    if creates {
+        header.push_str(&format!("new file mode {:o}\n", file_mode(file.new_mode)));
+    } else if deletes {
+        header.push_str(&format!("deleted file mode {:o}\n", file_mode(file.old_mode)));
+    }
+    if old_path != new_path {
+        let kind = if file.status == DiffFileStatus::Copied { "copy" } else { "rename" };
+        header.push_str(&format!(
+            "{kind} from {}\n{kind} to {}\n",
+            quote_path("", old_path),
+            quote_path("", new_path)
         ));
+    }
+    let old = if creates { "/dev/null".to_string() } else { old };
+    let new = if deletes { "/dev/null".to_string() } else { new };
+    header.push_str(&format!("--- {}\n+++ {}\n", old, new));
+    header
+}
+
+/// Mode to write for a created or deleted file; untracked files have none
+/// recorded yet
+fn file_mode(mode: u32) -> u32 {
+    if mode == 0 {
+        0o100644
     } else {
-        patch.push_str(&format!(
-            "@@ -{},{} +{},{} @@\n",
-            hunk.old_start, old_count, hunk.new_start, new_count
-        ));
+        mode
     }
+}
 
-    for line_str in &lines_output {
-        patch.push_str(line_str);
-        if !line_str.ends_with('\n') {
-            patch.push('\n');
+/// Path as git writes it in patch headers: C-quoted when it contains
+/// characters that would otherwise be ambiguous
+fn quote_path(prefix: &str, path: &str) -> String {
+    let needs_quoting = path
+        .chars()
+        .any(|c| c == '"' || c == '\\' || c.is_control());
+    if !needs_quoting {
+        return format!("{}{}", prefix, path);
+    }
+    let mut quoted = format!("\"{}", prefix);
+    for c in path.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\t' => quoted.push_str("\\t"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => quoted.push_str(&format!("\\{:03o}", c as u32)),
+            c => quoted.push(c),
         }
     }
-
-    patch
+    quoted.push('"');
+    quoted
 }
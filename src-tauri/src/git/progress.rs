@@ -0,0 +1,127 @@
+//! Parsing of git's `--progress` output
+//!
+//! Network commands write progress to stderr as lines such as
+//! `Receiving objects:  45% (4500/10000), 1.20 MiB | 2.00 MiB/s`, redrawn in
+//! place with `\r` until the phase ends with `, done.`.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::Serialize;
+
+/// One progress update of a git network operation
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct GitProgress {
+    /// Reported by the remote (`remote: Counting objects: ...`)
+    pub remote: bool,
+    /// e.g. "Receiving objects", "Resolving deltas", "Writing objects"
+    pub phase: String,
+    pub percent: Option<u8>,
+    pub current: Option<u64>,
+    pub total: Option<u64>,
+    /// Transferred size and rate, e.g. "1.20 MiB | 2.00 MiB/s"
+    pub transfer: Option<String>,
+    /// The phase has finished
+    pub done: bool,
+}
+
+/// Callback receiving progress updates while a git command runs
+pub type ProgressCallback = dyn Fn(GitProgress) + Send + Sync;
+
+static PROGRESS_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?P<remote>remote: )?(?P<phase>[A-Za-z][A-Za-z ]*?):\s+(?:(?P<percent>\d{1,3})% \((?P<current>\d+)/(?P<total>\d+)\)|(?P<count>\d+))(?:, (?P<transfer>[^,]+?\|[^,]+?))?(?P<done>, done\.?)?\s*$",
+    )
+    .expect("progress regex must compile")
+});
+
+/// Parse a single stderr line; `None` for anything that isn't a progress line
+pub fn parse_progress_line(line: &str) -> Option<GitProgress> {
+    let caps = PROGRESS_LINE.captures(line.trim())?;
+    let number = |name: &str| caps.name(name).and_then(|m| m.as_str().parse::<u64>().ok());
+
+    Some(GitProgress {
+        remote: caps.name("remote").is_some(),
+        phase: caps["phase"].to_string(),
+        percent: caps.name("percent").and_then(|m| m.as_str().parse().ok()),
+        current: number("current").or_else(|| number("count")),
+        total: number("total"),
+        transfer: caps.name("transfer").map(|m| m.as_str().trim().to_string()),
+        done: caps.name("done").is_some(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_line() {
+        let progress = parse_progress_line("Receiving objects:  45% (4500/10000)").unwrap();
+        assert_eq!(
+            progress,
+            GitProgress {
+                remote: false,
+                phase: "Receiving objects".into(),
+                percent: Some(45),
+                current: Some(4500),
+                total: Some(10000),
+                transfer: None,
+                done: false,
+            }
+        );
+    }
+
+    #[test]
+    fn count_only_line() {
+        let progress = parse_progress_line("Enumerating objects: 52, done.").unwrap();
+        assert_eq!(progress.phase, "Enumerating objects");
+        assert_eq!(progress.percent, None);
+        assert_eq!(progress.current, Some(52));
+        assert_eq!(progress.total, None);
+        assert!(progress.done);
+    }
+
+    #[test]
+    fn remote_line() {
+        let progress = parse_progress_line("remote: Counting objects:  37% (10/27)").unwrap();
+        assert!(progress.remote);
+        assert_eq!(progress.phase, "Counting objects");
+        assert_eq!(progress.percent, Some(37));
+        assert_eq!((progress.current, progress.total), (Some(10), Some(27)));
+        assert!(!progress.done);
+
+        let progress = parse_progress_line("remote: Compressing objects: 100% (20/20), done.");
+        assert!(progress.is_some_and(|p| p.remote && p.done));
+    }
+
+    #[test]
+    fn transfer_rate_and_done() {
+        let line = "Receiving objects: 100% (10000/10000), 5.12 MiB | 3.40 MiB/s, done.\r";
+        let progress = parse_progress_line(line).unwrap();
+        assert_eq!(progress.phase, "Receiving objects");
+        assert_eq!(progress.percent, Some(100));
+        assert_eq!(progress.transfer.as_deref(), Some("5.12 MiB | 3.40 MiB/s"));
+        assert!(progress.done);
+
+        let line = "Writing objects:  60% (3/5), 1.00 KiB | 1.00 KiB/s";
+        let progress = parse_progress_line(line).unwrap();
+        assert_eq!(progress.transfer.as_deref(), Some("1.00 KiB | 1.00 KiB/s"));
+        assert!(!progress.done);
+    }
+
+    #[test]
+    fn other_lines_are_not_progress() {
+        for line in [
+            "",
+            "To github.com:owner/repo.git",
+            " * [new tag]         v1.0.0 -> v1.0.0",
+            "Total 3 (delta 1), reused 0 (delta 0), pack-reused 0",
+            "fatal: unable to access 'https://example.com/': Could not resolve host",
+            "remote: Create a pull request for 'feature' on GitHub by visiting:",
+            "hint: Updates were rejected because the tip of your current branch is behind",
+        ] {
+            assert_eq!(parse_progress_line(line), None, "{line}");
+        }
+    }
+}
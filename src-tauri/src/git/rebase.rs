@@ -0,0 +1,567 @@
+//! Interactive rebase plans and progress
+//!
+//! A plan is written as a git todo list and handed to `git rebase -i` through
+//! `GIT_SEQUENCE_EDITOR`. New commit messages cannot be written inline in a
+//! todo list, so each one goes to a file under `<git dir>/gitsage-rebase/`
+//! and is applied by an `exec git commit --amend` line after its step.
+//!
+//! Progress is read back from `<git dir>/rebase-merge/`, which git keeps up
+//! to date while a rebase runs or is paused.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+/// Directory holding the message files of the running plan
+pub const MESSAGE_DIR: &str = "gitsage-rebase";
+
+/// What to do with a commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RebaseAction {
+    Pick,
+    /// Pick with a new message
+    Reword,
+    /// Pick, then pause so the commit can be amended
+    Edit,
+    /// Meld into the previous commit, combining messages
+    Squash,
+    /// Meld into the previous commit, keeping its message
+    Fixup,
+    Drop,
+}
+
+impl RebaseAction {
+    fn todo_command(self) -> &'static str {
+        match self {
+            // Rewording is a pick followed by an amend, see `todo_list`
+            RebaseAction::Pick | RebaseAction::Reword => "pick",
+            RebaseAction::Edit => "edit",
+            RebaseAction::Squash => "squash",
+            RebaseAction::Fixup => "fixup",
+            RebaseAction::Drop => "drop",
+        }
+    }
+
+    fn parse(command: &str) -> Option<Self> {
+        Some(match command {
+            "p" | "pick" => RebaseAction::Pick,
+            "r" | "reword" => RebaseAction::Reword,
+            "e" | "edit" => RebaseAction::Edit,
+            "s" | "squash" => RebaseAction::Squash,
+            "f" | "fixup" => RebaseAction::Fixup,
+            "d" | "drop" => RebaseAction::Drop,
+            _ => return None,
+        })
+    }
+}
+
+/// One line of a rebase plan. Steps run in order, so reordering the plan
+/// reorders the commits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RebaseStep {
+    pub action: RebaseAction,
+    pub commit_id: String,
+    pub summary: String,
+    /// New message for the resulting commit. Required for `Reword`; on a
+    /// `Squash` it replaces the combined message.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Why a rebase is waiting for the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RebasePause {
+    /// A step did not apply cleanly; resolve, then continue
+    Conflict,
+    /// An `edit` step stopped so the commit can be amended
+    Edit,
+    /// Stopped for another reason, e.g. a failed `exec`
+    Other,
+}
+
+/// Progress of the rebase in progress
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RebaseStatus {
+    /// Branch being rebased, `None` when HEAD was detached
+    pub branch: Option<String>,
+    pub onto: String,
+    /// 1-based number of the current step
+    pub current: usize,
+    pub total: usize,
+    /// Commit the rebase stopped at
+    pub stopped_at: Option<String>,
+    pub pause: Option<RebasePause>,
+    /// Steps not yet run
+    pub remaining: Vec<RebaseStep>,
+}
+
+/// Kind of commit that `git rebase --autosquash` melds into an earlier one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FixupKind {
+    /// `fixup!`: add the changes, keep the target's message
+    Fixup,
+    /// `squash!`: add the changes and append a message to the target's
+    Squash,
+    /// `amend!`: add the changes, if any, and replace the target's message
+    Amend,
+}
+
+/// A commit that history is rewritten from
+#[derive(Debug, Clone)]
+pub struct RewriteTarget {
+    pub id: String,
+    pub summary: String,
+    pub message: String,
+    /// `None` for a root commit
+    pub parent: Option<String>,
+    pub is_merge: bool,
+}
+
+/// A branch moved to rewritten history, for updating the ref with a
+/// compare-and-swap on the old tip
+#[derive(Debug, Clone)]
+pub struct HistoryRewrite {
+    pub refname: String,
+    pub old_head: String,
+    pub new_head: String,
+}
+
+/// A commit being split into several: its changes were rewound into the work
+/// tree by an interactive rebase that is paused until the split is finished
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitStatus {
+    /// The commit being split
+    pub commit_id: String,
+    /// Its message, to start the new messages from
+    pub message: String,
+    #[serde(skip_deserializing)]
+    pub rebase: Option<RebaseStatus>,
+}
+
+/// Marker for a split in progress, next to the plan's message files
+pub const SPLIT_MARKER: &str = "split.json";
+
+/// Outcome of creating a fixup commit and autosquashing it
+#[derive(Debug, Clone, Serialize)]
+pub struct AutosquashResult {
+    /// The `fixup!`/`squash!`/`amend!` commit, before it was melded in
+    pub commit_id: String,
+    /// Other branches, local or remote-tracking, that contain the rewritten
+    /// commits and still point at the old history
+    pub dependent_branches: Vec<String>,
+    /// Set when the rebase stopped before finishing
+    pub rebase: Option<RebaseStatus>,
+}
+
+/// Check a plan before running it
+pub fn validate_plan(steps: &[RebaseStep]) -> AppResult<()> {
+    if steps.is_empty() {
+        return Err(AppError::InvalidInput("The rebase plan is empty".to_string()));
+    }
+    let mut has_previous = false;
+    for step in steps {
+        if step.commit_id.is_empty() || !step.commit_id.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid commit id in rebase plan: {}",
+                step.commit_id
+            )));
+        }
+        match step.action {
+            RebaseAction::Reword if step.message.as_deref().is_none_or(|m| m.trim().is_empty()) => {
+                return Err(AppError::InvalidInput(format!(
+                    "Rewording {} needs a message",
+                    step.commit_id
+                )));
+            }
+            RebaseAction::Squash | RebaseAction::Fixup if !has_previous => {
+                return Err(AppError::InvalidInput(format!(
+                    "Cannot squash {} without a previous commit",
+                    step.commit_id
+                )));
+            }
+            _ => {}
+        }
+        has_previous |= step.action != RebaseAction::Drop;
+    }
+    Ok(())
+}
+
+/// Check that a plan covers exactly the commits of `todo`, the default plan
+/// for the same base. Rebasing runs with `rebase.missingCommitsCheck=ignore`,
+/// so a commit left out would otherwise be dropped without a word.
+pub fn validate_plan_commits(steps: &[RebaseStep], todo: &[RebaseStep]) -> AppResult<()> {
+    let expected: HashSet<&str> = todo.iter().map(|s| s.commit_id.as_str()).collect();
+    let mut seen = HashSet::new();
+    for step in steps {
+        if !expected.contains(step.commit_id.as_str()) {
+            return Err(AppError::InvalidInput(format!(
+                "Commit {} is not between the base and HEAD",
+                step.commit_id
+            )));
+        }
+        if !seen.insert(step.commit_id.as_str()) {
+            return Err(AppError::InvalidInput(format!(
+                "Commit {} appears more than once in the rebase plan",
+                step.commit_id
+            )));
+        }
+    }
+    if let Some(missing) = todo.iter().find(|s| !seen.contains(s.commit_id.as_str())) {
+        return Err(AppError::InvalidInput(format!(
+            "Commit {} is missing from the rebase plan; drop it explicitly to remove it",
+            missing.commit_id
+        )));
+    }
+    Ok(())
+}
+
+/// Render a plan as a git todo list, returning it with the message files to
+/// write first as `(path, message)` pairs
+pub fn todo_list(steps: &[RebaseStep], git_dir: &Path) -> (String, Vec<(PathBuf, String)>) {
+    let message_dir = git_dir.join(MESSAGE_DIR);
+    let mut todo = String::new();
+    let mut messages = Vec::new();
+    // Latest message in the current squash/fixup chain, with its step index
+    let mut pending: Option<(usize, &String)> = None;
+    for (i, step) in steps.iter().enumerate() {
+        let summary = step.summary.replace(['\r', '\n'], " ");
+        todo.push_str(&format!("{} {} {}\n", step.action.todo_command(), step.commit_id, summary));
+        if step.action == RebaseAction::Drop {
+            continue;
+        }
+        if let Some(message) = &step.message {
+            pending = Some((i, message));
+        }
+        // Amend only once the squash/fixup chain that follows has run
+        let chain_continues = steps[i + 1..]
+            .iter()
+            .find(|s| s.action != RebaseAction::Drop)
+            .is_some_and(|s| matches!(s.action, RebaseAction::Squash | RebaseAction::Fixup));
+        if chain_continues {
+            continue;
+        }
+        let Some((index, message)) = pending.take() else {
+            continue;
+        };
+        let path = message_dir.join(format!("message-{}", index));
+        todo.push_str(&format!(
+            "exec git commit --amend --only --allow-empty --no-verify --cleanup=strip -F {}\n",
+            shell_quote(&path.to_string_lossy())
+        ));
+        messages.push((path, message.clone()));
+    }
+    (todo, messages)
+}
+
+/// Quote a string for the POSIX shell git runs `exec` lines and editors with
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Read the state of a running or paused rebase; `None` when no rebase is in
+/// progress
+pub fn read_status(git_dir: &Path, has_conflicts: bool) -> Option<RebaseStatus> {
+    let dir = git_dir.join("rebase-merge");
+    if !dir.is_dir() {
+        return None;
+    }
+    let read = |name: &str| {
+        std::fs::read_to_string(dir.join(name))
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    let number = |name: &str| read(name).and_then(|s| s.parse().ok()).unwrap_or(0);
+
+    let stopped_at = read("stopped-sha");
+    let pause = if has_conflicts {
+        Some(RebasePause::Conflict)
+    } else if dir.join("amend").exists() {
+        Some(RebasePause::Edit)
+    } else if stopped_at.is_some() {
+        Some(RebasePause::Other)
+    } else {
+        None
+    };
+
+    let branch = read("head-name")
+        .filter(|name| name != "detached HEAD")
+        .map(|name| name.strip_prefix("refs/heads/").unwrap_or(&name).to_string());
+
+    Some(RebaseStatus {
+        branch,
+        onto: read("onto").unwrap_or_default(),
+        current: number("msgnum"),
+        total: number("end"),
+        stopped_at,
+        pause,
+        remaining: read("git-rebase-todo")
+            .map(|todo| parse_todo(&todo))
+            .unwrap_or_default(),
+    })
+}
+
+/// Parse the commit steps of a todo list; `exec`, `break` and other commands
+/// that name no commit are skipped
+pub fn parse_todo(todo: &str) -> Vec<RebaseStep> {
+    todo.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            let action = RebaseAction::parse(parts.next()?)?;
+            let mut commit_id = parts.next()?;
+            let mut summary = parts.next().unwrap_or("");
+            // `fixup -C <commit>` / `fixup -c <commit>`
+            if commit_id.starts_with('-') {
+                let mut rest = summary.splitn(2, ' ');
+                commit_id = rest.next()?;
+                summary = rest.next().unwrap_or("");
+            }
+            Some(RebaseStep {
+                action,
+                commit_id: commit_id.to_string(),
+                summary: summary.trim_start_matches("# ").to_string(),
+                message: None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(action: RebaseAction, id: &str, message: Option<&str>) -> RebaseStep {
+        RebaseStep {
+            action,
+            commit_id: id.to_string(),
+            summary: format!("commit {}", id),
+            message: message.map(String::from),
+        }
+    }
+
+    fn amend_line(path: &Path) -> String {
+        format!(
+            "exec git commit --amend --only --allow-empty --no-verify --cleanup=strip -F {}",
+            shell_quote(&path.to_string_lossy())
+        )
+    }
+
+    #[test]
+    fn reword_amends_right_after_its_pick() {
+        let git_dir = Path::new("/repo/.git");
+        let steps = [
+            step(RebaseAction::Pick, "aaa111", None),
+            step(RebaseAction::Reword, "bbb222", Some("New message")),
+            step(RebaseAction::Pick, "ccc333", None),
+        ];
+        let (todo, messages) = todo_list(&steps, git_dir);
+
+        let path = git_dir.join(MESSAGE_DIR).join("message-1");
+        let expected = format!(
+            "pick aaa111 commit aaa111\npick bbb222 commit bbb222\n{}\npick ccc333 commit ccc333\n",
+            amend_line(&path)
+        );
+        assert_eq!(todo, expected);
+        assert_eq!(messages, [(path, "New message".to_string())]);
+    }
+
+    #[test]
+    fn amend_waits_for_the_squash_chain() {
+        let git_dir = Path::new("/repo/.git");
+        let steps = [
+            step(RebaseAction::Reword, "aaa111", Some("Combined message")),
+            step(RebaseAction::Fixup, "bbb222", None),
+            step(RebaseAction::Drop, "ccc333", None),
+            step(RebaseAction::Squash, "ddd444", Some("Squashed message")),
+            step(RebaseAction::Pick, "eee555", None),
+        ];
+        let (todo, messages) = todo_list(&steps, git_dir);
+        let lines: Vec<&str> = todo.lines().collect();
+
+        // Only the last message of the chain is applied, after its last step
+        let path = git_dir.join(MESSAGE_DIR).join("message-3");
+        assert_eq!(
+            lines,
+            [
+                "pick aaa111 commit aaa111",
+                "fixup bbb222 commit bbb222",
+                "drop ccc333 commit ccc333",
+                "squash ddd444 commit ddd444",
+                amend_line(&path).as_str(),
+                "pick eee555 commit eee555",
+            ]
+        );
+        assert_eq!(messages, [(path, "Squashed message".to_string())]);
+    }
+
+    #[test]
+    fn message_is_carried_past_a_plain_fixup() {
+        let git_dir = Path::new("/repo/.git");
+        let steps = [
+            step(RebaseAction::Reword, "aaa111", Some("X")),
+            step(RebaseAction::Fixup, "bbb222", None),
+            step(RebaseAction::Drop, "ccc333", None),
+            step(RebaseAction::Fixup, "ddd444", None),
+            step(RebaseAction::Pick, "eee555", None),
+        ];
+        let (todo, messages) = todo_list(&steps, git_dir);
+        let lines: Vec<&str> = todo.lines().collect();
+
+        let path = git_dir.join(MESSAGE_DIR).join("message-0");
+        assert_eq!(
+            lines,
+            [
+                "pick aaa111 commit aaa111",
+                "fixup bbb222 commit bbb222",
+                "drop ccc333 commit ccc333",
+                "fixup ddd444 commit ddd444",
+                amend_line(&path).as_str(),
+                "pick eee555 commit eee555",
+            ]
+        );
+        assert_eq!(messages, [(path, "X".to_string())]);
+    }
+
+    #[test]
+    fn chain_at_the_end_of_the_plan() {
+        let git_dir = Path::new("/repo/.git");
+        let steps = [
+            step(RebaseAction::Pick, "aaa111", None),
+            step(RebaseAction::Squash, "bbb222", Some("All in one")),
+            step(RebaseAction::Drop, "ccc333", Some("ignored")),
+        ];
+        let (todo, messages) = todo_list(&steps, git_dir);
+
+        let path = git_dir.join(MESSAGE_DIR).join("message-1");
+        assert!(todo.ends_with("drop ccc333 commit ccc333\n"));
+        assert_eq!(todo.lines().nth(2), Some(amend_line(&path).as_str()));
+        assert_eq!(messages.len(), 1);
+    }
+
+    #[test]
+    fn summaries_stay_on_one_line() {
+        let mut pick = step(RebaseAction::Pick, "aaa111", None);
+        pick.summary = "two\nlines\r\n".to_string();
+        let (todo, _) = todo_list(&[pick], Path::new("/repo/.git"));
+        assert_eq!(todo, "pick aaa111 two lines  \n");
+    }
+
+    #[test]
+    fn message_paths_are_shell_quoted() {
+        let git_dir = Path::new("/home/o'brien/my repo/.git");
+        let steps = [step(RebaseAction::Reword, "aaa111", Some("msg"))];
+        let (todo, _) = todo_list(&steps, git_dir);
+
+        assert!(todo.ends_with(" -F '/home/o'\\''brien/my repo/.git/gitsage-rebase/message-0'\n"));
+        assert_eq!(shell_quote("plain"), "'plain'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shell_quote_round_trips_through_sh() {
+        for value in ["plain", "with space", "it's", "'; rm -rf / #", "$HOME `id` \\n \"q\""] {
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("printf %s {}", shell_quote(value)))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), value);
+        }
+    }
+
+    #[test]
+    fn parse_todo_steps() {
+        let todo = "\
+pick aaa111 # First commit
+# a comment
+r bbb222 Second commit
+exec make test
+fixup -C ccc333 # amend! Second commit
+fixup -c ddd444 Edit the message
+f eee555 Plain fixup
+break
+drop fff666 Dropped
+
+";
+        let steps = parse_todo(todo);
+        let summary: Vec<_> = steps
+            .iter()
+            .map(|s| (s.action, s.commit_id.as_str(), s.summary.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (RebaseAction::Pick, "aaa111", "First commit"),
+                (RebaseAction::Reword, "bbb222", "Second commit"),
+                (RebaseAction::Fixup, "ccc333", "amend! Second commit"),
+                (RebaseAction::Fixup, "ddd444", "Edit the message"),
+                (RebaseAction::Fixup, "eee555", "Plain fixup"),
+                (RebaseAction::Drop, "fff666", "Dropped"),
+            ]
+        );
+        assert!(steps.iter().all(|s| s.message.is_none()));
+    }
+
+    #[test]
+    fn validate_plans() {
+        assert!(validate_plan(&[]).is_err());
+        assert!(validate_plan(&[step(RebaseAction::Pick, "not-hex", None)]).is_err());
+        assert!(validate_plan(&[step(RebaseAction::Reword, "aaa111", Some("  "))]).is_err());
+        assert!(validate_plan(&[
+            step(RebaseAction::Drop, "aaa111", None),
+            step(RebaseAction::Squash, "bbb222", None),
+        ])
+        .is_err());
+        assert!(validate_plan(&[
+            step(RebaseAction::Pick, "aaa111", None),
+            step(RebaseAction::Drop, "bbb222", None),
+            step(RebaseAction::Fixup, "ccc333", None),
+        ])
+        .is_ok());
+    }
+
+    #[test]
+    fn plans_cover_every_commit() {
+        let todo = [
+            step(RebaseAction::Pick, "aaa111", None),
+            step(RebaseAction::Pick, "bbb222", None),
+        ];
+        // Reordered, with a commit dropped explicitly
+        assert!(validate_plan_commits(
+            &[step(RebaseAction::Drop, "bbb222", None), step(RebaseAction::Pick, "aaa111", None)],
+            &todo
+        )
+        .is_ok());
+        // Left out
+        assert!(validate_plan_commits(&[step(RebaseAction::Pick, "aaa111", None)], &todo).is_err());
+        // Not on the branch
+        assert!(validate_plan_commits(
+            &[
+                step(RebaseAction::Pick, "aaa111", None),
+                step(RebaseAction::Pick, "bbb222", None),
+                step(RebaseAction::Pick, "ccc333", None),
+            ],
+            &todo
+        )
+        .is_err());
+        // Picked twice
+        assert!(validate_plan_commits(
+            &[
+                step(RebaseAction::Pick, "aaa111", None),
+                step(RebaseAction::Pick, "bbb222", None),
+                step(RebaseAction::Pick, "aaa111", None),
+            ],
+            &todo
+        )
+        .is_err());
+    }
+}
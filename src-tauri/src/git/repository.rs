@@ -2,11 +2,19 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::error::{AppError, AppResult};
-use crate::git::diff::DiffOutput;
+use crate::git::combined::{self, CombinedDiff};
+use crate::git::diff::{DiffOptions, DiffOutput, DiffStatOutput};
+use crate::git::journal::{JournalAction, WorkdirSnapshot};
 use crate::git::libgit::LibGitOps;
+use crate::git::patch::{self, PatchTarget};
+use crate::git::progress::ProgressCallback;
+use crate::git::rebase::{
+    self, AutosquashResult, FixupKind, HistoryRewrite, RebaseAction, RebaseStatus, RebaseStep,
+    SplitStatus,
+};
 
 // ==================== 基础类型 ====================
 
@@ -85,6 +93,15 @@ pub struct TagInfo {
     pub is_lightweight: bool,
 }
 
+/// How `pull` integrates remote changes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum PullStrategy {
+    #[default]
+    Merge,
+    Rebase,
+    FastForwardOnly,
+}
+
 /// Remote information
 #[derive(Debug, Clone, Serialize)]
 pub struct RemoteInfo {
@@ -162,12 +179,38 @@ pub struct WorktreeInfo {
     pub is_main: bool,
 }
 
+// ==================== Reflog 类型 ====================
+
+/// One reflog entry: a ref moved from `old_id` to `new_id`
+#[derive(Debug, Clone, Serialize)]
+pub struct ReflogEntry {
+    /// Position in the reflog, newest first: entry `n` is `<ref>@{n}`
+    pub index: usize,
+    pub old_id: String,
+    pub new_id: String,
+    pub committer_name: String,
+    pub committer_email: String,
+    pub timestamp: i64,
+    /// e.g. "reset: moving to HEAD~1", "commit: Fix parser"
+    pub message: String,
+}
+
+/// A commit no ref or reflog reaches any more
+#[derive(Debug, Clone, Serialize)]
+pub struct DanglingCommit {
+    pub commit: CommitInfo,
+    /// Looks like a dropped stash entry
+    pub is_stash: bool,
+}
+
 // ==================== GitRepository ====================
 
 /// Repository wrapper combining git2 (read) and CLI (write) operations.
 /// Read operations run via `spawn_blocking` to avoid blocking the Tokio runtime.
 pub struct GitRepository {
     pub path: PathBuf,
+    /// The repository's git directory (`.git`, or the gitdir of a worktree)
+    pub git_dir: PathBuf,
     libgit: Arc<LibGitOps>,
 }
 
@@ -177,6 +220,7 @@ impl GitRepository {
         let libgit = LibGitOps::open(path)?;
         Ok(Self {
             path: path.to_path_buf(),
+            git_dir: libgit.git_dir()?,
             libgit: Arc::new(libgit),
         })
     }
@@ -186,6 +230,7 @@ impl GitRepository {
         let libgit = LibGitOps::init(path)?;
         Ok(Self {
             path: path.to_path_buf(),
+            git_dir: libgit.git_dir()?,
             libgit: Arc::new(libgit),
         })
     }
@@ -198,6 +243,11 @@ impl GitRepository {
             .unwrap_or_else(|| "unknown".to_string())
     }
 
+    /// Shared handle to the libgit2 layer, for callers outside the async runtime
+    pub(crate) fn libgit(&self) -> Arc<LibGitOps> {
+        Arc::clone(&self.libgit)
+    }
+
     /// Run a blocking closure on the blocking thread pool.
     async fn blocking<F, T>(&self, f: F) -> AppResult<T>
     where
@@ -223,14 +273,21 @@ impl GitRepository {
     }
 
     /// Get diff for a file
-    pub async fn diff_file(&self, path: &str, staged: bool) -> AppResult<DiffOutput> {
+    pub async fn diff_file(
+        &self,
+        path: &str,
+        staged: bool,
+        options: &DiffOptions,
+    ) -> AppResult<DiffOutput> {
         let path = path.to_string();
-        self.blocking(move |g| g.diff_file(&path, staged)).await
+        let options = options.clone();
+        self.blocking(move |g| g.diff_file(&path, staged, &options)).await
     }
 
     /// Get all staged changes diff
-    pub async fn diff_staged(&self) -> AppResult<DiffOutput> {
-        self.blocking(|g| g.diff_staged()).await
+    pub async fn diff_staged(&self, options: &DiffOptions) -> AppResult<DiffOutput> {
+        let options = options.clone();
+        self.blocking(move |g| g.diff_staged(&options)).await
     }
 
     /// List branches
@@ -275,9 +332,63 @@ impl GitRepository {
     }
 
     /// Get diff for a specific commit
-    pub async fn commit_diff(&self, commit_id: &str) -> AppResult<DiffOutput> {
+    pub async fn commit_diff(
+        &self,
+        commit_id: &str,
+        parent: Option<usize>,
+        options: &DiffOptions,
+    ) -> AppResult<DiffOutput> {
         let commit_id = commit_id.to_string();
-        self.blocking(move |g| g.commit_diff(&commit_id)).await
+        let options = options.clone();
+        self.blocking(move |g| g.commit_diff(&commit_id, parent, &options)).await
+    }
+
+    /// Diff of a merge commit against all of its parents at once, keeping
+    /// only what the merge resolution changed
+    pub async fn combined_diff(
+        &self,
+        commit_id: &str,
+        options: &DiffOptions,
+    ) -> AppResult<CombinedDiff> {
+        let id = commit_id.to_string();
+        let (commit_id, parent_ids) = self.blocking(move |g| g.commit_parents(&id)).await?;
+        if parent_ids.len() < 2 {
+            return Err(AppError::InvalidInput(format!(
+                "Commit {} is not a merge commit",
+                commit_id
+            )));
+        }
+        let output = crate::git::cli::combined_diff(&self.path, &commit_id, options).await?;
+        Ok(combined::parse(commit_id, parent_ids, &output))
+    }
+
+    /// Diff between two revisions or across a range, see
+    /// `LibGitOps::diff_revisions`
+    pub async fn diff_revisions(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        pathspecs: &[String],
+        options: &DiffOptions,
+    ) -> AppResult<DiffOutput> {
+        let (from, to) = (from.to_string(), to.map(str::to_string));
+        let (pathspecs, options) = (pathspecs.to_vec(), options.clone());
+        self.blocking(move |g| g.diff_revisions(&from, to.as_deref(), &pathspecs, &options))
+            .await
+    }
+
+    /// Per-file line counts of `diff_revisions`
+    pub async fn diff_revisions_stats(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        pathspecs: &[String],
+        options: &DiffOptions,
+    ) -> AppResult<DiffStatOutput> {
+        let (from, to) = (from.to_string(), to.map(str::to_string));
+        let (pathspecs, options) = (pathspecs.to_vec(), options.clone());
+        self.blocking(move |g| g.diff_revisions_stats(&from, to.as_deref(), &pathspecs, &options))
+            .await
     }
 
     /// Get file history
@@ -358,18 +469,28 @@ impl GitRepository {
     }
 
     /// Fetch from remote
-    pub async fn fetch(&self, remote: &str) -> AppResult<()> {
-        crate::git::cli::fetch(&self.path, remote).await
+    pub async fn fetch(&self, remote: &str, on_progress: &ProgressCallback) -> AppResult<()> {
+        crate::git::cli::fetch(&self.path, remote, on_progress).await
     }
 
     /// Pull from remote
-    pub async fn pull(&self, remote: &str, branch: &str) -> AppResult<()> {
-        crate::git::cli::pull(&self.path, remote, branch).await
+    pub async fn pull(
+        &self,
+        remote: &str,
+        branch: &str,
+        on_progress: &ProgressCallback,
+    ) -> AppResult<()> {
+        crate::git::cli::pull(&self.path, remote, branch, on_progress).await
     }
 
     /// Push to remote
-    pub async fn push(&self, remote: &str, branch: &str) -> AppResult<()> {
-        crate::git::cli::push(&self.path, remote, branch).await
+    pub async fn push(
+        &self,
+        remote: &str,
+        branch: &str,
+        on_progress: &ProgressCallback,
+    ) -> AppResult<()> {
+        crate::git::cli::push(&self.path, remote, branch, on_progress).await
     }
 
     // Phase 1 writes
@@ -452,13 +573,22 @@ impl GitRepository {
     }
 
     /// Push tag
-    pub async fn push_tag(&self, remote: &str, tag: &str) -> AppResult<()> {
-        crate::git::cli::push_tag(&self.path, remote, tag).await
+    pub async fn push_tag(
+        &self,
+        remote: &str,
+        tag: &str,
+        on_progress: &ProgressCallback,
+    ) -> AppResult<()> {
+        crate::git::cli::push_tag(&self.path, remote, tag, on_progress).await
     }
 
     /// Push all tags
-    pub async fn push_all_tags(&self, remote: &str) -> AppResult<()> {
-        crate::git::cli::push_all_tags(&self.path, remote).await
+    pub async fn push_all_tags(
+        &self,
+        remote: &str,
+        on_progress: &ProgressCallback,
+    ) -> AppResult<()> {
+        crate::git::cli::push_all_tags(&self.path, remote, on_progress).await
     }
 
     /// Cherry-pick
@@ -498,7 +628,9 @@ impl GitRepository {
 
     /// Rebase abort
     pub async fn rebase_abort(&self) -> AppResult<()> {
-        crate::git::cli::rebase_abort(&self.path).await
+        crate::git::cli::rebase_abort(&self.path).await?;
+        // Aborting also abandons a split in progress
+        self.clear_split_marker()
     }
 
     /// Rebase continue
@@ -511,9 +643,243 @@ impl GitRepository {
         crate::git::cli::rebase_skip(&self.path).await
     }
 
+    /// Default interactive rebase plan for the commits of HEAD since `base`
+    pub async fn rebase_todo(&self, base: &str) -> AppResult<Vec<RebaseStep>> {
+        let base = base.to_string();
+        self.blocking(move |g| g.rebase_todo(&base)).await
+    }
+
+    /// Progress of the rebase in progress, if any
+    pub async fn rebase_status(&self) -> AppResult<Option<RebaseStatus>> {
+        self.blocking(|g| g.rebase_status()).await
+    }
+
+    /// Rebase HEAD onto `base` following `steps`. Returns the rebase status
+    /// when it stopped at an `edit` step, `None` when it finished.
+    pub async fn interactive_rebase(
+        &self,
+        base: &str,
+        steps: &[RebaseStep],
+    ) -> AppResult<Option<RebaseStatus>> {
+        self.ensure_no_operation().await?;
+        rebase::validate_plan(steps)?;
+        rebase::validate_plan_commits(steps, &self.rebase_todo(base).await?)?;
+        let (todo, messages) = rebase::todo_list(steps, &self.git_dir);
+
+        // Left over from the previous plan, which has finished or been aborted
+        let plan_dir = self.git_dir.join(rebase::MESSAGE_DIR);
+        if plan_dir.exists() {
+            std::fs::remove_dir_all(&plan_dir)?;
+        }
+        std::fs::create_dir_all(&plan_dir)?;
+        for (path, message) in &messages {
+            std::fs::write(path, message)?;
+        }
+        let todo_path = plan_dir.join("todo");
+        std::fs::write(&todo_path, todo)?;
+
+        crate::git::cli::rebase_interactive(&self.path, base, &todo_path).await?;
+        self.rebase_status().await
+    }
+
+    /// Commit the staged changes as a `fixup!`, `squash!` or `amend!` commit
+    /// for `target`, then autosquash it in by rebasing onto the target's parent
+    pub async fn fixup_commit(
+        &self,
+        kind: FixupKind,
+        target: &str,
+        message: Option<&str>,
+    ) -> AppResult<AutosquashResult> {
+        self.ensure_no_operation().await?;
+        let message = message.map(str::trim).filter(|m| !m.is_empty());
+        let target_id = target.to_string();
+        let target = self.blocking(move |g| g.rewrite_target(&target_id)).await?;
+        // Autosquash rebases everything from the target on, merges included
+        let base = target.parent.clone();
+        if self.blocking(move |g| g.range_has_merges(base.as_deref())).await? {
+            return Err(AppError::InvalidInput(
+                "This commit or the ones after it include merges, which autosquashing would \
+                 flatten"
+                    .to_string(),
+            ));
+        }
+        let dependent_id = target.id.clone();
+        let dependent_branches = self
+            .blocking(move |g| g.dependent_branches(&dependent_id))
+            .await?;
+
+        let message = match kind {
+            FixupKind::Fixup => None,
+            FixupKind::Squash => message,
+            FixupKind::Amend => Some(message.ok_or_else(|| {
+                AppError::InvalidInput("An amend! commit needs the new message".to_string())
+            })?),
+        };
+        crate::git::cli::commit_fixup(&self.path, kind, &target.id, message).await?;
+        let (_, commit_id) = self.blocking(|g| g.head_state()).await?;
+
+        crate::git::cli::rebase_autosquash(&self.path, target.parent.as_deref()).await?;
+        Ok(AutosquashResult {
+            commit_id,
+            dependent_branches,
+            rebase: self.rebase_status().await?,
+        })
+    }
+
+    /// Replace the message of a commit in the current branch's first-parent
+    /// history, replaying its descendants. Other local branches pointing at
+    /// the rewritten commits move along. The index and work tree are left
+    /// alone. Returns the new branch tip.
+    pub async fn reword_commit(&self, commit_id: &str, message: &str) -> AppResult<String> {
+        self.ensure_no_operation().await?;
+        let commit_id = commit_id.to_string();
+        let message_owned = message.to_string();
+        let rewrites = self
+            .blocking(move |g| g.reword_commit(&commit_id, &message_owned))
+            .await?;
+        let summary = message.lines().next().unwrap_or("");
+        self.apply_rewrites(&rewrites, &format!("reword: {}", summary)).await?;
+        Ok(rewrites[0].new_head.clone())
+    }
+
+    /// Start splitting a commit: rebase to it, then rewind it so its changes
+    /// are in the work tree, ready to be staged and committed in parts with
+    /// the usual staging commands. `finish_split` replays the descendants.
+    pub async fn start_split(&self, commit_id: &str) -> AppResult<SplitStatus> {
+        self.ensure_no_operation().await?;
+        let target_id = commit_id.to_string();
+        let target = self.blocking(move |g| g.rewrite_target(&target_id)).await?;
+        if target.is_merge {
+            return Err(AppError::InvalidInput("Merge commits cannot be split".to_string()));
+        }
+        let parent = target.parent.clone().ok_or_else(|| {
+            AppError::InvalidInput("The root commit cannot be split".to_string())
+        })?;
+        let base = parent.clone();
+        if self.blocking(move |g| g.range_has_merges(Some(&base))).await? {
+            return Err(AppError::InvalidInput(
+                "Commits after this one include merges, which splitting would flatten"
+                    .to_string(),
+            ));
+        }
+
+        let mut steps = self.rebase_todo(&parent).await?;
+        let step = steps
+            .iter_mut()
+            .find(|step| step.commit_id == target.id)
+            .ok_or_else(|| {
+                AppError::InvalidInput(format!(
+                    "Commit {} is not in the history of the current branch",
+                    commit_id
+                ))
+            })?;
+        step.action = RebaseAction::Edit;
+        self.interactive_rebase(&parent, &steps).await?;
+        crate::git::cli::reset_intent_to_add(&self.path, "HEAD~1").await?;
+
+        let split = SplitStatus {
+            commit_id: target.id,
+            message: target.message,
+            rebase: None,
+        };
+        let marker = self.git_dir.join(rebase::MESSAGE_DIR).join(rebase::SPLIT_MARKER);
+        std::fs::write(marker, serde_json::to_string(&split)?)?;
+        Ok(SplitStatus {
+            rebase: self.rebase_status().await?,
+            ..split
+        })
+    }
+
+    /// The split in progress, if any
+    pub async fn split_status(&self) -> AppResult<Option<SplitStatus>> {
+        let Some(rebase) = self.rebase_status().await? else {
+            return Ok(None);
+        };
+        let marker = self.git_dir.join(rebase::MESSAGE_DIR).join(rebase::SPLIT_MARKER);
+        let Ok(json) = std::fs::read_to_string(marker) else {
+            return Ok(None);
+        };
+        let split: SplitStatus = serde_json::from_str(&json)?;
+        Ok(Some(SplitStatus {
+            rebase: Some(rebase),
+            ..split
+        }))
+    }
+
+    /// Finish a split: commit what is left with `message`, if anything, then
+    /// replay the descendants. Returns the rebase status if it stopped again.
+    pub async fn finish_split(&self, message: Option<&str>) -> AppResult<Option<RebaseStatus>> {
+        if self.split_status().await?.is_none() {
+            return Err(AppError::RepoState("No commit is being split".to_string()));
+        }
+        let remaining = self.blocking(|g| g.uncommitted_paths()).await?;
+        if !remaining.is_empty() {
+            let message = message.map(str::trim).filter(|m| !m.is_empty()).ok_or_else(|| {
+                AppError::InvalidInput(
+                    "Changes of the split commit are left; commit them or give a message for the rest"
+                        .to_string(),
+                )
+            })?;
+            crate::git::cli::commit_all(&self.path, message).await?;
+        }
+        let continued = crate::git::cli::rebase_continue(&self.path).await;
+        // The split is over even when a later step stops the rebase, which is
+        // then an ordinary conflict
+        self.clear_split_marker()?;
+        continued?;
+        self.rebase_status().await
+    }
+
+    fn clear_split_marker(&self) -> AppResult<()> {
+        let marker = self.git_dir.join(rebase::MESSAGE_DIR).join(rebase::SPLIT_MARKER);
+        match std::fs::remove_file(marker) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Move branches to rewritten history, unless one moved in the meantime
+    async fn apply_rewrites(&self, rewrites: &[HistoryRewrite], reason: &str) -> AppResult<()> {
+        crate::git::cli::move_refs(&self.path, rewrites, reason).await
+    }
+
+    /// History can't be rewritten while a merge, rebase or similar is running
+    async fn ensure_no_operation(&self) -> AppResult<()> {
+        match self.repo_state().await? {
+            RepoOperationState::Normal => Ok(()),
+            state => Err(AppError::RepoState(format!(
+                "Finish or abort the operation in progress first ({:?})",
+                state
+            ))),
+        }
+    }
+
     /// Pull with rebase
-    pub async fn pull_rebase(&self, remote: &str, branch: &str) -> AppResult<()> {
-        crate::git::cli::pull_rebase(&self.path, remote, branch).await
+    pub async fn pull_rebase(
+        &self,
+        remote: &str,
+        branch: &str,
+        on_progress: &ProgressCallback,
+    ) -> AppResult<()> {
+        crate::git::cli::pull_rebase(&self.path, remote, branch, on_progress).await
+    }
+
+    /// Pull using the given strategy
+    pub async fn pull_with_strategy(
+        &self,
+        remote: &str,
+        branch: &str,
+        strategy: PullStrategy,
+        on_progress: &ProgressCallback,
+    ) -> AppResult<()> {
+        match strategy {
+            PullStrategy::Merge => self.pull(remote, branch, on_progress).await,
+            PullStrategy::Rebase => self.pull_rebase(remote, branch, on_progress).await,
+            PullStrategy::FastForwardOnly => {
+                crate::git::cli::pull_ff_only(&self.path, remote, branch, on_progress).await
+            }
+        }
     }
 
     /// Add remote
@@ -532,18 +898,28 @@ impl GitRepository {
     }
 
     /// Fetch with prune
-    pub async fn fetch_prune(&self, remote: &str) -> AppResult<()> {
-        crate::git::cli::fetch_prune(&self.path, remote).await
+    pub async fn fetch_prune(&self, remote: &str, on_progress: &ProgressCallback) -> AppResult<()> {
+        crate::git::cli::fetch_prune(&self.path, remote, on_progress).await
     }
 
     /// Push with set upstream
-    pub async fn push_set_upstream(&self, remote: &str, branch: &str) -> AppResult<()> {
-        crate::git::cli::push_set_upstream(&self.path, remote, branch).await
+    pub async fn push_set_upstream(
+        &self,
+        remote: &str,
+        branch: &str,
+        on_progress: &ProgressCallback,
+    ) -> AppResult<()> {
+        crate::git::cli::push_set_upstream(&self.path, remote, branch, on_progress).await
     }
 
     /// Sync (pull then push)
-    pub async fn sync(&self, remote: &str, branch: &str) -> AppResult<()> {
-        crate::git::cli::sync(&self.path, remote, branch).await
+    pub async fn sync(
+        &self,
+        remote: &str,
+        branch: &str,
+        on_progress: &ProgressCallback,
+    ) -> AppResult<()> {
+        crate::git::cli::sync(&self.path, remote, branch, on_progress).await
     }
 
     // Phase 3 writes
@@ -553,6 +929,44 @@ impl GitRepository {
         crate::git::cli::apply_patch(&self.path, patch, cached, reverse).await
     }
 
+    /// Stage, unstage or discard one hunk of a file's diff, or only the given
+    /// lines of it (indices into the hunk's `lines`).
+    ///
+    /// The hunk is the one with `fingerprint`, expected at `hunk_index`; it
+    /// may have moved if hunks before it were staged or discarded. Fails with
+    /// `StaleDiff` when the file changed and no such hunk is left. `options`
+    /// are those the diff was shown with, so the hunks are cut the same way.
+    pub async fn apply_hunk_selection(
+        &self,
+        path: &str,
+        hunk_index: usize,
+        fingerprint: &str,
+        lines: Option<&[usize]>,
+        target: PatchTarget,
+        options: &DiffOptions,
+    ) -> AppResult<()> {
+        if options.ignores_whitespace() {
+            return Err(AppError::InvalidInput(
+                "Changes cannot be staged or discarded from a diff that ignores whitespace"
+                    .to_string(),
+            ));
+        }
+        let diff = self.diff_file(path, target.staged(), options).await?;
+        let file = diff.files.first().ok_or_else(|| AppError::StaleDiff(path.to_string()))?;
+        let hunk = file
+            .hunks
+            .get(hunk_index)
+            .filter(|hunk| hunk.fingerprint == fingerprint)
+            .or_else(|| file.hunks.iter().find(|hunk| hunk.fingerprint == fingerprint))
+            .ok_or_else(|| AppError::StaleDiff(path.to_string()))?;
+
+        let patch = match lines {
+            Some(lines) => patch::generate_line_patch(file, hunk, lines, target.reverse())?,
+            None => patch::generate_hunk_patch(file, hunk, target.reverse())?,
+        };
+        self.apply_patch(&patch, target.cached(), target.reverse()).await
+    }
+
     /// Mark conflict as resolved
     pub async fn mark_resolved(&self, path: &str) -> AppResult<()> {
         crate::git::cli::mark_resolved(&self.path, path).await
@@ -576,8 +990,12 @@ impl GitRepository {
     }
 
     /// Update submodules
-    pub async fn submodule_update(&self, recursive: bool) -> AppResult<()> {
-        crate::git::cli::submodule_update(&self.path, recursive).await
+    pub async fn submodule_update(
+        &self,
+        recursive: bool,
+        on_progress: &ProgressCallback,
+    ) -> AppResult<()> {
+        crate::git::cli::submodule_update(&self.path, recursive, on_progress).await
     }
 
     /// Add submodule
@@ -599,4 +1017,907 @@ impl GitRepository {
     pub async fn worktree_list(&self) -> AppResult<String> {
         crate::git::cli::worktree_list(&self.path).await
     }
+
+    // Reflog & recovery
+
+    /// Reflog of a ref, newest first
+    pub async fn reflog(&self, refname: &str) -> AppResult<Vec<ReflogEntry>> {
+        let refname = refname.to_string();
+        self.blocking(move |g| g.reflog(&refname)).await
+    }
+
+    /// HEAD and the local branches that have a reflog
+    pub async fn reflog_refs(&self) -> AppResult<Vec<String>> {
+        self.blocking(|g| g.reflog_refs()).await
+    }
+
+    /// Commits lost from every ref and reflog, e.g. dropped stashes, newest first
+    pub async fn dangling_commits(&self) -> AppResult<Vec<DanglingCommit>> {
+        let ids = crate::git::cli::dangling_commits(&self.path).await?;
+        self.blocking(move |g| g.dangling_commits(&ids)).await
+    }
+
+    /// Create a branch at any commit, e.g. from a reflog entry
+    pub async fn create_branch_at(&self, name: &str, commit_id: &str) -> AppResult<()> {
+        crate::git::cli::create_branch_at(&self.path, name, commit_id).await
+    }
+
+    /// Point a branch back at an earlier commit. The checked-out branch is
+    /// reset with `--keep`, which refuses to overwrite local changes.
+    pub async fn restore_branch(&self, name: &str, commit_id: &str) -> AppResult<()> {
+        let (current, _) = self.blocking(|g| g.head_state()).await?;
+        if current.as_deref() == Some(name) {
+            crate::git::cli::reset_keep(&self.path, commit_id).await
+        } else {
+            crate::git::cli::force_branch(&self.path, name, commit_id).await
+        }
+    }
+
+    // Operation journal
+
+    /// Snapshot what discarding `paths` (files or directories), or every
+    /// change when `None`, would throw away
+    pub async fn capture_discard(&self, paths: Option<&[String]>) -> AppResult<JournalAction> {
+        let requested: Option<Vec<String>> = paths.map(|paths| {
+            paths
+                .iter()
+                .map(|p| p.trim_end_matches('/').to_string())
+                .collect()
+        });
+        self.blocking(move |g| {
+            let mut paths = g.discardable_paths()?;
+            if let Some(requested) = requested {
+                paths.retain(|path| {
+                    requested.iter().any(|r| {
+                        r == "." || path == r || path.starts_with(&format!("{}/", r))
+                    })
+                });
+            }
+            Ok(JournalAction::Discard {
+                snapshot: g.snapshot_workdir(&paths)?,
+            })
+        })
+        .await
+    }
+
+    /// Capture HEAD and the index, plus uncommitted work for a hard reset
+    pub async fn capture_reset(&self, hard: bool) -> AppResult<JournalAction> {
+        self.blocking(move |g| {
+            let (branch, previous_head) = g.head_state()?;
+            let index = g.snapshot_index()?;
+            let worktree = if hard {
+                Some(g.snapshot_workdir(&g.uncommitted_paths()?)?)
+            } else {
+                None
+            };
+            Ok(JournalAction::Reset {
+                branch,
+                previous_head,
+                index,
+                worktree,
+            })
+        })
+        .await
+    }
+
+    /// Capture the stash entry `stash drop` would remove (`stash@{0}` by default)
+    pub async fn capture_stash_drop(&self, index: Option<usize>) -> AppResult<JournalAction> {
+        let index = index.unwrap_or(0);
+        let stashes = self.blocking(|g| g.stash_commits()).await?;
+        let stash = stashes
+            .into_iter()
+            .nth(index)
+            .ok_or_else(|| AppError::InvalidInput(format!("No stash entry stash@{{{}}}", index)))?;
+        Ok(JournalAction::DropStashes {
+            stashes: vec![stash],
+        })
+    }
+
+    /// Capture every stash entry
+    pub async fn capture_stash_clear(&self) -> AppResult<JournalAction> {
+        let stashes = self.blocking(|g| g.stash_commits()).await?;
+        Ok(JournalAction::DropStashes { stashes })
+    }
+
+    /// Capture a branch or tag about to be deleted, e.g. `refs/heads/topic`
+    pub async fn capture_ref_deletion(&self, refname: &str) -> AppResult<JournalAction> {
+        let refname = refname.to_string();
+        self.blocking(move |g| {
+            let (target, upstream) = g.ref_state(&refname)?;
+            Ok(JournalAction::DeleteRef {
+                refname,
+                target,
+                upstream,
+            })
+        })
+        .await
+    }
+
+    /// Keep the objects a journal entry needs reachable
+    pub async fn anchor_journal_objects(&self, entry_id: i64, objects: Vec<String>) -> AppResult<()> {
+        self.blocking(move |g| g.anchor_journal_objects(entry_id, &objects))
+            .await
+    }
+
+    /// Release the objects of journal entries that were undone or pruned
+    pub async fn drop_journal_anchors(&self, entry_ids: Vec<i64>) -> AppResult<()> {
+        self.blocking(move |g| g.drop_journal_anchors(&entry_ids)).await
+    }
+
+    /// Restore the state captured in a journal entry
+    pub async fn undo_journal_action(&self, action: &JournalAction) -> AppResult<()> {
+        match action {
+            JournalAction::Discard { snapshot } => self.restore_snapshot(snapshot).await,
+            JournalAction::Reset {
+                branch,
+                previous_head,
+                index,
+                worktree,
+            } => {
+                let (current_branch, _) = self.blocking(|g| g.head_state()).await?;
+                if current_branch != *branch {
+                    return Err(AppError::RepoState(match branch {
+                        Some(branch) => format!("HEAD is no longer on branch '{}'", branch),
+                        None => "HEAD is no longer detached".to_string(),
+                    }));
+                }
+                match worktree {
+                    Some(snapshot) => {
+                        let uncommitted = self.blocking(|g| g.uncommitted_paths()).await?;
+                        if !uncommitted.is_empty() {
+                            return Err(AppError::RepoState(
+                                "Commit or stash your changes before undoing a hard reset".into(),
+                            ));
+                        }
+                        crate::git::cli::reset(&self.path, previous_head, "hard").await?;
+                        crate::git::cli::read_tree(&self.path, index).await?;
+                        self.restore_snapshot(snapshot).await
+                    }
+                    None => {
+                        crate::git::cli::reset(&self.path, previous_head, "soft").await?;
+                        crate::git::cli::read_tree(&self.path, index).await
+                    }
+                }
+            }
+            JournalAction::DropStashes { stashes } => {
+                // Oldest first, so the entries end up in their old order
+                for stash in stashes.iter().rev() {
+                    crate::git::cli::stash_store(&self.path, &stash.commit, &stash.message)
+                        .await?;
+                }
+                Ok(())
+            }
+            JournalAction::DeleteRef {
+                refname,
+                target,
+                upstream,
+            } => {
+                crate::git::cli::create_ref(&self.path, refname, target).await?;
+                let branch = refname.strip_prefix("refs/heads/");
+                if let (Some(branch), Some(upstream)) = (branch, upstream) {
+                    // The remote branch may be gone by now; the branch itself is back
+                    if let Err(e) = crate::git::cli::set_upstream(&self.path, branch, upstream).await
+                    {
+                        tracing::warn!("Failed to restore upstream of {}: {}", branch, e);
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn restore_snapshot(&self, snapshot: &WorkdirSnapshot) -> AppResult<()> {
+        if !snapshot.paths.is_empty() {
+            crate::git::cli::restore_worktree(&self.path, &snapshot.commit, &snapshot.paths)
+                .await?;
+        }
+        for path in &snapshot.removed {
+            match std::fs::remove_file(self.path.join(path)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::git::diff::{DiffFileStatus, DiffLineType};
+    use crate::git::journal::ANCHOR_PREFIX;
+
+    /// A scratch repository, removed on drop
+    struct TestRepo {
+        dir: PathBuf,
+        repo: GitRepository,
+    }
+
+    impl TestRepo {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("gitsage-test-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            git(&dir, &["init", "-q", "-b", "main"]);
+            git(&dir, &["config", "user.name", "Test"]);
+            git(&dir, &["config", "user.email", "test@example.com"]);
+            git(&dir, &["config", "core.autocrlf", "false"]);
+            let repo = GitRepository::open(&dir).unwrap();
+            Self { dir, repo }
+        }
+
+        fn write(&self, path: &str, content: &str) {
+            let path = self.dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        fn read(&self, path: &str) -> String {
+            std::fs::read_to_string(self.dir.join(path)).unwrap()
+        }
+
+        fn commit(&self, files: &[(&str, &str)]) {
+            self.commit_as("commit", files);
+        }
+
+        /// Commit `files` with `message`, returning the new commit's id
+        fn commit_as(&self, message: &str, files: &[(&str, &str)]) -> String {
+            for (path, content) in files {
+                self.write(path, content);
+            }
+            git(&self.dir, &["add", "-A"]);
+            git(&self.dir, &["commit", "-q", "-m", message]);
+            self.rev("HEAD")
+        }
+
+        fn rev(&self, rev: &str) -> String {
+            git(&self.dir, &["rev-parse", rev]).trim().to_string()
+        }
+
+        /// Commit subjects from `rev` back to the root
+        fn subjects(&self, rev: &str) -> Vec<String> {
+            git(&self.dir, &["log", "--format=%s", rev]).lines().map(str::to_string).collect()
+        }
+
+        /// Content of `path` in the index
+        fn staged(&self, path: &str) -> String {
+            git(&self.dir, &["show", &format!(":{}", path)])
+        }
+
+        /// Changed lines of `git diff --cached` for `path`
+        fn cached_changes(&self, path: &str) -> Vec<String> {
+            git(&self.dir, &["diff", "--cached", "-U0", "--no-color", "--", path])
+                .lines()
+                .filter(|line| {
+                    (line.starts_with('+') || line.starts_with('-'))
+                        && !line.starts_with("+++")
+                        && !line.starts_with("---")
+                })
+                .map(str::to_string)
+                .collect()
+        }
+
+        fn is_tracked(&self, path: &str) -> bool {
+            git(&self.dir, &["ls-files", "--", path]).trim() == path
+        }
+
+        /// Apply the lines of the first hunk of `path` that match `wanted`,
+        /// given as `+text` or `-text`; all of the hunk when `wanted` is empty
+        async fn apply(&self, path: &str, target: PatchTarget, wanted: &[&str]) -> AppResult<()> {
+            let options = DiffOptions::default();
+            let diff = self.repo.diff_file(path, target.staged(), &options).await?;
+            let hunk = &diff.files[0].hunks[0];
+            let selected: Vec<usize> = hunk
+                .lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| {
+                    let prefix = match line.origin {
+                        DiffLineType::Addition => '+',
+                        DiffLineType::Deletion => '-',
+                        _ => return false,
+                    };
+                    let text = format!("{}{}", prefix, line.content.trim_end());
+                    wanted.contains(&text.as_str())
+                })
+                .map(|(i, _)| i)
+                .collect();
+            let lines = (!wanted.is_empty()).then_some(selected.as_slice());
+            self.repo
+                .apply_hunk_selection(path, 0, &hunk.fingerprint, lines, target, &options)
+                .await
+        }
+    }
+
+    impl Drop for TestRepo {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git").current_dir(dir).args(args).output().unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    #[tokio::test]
+    async fn stage_unstage_discard_line_selections() {
+        let t = TestRepo::new("lines");
+        t.commit(&[("f", "1\n2\n3\n4\n5\n")]);
+        t.write("f", "1\nA\n3\nB\n5\nC\n");
+
+        // The unselected "-2" stays as context, the unselected additions are left out
+        t.apply("f", PatchTarget::Stage, &["+A"]).await.unwrap();
+        assert_eq!(t.staged("f"), "1\n2\nA\n3\n4\n5\n");
+        assert_eq!(t.cached_changes("f"), ["+A"]);
+
+        t.apply("f", PatchTarget::Stage, &["-2", "-4"]).await.unwrap();
+        assert_eq!(t.staged("f"), "1\nA\n3\n5\n");
+        assert_eq!(t.cached_changes("f"), ["-2", "+A", "-4"]);
+
+        t.apply("f", PatchTarget::Unstage, &["+A"]).await.unwrap();
+        assert_eq!(t.cached_changes("f"), ["-2", "-4"]);
+        t.apply("f", PatchTarget::Unstage, &["-4"]).await.unwrap();
+        assert_eq!(t.staged("f"), "1\n3\n4\n5\n");
+        assert_eq!(t.read("f"), "1\nA\n3\nB\n5\nC\n");
+
+        // Work tree against the index "1 3 4 5"
+        t.apply("f", PatchTarget::Discard, &["+B", "+C"]).await.unwrap();
+        assert_eq!(t.read("f"), "1\nA\n3\n5\n");
+        t.apply("f", PatchTarget::Discard, &["-4"]).await.unwrap();
+        assert_eq!(t.read("f"), "1\nA\n3\n4\n5\n");
+        assert_eq!(t.staged("f"), "1\n3\n4\n5\n");
+
+        // Whole hunks
+        t.apply("f", PatchTarget::Stage, &[]).await.unwrap();
+        assert_eq!(t.staged("f"), "1\nA\n3\n4\n5\n");
+        t.apply("f", PatchTarget::Unstage, &[]).await.unwrap();
+        assert!(t.cached_changes("f").is_empty());
+        t.apply("f", PatchTarget::Discard, &[]).await.unwrap();
+        assert_eq!(t.read("f"), "1\n2\n3\n4\n5\n");
+    }
+
+    #[tokio::test]
+    async fn no_newline_at_end_of_file() {
+        let t = TestRepo::new("eol");
+        t.commit(&[("f", "1\n2\nlast"), ("g", "x\ny\n")]);
+
+        t.write("f", "1\n2\nlast\nmore\n");
+        t.apply("f", PatchTarget::Stage, &["-last", "+last"]).await.unwrap();
+        assert_eq!(t.staged("f"), "1\n2\nlast\n");
+        t.apply("f", PatchTarget::Unstage, &["-last", "+last"]).await.unwrap();
+        assert_eq!(t.staged("f"), "1\n2\nlast");
+        t.apply("f", PatchTarget::Discard, &["+more"]).await.unwrap();
+        assert_eq!(t.read("f"), "1\n2\nlast\n");
+
+        t.write("g", "x\ny");
+        t.apply("g", PatchTarget::Stage, &[]).await.unwrap();
+        assert_eq!(t.staged("g"), "x\ny");
+        t.apply("g", PatchTarget::Unstage, &[]).await.unwrap();
+        assert_eq!(t.staged("g"), "x\ny\n");
+        t.apply("g", PatchTarget::Discard, &[]).await.unwrap();
+        assert_eq!(t.read("g"), "x\ny\n");
+    }
+
+    #[tokio::test]
+    async fn addition_after_unterminated_last_line_needs_its_change() {
+        let t = TestRepo::new("eol-after");
+        t.commit(&[("f", "a\nb")]);
+        t.write("f", "a\nb\nc\n");
+
+        let err = t.apply("f", PatchTarget::Stage, &["+c"]).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
+        assert!(t.cached_changes("f").is_empty());
+
+        t.apply("f", PatchTarget::Stage, &["-b", "+b", "+c"]).await.unwrap();
+        assert_eq!(t.staged("f"), "a\nb\nc\n");
+    }
+
+    #[tokio::test]
+    async fn new_and_deleted_files() {
+        let t = TestRepo::new("new-deleted");
+        t.commit(&[("old", "o1\no2\no3\n")]);
+
+        t.write("new", "n1\nn2\nn3");
+        t.apply("new", PatchTarget::Stage, &["+n1", "+n3"]).await.unwrap();
+        assert_eq!(t.staged("new"), "n1\nn3");
+        t.apply("new", PatchTarget::Unstage, &["+n1"]).await.unwrap();
+        assert_eq!(t.staged("new"), "n3");
+        t.apply("new", PatchTarget::Unstage, &["+n3"]).await.unwrap();
+        assert!(!t.is_tracked("new"));
+
+        t.apply("new", PatchTarget::Discard, &["+n2"]).await.unwrap();
+        assert_eq!(t.read("new"), "n1\nn3");
+        t.apply("new", PatchTarget::Discard, &[]).await.unwrap();
+        assert!(!t.dir.join("new").exists());
+
+        std::fs::remove_file(t.dir.join("old")).unwrap();
+        t.apply("old", PatchTarget::Stage, &["-o2"]).await.unwrap();
+        assert_eq!(t.staged("old"), "o1\no3\n");
+        t.apply("old", PatchTarget::Stage, &[]).await.unwrap();
+        assert!(!t.is_tracked("old"));
+        assert_eq!(t.cached_changes("old"), ["-o1", "-o2", "-o3"]);
+        t.apply("old", PatchTarget::Unstage, &["-o1"]).await.unwrap();
+        assert_eq!(t.staged("old"), "o1\n");
+        t.apply("old", PatchTarget::Unstage, &[]).await.unwrap();
+        assert!(t.cached_changes("old").is_empty());
+
+        t.apply("old", PatchTarget::Discard, &["-o3"]).await.unwrap();
+        assert_eq!(t.read("old"), "o3\n");
+    }
+
+    #[tokio::test]
+    async fn renamed_file() {
+        let t = TestRepo::new("rename");
+        t.commit(&[("before.txt", "1\n2\n3\n4\n5\n6\n7\n8\n")]);
+        git(&t.dir, &["mv", "before.txt", "after.txt"]);
+        t.write("after.txt", "1\n2\n3\n4\n5\n6\n7\n8\n9\n");
+        git(&t.dir, &["add", "after.txt"]);
+
+        let diff = t.repo.diff_staged(&DiffOptions::default()).await.unwrap();
+        let file = &diff.files[0];
+        assert_eq!(file.status, crate::git::diff::DiffFileStatus::Renamed);
+        let hunk = &file.hunks[0];
+        let addition = hunk
+            .lines
+            .iter()
+            .position(|line| line.origin == DiffLineType::Addition)
+            .unwrap();
+
+        // Unstaging the line keeps the rename staged
+        let patch = patch::generate_line_patch(file, hunk, &[addition], true).unwrap();
+        t.repo.apply_patch(&patch, true, true).await.unwrap();
+        assert!(!t.is_tracked("before.txt"));
+        assert_eq!(t.staged("after.txt"), "1\n2\n3\n4\n5\n6\n7\n8\n");
+        assert_eq!(t.read("after.txt"), "1\n2\n3\n4\n5\n6\n7\n8\n9\n");
+
+        // Staging it onto the unrenamed index renames the file there too
+        git(&t.dir, &["reset", "-q"]);
+        assert!(t.is_tracked("before.txt"));
+        let patch = patch::generate_line_patch(file, hunk, &[addition], false).unwrap();
+        t.repo.apply_patch(&patch, true, false).await.unwrap();
+        assert!(!t.is_tracked("before.txt"));
+        assert_eq!(t.staged("after.txt"), "1\n2\n3\n4\n5\n6\n7\n8\n9\n");
+    }
+
+    #[tokio::test]
+    async fn crlf_file() {
+        let t = TestRepo::new("crlf");
+        t.commit(&[("w.txt", "a\r\nb\r\nc\r\n")]);
+        t.write("w.txt", "a\r\nB\r\nc\r\nd\r\n");
+
+        t.apply("w.txt", PatchTarget::Stage, &["+d"]).await.unwrap();
+        assert_eq!(t.staged("w.txt"), "a\r\nb\r\nc\r\nd\r\n");
+        t.apply("w.txt", PatchTarget::Discard, &["-b"]).await.unwrap();
+        assert_eq!(t.read("w.txt"), "a\r\nb\r\nB\r\nc\r\nd\r\n");
+        t.apply("w.txt", PatchTarget::Unstage, &["+d"]).await.unwrap();
+        assert_eq!(t.staged("w.txt"), "a\r\nb\r\nc\r\n");
+    }
+
+    #[tokio::test]
+    async fn hunks_are_found_by_fingerprint() {
+        let t = TestRepo::new("fingerprint");
+        let options = DiffOptions::default();
+        let lines: String = (1..=12).map(|i| format!("{}\n", i)).collect();
+        t.commit(&[("f", &lines)]);
+        // Changes at both ends, far enough apart for two hunks
+        let changed = lines.replacen("1\n", "one\n", 1).replace("12\n", "twelve\n");
+        t.write("f", &changed);
+
+        let diff = t.repo.diff_file("f", false, &options).await.unwrap();
+        let hunks = &diff.files[0].hunks;
+        assert_eq!(hunks.len(), 2);
+        let (first, second) = (hunks[0].fingerprint.clone(), hunks[1].fingerprint.clone());
+        t.repo
+            .apply_hunk_selection("f", 0, &first, None, PatchTarget::Stage, &options)
+            .await
+            .unwrap();
+        // The second hunk is now the only one, but is still found
+        t.repo
+            .apply_hunk_selection("f", 1, &second, None, PatchTarget::Stage, &options)
+            .await
+            .unwrap();
+        assert_eq!(t.staged("f"), t.read("f"));
+
+        t.write("f", "changed\n");
+        let diff = t.repo.diff_file("f", false, &options).await.unwrap();
+        let fingerprint = diff.files[0].hunks[0].fingerprint.clone();
+        t.write("f", "changed again\n");
+        let err = t
+            .repo
+            .apply_hunk_selection("f", 0, &fingerprint, None, PatchTarget::Discard, &options)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::StaleDiff);
+        assert_eq!(t.read("f"), "changed again\n");
+    }
+
+    #[tokio::test]
+    async fn interactive_rebase_runs_a_plan() {
+        let t = TestRepo::new("rebase-plan");
+        let base = t.commit_as("base", &[("a", "a\n")]);
+        t.commit_as("one", &[("b", "b\n")]);
+        t.commit_as("two", &[("c", "c\n")]);
+        t.commit_as("three", &[("d", "d\n")]);
+
+        let mut steps = t.repo.rebase_todo(&base).await.unwrap();
+        steps[0].action = RebaseAction::Drop;
+        steps[1].action = RebaseAction::Reword;
+        steps[1].message = Some("two, reworded".to_string());
+        steps.swap(1, 2);
+        let status = t.repo.interactive_rebase(&base, &steps).await.unwrap();
+        assert!(status.is_none());
+        assert_eq!(t.subjects("HEAD"), ["two, reworded", "three", "base"]);
+        assert!(!t.dir.join("b").exists());
+        assert_eq!(t.read("c"), "c\n");
+        assert_eq!(t.read("d"), "d\n");
+    }
+
+    #[tokio::test]
+    async fn interactive_rebase_rejects_incomplete_plans() {
+        let t = TestRepo::new("rebase-incomplete");
+        let base = t.commit_as("base", &[("a", "a\n")]);
+        t.commit_as("one", &[("b", "b\n")]);
+        t.commit_as("two", &[("c", "c\n")]);
+        let head = t.rev("HEAD");
+
+        // Leaving a commit out does not drop it
+        let mut steps = t.repo.rebase_todo(&base).await.unwrap();
+        steps.remove(0);
+        let err = t.repo.interactive_rebase(&base, &steps).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
+
+        // Nor does a commit from elsewhere get picked in
+        let mut steps = t.repo.rebase_todo(&base).await.unwrap();
+        steps[0].commit_id = base.clone();
+        let err = t.repo.interactive_rebase(&base, &steps).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
+        assert_eq!(t.rev("HEAD"), head);
+    }
+
+    #[tokio::test]
+    async fn interactive_rebase_refuses_mid_operation() {
+        let t = TestRepo::new("rebase-busy");
+        let base = t.commit_as("base", &[("a", "a\n")]);
+        t.commit_as("one", &[("b", "b\n")]);
+        let steps = t.repo.rebase_todo(&base).await.unwrap();
+        std::fs::write(t.dir.join(".git/MERGE_HEAD"), format!("{}\n", base)).unwrap();
+
+        let err = t.repo.interactive_rebase(&base, &steps).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::RepoState);
+    }
+
+    #[tokio::test]
+    async fn fixup_and_amend_target_commits_by_id() {
+        let t = TestRepo::new("fixup");
+        t.commit_as("base", &[("a", "a\n")]);
+        t.commit_as("same", &[("b", "b\n")]);
+        let target = t.commit_as("same", &[("c", "c\n")]);
+        t.commit_as("tip", &[("d", "d\n")]);
+
+        // Lands in the second "same", not the first one autosquash would match
+        t.write("c", "c fixed\n");
+        git(&t.dir, &["add", "c"]);
+        let result = t.repo.fixup_commit(FixupKind::Fixup, &target, None).await.unwrap();
+        assert!(result.rebase.is_none());
+        assert_eq!(t.subjects("HEAD"), ["tip", "same", "same", "base"]);
+        assert_eq!(git(&t.dir, &["show", "HEAD~1:c"]), "c fixed\n");
+        assert_eq!(git(&t.dir, &["show", "--name-only", "--format=", "HEAD~2"]), "b\n");
+
+        let target = t.rev("HEAD~1");
+        t.repo
+            .fixup_commit(FixupKind::Amend, &target, Some("reworded\n\nwith a body"))
+            .await
+            .unwrap();
+        assert_eq!(t.subjects("HEAD"), ["tip", "reworded", "same", "base"]);
+        assert_eq!(
+            git(&t.dir, &["log", "-1", "--format=%B", "HEAD~1"]).trim_end(),
+            "reworded\n\nwith a body"
+        );
+        assert_eq!(git(&t.dir, &["show", "HEAD~1:c"]), "c fixed\n");
+    }
+
+    #[tokio::test]
+    async fn fixup_refuses_merges_and_operations_in_progress() {
+        let t = TestRepo::new("fixup-refused");
+        let base = t.commit_as("base", &[("a", "a\n")]);
+        git(&t.dir, &["checkout", "-q", "-b", "side"]);
+        t.commit_as("side", &[("b", "b\n")]);
+        git(&t.dir, &["checkout", "-q", "main"]);
+        let target = t.commit_as("main", &[("c", "c\n")]);
+        git(&t.dir, &["merge", "-q", "--no-ff", "-m", "merge", "side"]);
+        let head = t.rev("HEAD");
+
+        t.write("a", "a fixed\n");
+        git(&t.dir, &["add", "a"]);
+        let err = t.repo.fixup_commit(FixupKind::Fixup, &target, None).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
+        // The root commit is rebased with `--root`, which would flatten it too
+        let err = t.repo.fixup_commit(FixupKind::Fixup, &base, None).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
+        assert_eq!(t.rev("HEAD"), head);
+
+        git(&t.dir, &["reset", "-q", "--hard", &target]);
+        t.write("a", "a fixed\n");
+        git(&t.dir, &["add", "a"]);
+        std::fs::write(t.dir.join(".git/MERGE_HEAD"), format!("{}\n", base)).unwrap();
+        let err = t.repo.fixup_commit(FixupKind::Fixup, &target, None).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::RepoState);
+        assert_eq!(t.rev("HEAD"), target);
+    }
+
+    /// Run `operation` the way journaled commands do, with the objects
+    /// `action` captured anchored, then collect garbage and undo it
+    async fn undo_after_gc(
+        t: &TestRepo,
+        action: AppResult<JournalAction>,
+        operation: impl std::future::Future<Output = AppResult<()>>,
+    ) {
+        let action = action.unwrap();
+        assert!(!action.is_empty());
+        t.repo.anchor_journal_objects(1, action.objects()).await.unwrap();
+        operation.await.unwrap();
+        // Anything only the journal still reaches must survive this
+        git(&t.dir, &["gc", "-q", "--prune=now"]);
+
+        t.repo.undo_journal_action(&action).await.unwrap();
+        t.repo.drop_journal_anchors(vec![1]).await.unwrap();
+        assert_eq!(git(&t.dir, &["for-each-ref", ANCHOR_PREFIX]), "");
+    }
+
+    #[tokio::test]
+    async fn undo_discard_all() {
+        let t = TestRepo::new("undo-discard");
+        t.commit(&[("f", "f\n"), ("g", "g\n")]);
+        t.write("f", "f changed\n");
+        t.write("new", "new\n");
+        std::fs::remove_file(t.dir.join("g")).unwrap();
+
+        undo_after_gc(&t, t.repo.capture_discard(None).await, t.repo.discard_all()).await;
+        assert_eq!(t.read("f"), "f changed\n");
+        assert_eq!(t.read("new"), "new\n");
+        assert!(!t.dir.join("g").exists());
+    }
+
+    #[tokio::test]
+    async fn undo_hard_reset() {
+        let t = TestRepo::new("undo-reset");
+        t.commit(&[("f", "1\n")]);
+        t.commit(&[("f", "2\n")]);
+        let head = t.rev("HEAD");
+        t.write("f", "3\n");
+        git(&t.dir, &["add", "f"]);
+        t.write("f", "4\n");
+        t.write("new", "new\n");
+
+        undo_after_gc(&t, t.repo.capture_reset(true).await, t.repo.reset("HEAD~1", "hard"))
+            .await;
+        assert_eq!(t.rev("HEAD"), head);
+        assert_eq!(t.staged("f"), "3\n");
+        assert_eq!(t.read("f"), "4\n");
+        assert_eq!(t.read("new"), "new\n");
+    }
+
+    #[tokio::test]
+    async fn undo_stash_drop_and_clear() {
+        let t = TestRepo::new("undo-stash");
+        t.commit(&[("f", "0\n")]);
+        for content in ["1\n", "2\n"] {
+            t.write("f", content);
+            t.repo.stash_push(Some(content.trim()), false).await.unwrap();
+        }
+        let stashes = || git(&t.dir, &["stash", "list", "--format=%H %gs"]);
+        let before = stashes();
+
+        undo_after_gc(&t, t.repo.capture_stash_drop(Some(1)).await, t.repo.stash_drop(Some(1)))
+            .await;
+        assert_eq!(git(&t.dir, &["stash", "list", "--format=%H"]).lines().count(), 2);
+        // Restored on top, keeping its message
+        assert_eq!(stashes().lines().next(), before.lines().nth(1));
+
+        git(&t.dir, &["stash", "drop", "-q"]);
+        t.write("f", "2\n");
+        t.repo.stash_push(Some("2"), false).await.unwrap();
+        let before = stashes();
+        undo_after_gc(&t, t.repo.capture_stash_clear().await, t.repo.stash_clear()).await;
+        assert_eq!(stashes(), before);
+    }
+
+    #[tokio::test]
+    async fn undo_branch_delete() {
+        let t = TestRepo::new("undo-branch");
+        t.commit(&[("f", "1\n")]);
+        let target = t.rev("HEAD");
+        t.commit(&[("f", "2\n")]);
+        git(&t.dir, &["branch", "topic", &target]);
+
+        undo_after_gc(
+            &t,
+            t.repo.capture_ref_deletion("refs/heads/topic").await,
+            t.repo.delete_branch("topic"),
+        )
+        .await;
+        assert_eq!(t.rev("topic"), target);
+    }
+
+    #[tokio::test]
+    async fn reword_a_middle_commit() {
+        let t = TestRepo::new("reword");
+        t.commit_as("base", &[("a", "a\n")]);
+        let before = t.commit_as("one", &[("b", "b\n")]);
+        let target = t.commit_as("tpyo", &[("c", "c\n")]);
+        let stacked = t.commit_as("three", &[("d", "d\n")]);
+        t.commit_as("four", &[("e", "e\n")]);
+        git(&t.dir, &["branch", "older", &before]);
+        git(&t.dir, &["branch", "stacked", &stacked]);
+        git(&t.dir, &["branch", "at-target", &target]);
+        let tree = t.rev("HEAD^{tree}");
+        t.write("e", "uncommitted\n");
+
+        let head = t.repo.reword_commit(&target, "typo\n\nFixed.").await.unwrap();
+        assert_eq!(t.rev("HEAD"), head);
+        assert_eq!(t.subjects("HEAD"), ["four", "three", "typo", "one", "base"]);
+        let message = git(&t.dir, &["log", "-1", "--format=%B", "HEAD~2"]);
+        assert_eq!(message.trim_end(), "typo\n\nFixed.");
+        assert_eq!(t.rev("HEAD^{tree}"), tree);
+        assert_eq!(t.read("e"), "uncommitted\n");
+
+        // Branches on the rewritten commits follow, the others stay
+        assert_eq!(t.rev("stacked"), t.rev("HEAD~1"));
+        assert_eq!(t.rev("at-target"), t.rev("HEAD~2"));
+        assert_eq!(t.rev("older"), before);
+    }
+
+    #[tokio::test]
+    async fn reword_refuses_commits_on_the_upstream() {
+        let t = TestRepo::new("reword-upstream");
+        t.commit_as("base", &[("a", "a\n")]);
+        let pushed = t.commit_as("pushed", &[("b", "b\n")]);
+        let local = t.commit_as("local", &[("c", "c\n")]);
+        git(&t.dir, &["remote", "add", "origin", "../nowhere"]);
+        git(&t.dir, &["update-ref", "refs/remotes/origin/main", &pushed]);
+        git(&t.dir, &["branch", "--set-upstream-to=origin/main"]);
+
+        let err = t.repo.reword_commit(&pushed, "rewritten").await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::RepoState);
+        assert_eq!(t.rev("HEAD"), local);
+
+        t.repo.reword_commit(&local, "rewritten").await.unwrap();
+        assert_eq!(t.subjects("HEAD"), ["rewritten", "pushed", "base"]);
+    }
+
+    #[tokio::test]
+    async fn reword_leaves_refs_alone_when_the_branch_moved() {
+        let t = TestRepo::new("reword-moved");
+        t.commit_as("base", &[("a", "a\n")]);
+        let target = t.commit_as("tpyo", &[("b", "b\n")]);
+        git(&t.dir, &["branch", "stacked"]);
+        let rewrites = t.repo.libgit().reword_commit(&target, "typo").unwrap();
+        assert_eq!(rewrites.len(), 2);
+
+        let moved = t.commit_as("meanwhile", &[("c", "c\n")]);
+        assert!(t.repo.apply_rewrites(&rewrites, "reword: typo").await.is_err());
+        assert_eq!(t.rev("HEAD"), moved);
+        // Nor does any other branch move
+        assert_eq!(t.rev("stacked"), target);
+    }
+
+    #[tokio::test]
+    async fn split_a_middle_commit() {
+        let t = TestRepo::new("split");
+        t.commit_as("base", &[("a", "a\n")]);
+        let target = t.commit_as("both", &[("b", "b\n"), ("c", "c\n")]);
+        t.commit_as("after", &[("d", "d\n")]);
+
+        let split = t.repo.start_split(&target).await.unwrap();
+        assert_eq!(split.commit_id, target);
+        assert!(t.repo.split_status().await.unwrap().is_some());
+        assert_eq!(t.read("b"), "b\n");
+        git(&t.dir, &["add", "b"]);
+        git(&t.dir, &["commit", "-q", "-m", "just b"]);
+
+        let err = t.repo.finish_split(None).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
+        let status = t.repo.finish_split(Some("just c")).await.unwrap();
+        assert!(status.is_none());
+        assert_eq!(t.subjects("HEAD"), ["after", "just c", "just b", "base"]);
+        assert_eq!(git(&t.dir, &["show", "--name-only", "--format=", "HEAD~1"]), "c\n");
+        assert!(t.repo.split_status().await.unwrap().is_none());
+        assert!(!t.dir.join(".git/gitsage-rebase/split.json").exists());
+    }
+
+    #[tokio::test]
+    async fn failed_split_leaves_no_marker() {
+        let t = TestRepo::new("split-conflict");
+        t.commit_as("base", &[("a", "a\n")]);
+        let target = t.commit_as("add f", &[("f", "1\n")]);
+        t.commit_as("change f", &[("f", "1\n2\n")]);
+
+        t.repo.start_split(&target).await.unwrap();
+        // Committed differently, so the next commit no longer applies
+        t.write("f", "other\n");
+        let err = t.repo.finish_split(Some("add f differently")).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::MergeConflict);
+        assert!(!t.dir.join(".git/gitsage-rebase/split.json").exists());
+        assert!(t.repo.split_status().await.unwrap().is_none());
+        assert!(t.repo.rebase_status().await.unwrap().is_some());
+
+        t.repo.rebase_abort().await.unwrap();
+        assert_eq!(t.subjects("HEAD"), ["change f", "add f", "base"]);
+    }
+
+    #[tokio::test]
+    async fn diff_revision_ranges() {
+        let t = TestRepo::new("revision-diff");
+        t.commit(&[("f", "base\n"), ("dir/x", "x\n")]);
+        git(&t.dir, &["checkout", "-q", "-b", "side"]);
+        t.commit(&[("s", "side\n"), ("dir/x", "x\nside\n")]);
+        git(&t.dir, &["checkout", "-q", "main"]);
+        t.commit(&[("m", "main\n")]);
+
+        let options = DiffOptions::default();
+        let paths = |diff: DiffOutput| -> Vec<(String, DiffFileStatus)> {
+            let mut paths: Vec<_> = diff
+                .files
+                .into_iter()
+                .map(|f| (f.new_path.or(f.old_path).unwrap(), f.status))
+                .collect();
+            paths.sort_by(|a, b| a.0.cmp(&b.0));
+            paths
+        };
+
+        // Two dots: one tip against the other, so main's own commit shows as deleted
+        let two_dots = t.repo.diff_revisions("main..side", None, &[], &options).await.unwrap();
+        assert_eq!(
+            paths(two_dots),
+            [
+                ("dir/x".to_string(), DiffFileStatus::Modified),
+                ("m".to_string(), DiffFileStatus::Deleted),
+                ("s".to_string(), DiffFileStatus::Added),
+            ]
+        );
+        let explicit = t.repo.diff_revisions("main", Some("side"), &[], &options).await.unwrap();
+        assert_eq!(explicit.stats.files_changed, 3);
+
+        // Three dots: only what side changed since it forked
+        let three_dots = t.repo.diff_revisions("main...side", None, &[], &options).await.unwrap();
+        assert_eq!(
+            paths(three_dots),
+            [
+                ("dir/x".to_string(), DiffFileStatus::Modified),
+                ("s".to_string(), DiffFileStatus::Added),
+            ]
+        );
+
+        let pathspecs = ["dir".to_string()];
+        let filtered = t
+            .repo
+            .diff_revisions("main...side", None, &pathspecs, &options)
+            .await
+            .unwrap();
+        assert_eq!(paths(filtered), [("dir/x".to_string(), DiffFileStatus::Modified)]);
+        let stats = t
+            .repo
+            .diff_revisions_stats("main...side", None, &pathspecs, &options)
+            .await
+            .unwrap();
+        assert_eq!(stats.files.len(), 1);
+        assert_eq!((stats.stats.insertions, stats.stats.deletions), (1, 0));
+
+        for (from, to) in [("main", None), ("nope..side", None), ("main", Some("nope"))] {
+            let err = t.repo.diff_revisions(from, to, &[], &options).await.unwrap_err();
+            assert_eq!(err.code(), ErrorCode::InvalidInput);
+        }
+    }
 }
@@ -7,6 +7,16 @@ use super::types::*;
 const GITHUB_API: &str = "https://api.github.com";
 const USER_AGENT: &str = "GitSage/0.1.0";
 
+/// Turn a non-2xx response into an `AppError::GitHub` carrying the status and body
+async fn api_error(resp: reqwest::Response, context: &str) -> AppError {
+    let status = resp.status().as_u16();
+    let text = resp.text().await.unwrap_or_default();
+    AppError::GitHub {
+        status,
+        message: format!("{}: {}", context, text),
+    }
+}
+
 #[derive(Clone)]
 pub struct GitHubClient {
     client: Client,
@@ -145,8 +155,7 @@ impl GitHubClient {
         if resp.status().is_success() {
             Ok(())
         } else {
-            let text = resp.text().await.unwrap_or_default();
-            Err(AppError::General(format!("Failed to merge PR: {}", text)))
+            Err(api_error(resp, "Failed to merge PR").await)
         }
     }
 
@@ -321,11 +330,7 @@ impl GitHubClient {
         if resp.status().is_success() || resp.status() == 404 {
             Ok(())
         } else {
-            let text = resp.text().await.unwrap_or_default();
-            Err(AppError::General(format!(
-                "Failed to remove label: {}",
-                text
-            )))
+            Err(api_error(resp, "Failed to remove label").await)
         }
     }
 
@@ -367,11 +372,7 @@ impl GitHubClient {
         if resp.status().is_success() {
             Ok(())
         } else {
-            let text = resp.text().await.unwrap_or_default();
-            Err(AppError::General(format!(
-                "Failed to request reviewers: {}",
-                text
-            )))
+            Err(api_error(resp, "Failed to request reviewers").await)
         }
     }
 
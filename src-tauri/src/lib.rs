@@ -33,13 +33,25 @@ pub fn run() {
                 .app_data_dir()
                 .expect("failed to resolve app data dir");
 
-            let recent_repos = persistence::load_recent_repos(&app_data_dir)
-                .unwrap_or_else(|e| {
-                    tracing::warn!("Failed to load recent repos: {e}");
-                    Vec::new()
-                });
+            let db = tauri::async_runtime::block_on(db::Database::open(&app_data_dir))?;
+            if let Err(e) =
+                tauri::async_runtime::block_on(db.import_legacy_recent_repos(&app_data_dir))
+            {
+                tracing::warn!("Failed to import recent repos: {e}");
+            }
 
-            app.manage(AppState::new_with_data(app_data_dir, recent_repos));
+            let config = persistence::load_settings(&app_data_dir).unwrap_or_else(|e| {
+                tracing::warn!("Failed to load settings: {e}");
+                config::AppConfig::default()
+            });
+
+            app.manage(AppState::new(app_data_dir, db, config));
+
+            if let Err(e) =
+                tauri::async_runtime::block_on(git::askpass::start(app.handle().clone()))
+            {
+                tracing::warn!("Failed to start askpass bridge, git will not prompt: {e}");
+            }
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -48,7 +60,11 @@ pub fn run() {
             commands::repo::init_repo,
             commands::repo::get_recent_repos,
             commands::repo::close_repo,
+            commands::repo::list_open_repos,
+            commands::repo::set_active_repo,
             commands::repo::clone_repo,
+            commands::repo::get_repo_settings,
+            commands::repo::update_repo_settings,
             // Workspace
             commands::workspace::get_status,
             commands::workspace::stage_files,
@@ -60,7 +76,9 @@ pub fn run() {
             commands::workspace::stage_hunk,
             commands::workspace::unstage_hunk,
             commands::workspace::stage_lines,
+            commands::workspace::unstage_lines,
             commands::workspace::discard_hunk,
+            commands::workspace::discard_lines,
             commands::workspace::get_conflict_files,
             commands::workspace::get_conflict_versions,
             commands::workspace::mark_resolved,
@@ -75,10 +93,18 @@ pub fn run() {
             commands::commit::undo_last_commit,
             commands::commit::reset_to_commit,
             commands::commit::get_commit_diff,
+            commands::commit::get_combined_diff,
+            commands::commit::get_revision_diff,
+            commands::commit::get_revision_diff_stats,
             commands::commit::get_file_history,
             commands::commit::search_commits,
             commands::commit::get_commit_log_paged,
             commands::commit::get_branch_log,
+            commands::commit::create_fixup_commit,
+            commands::commit::reword_commit,
+            commands::commit::start_split_commit,
+            commands::commit::get_split_status,
+            commands::commit::finish_split_commit,
             // Branch
             commands::branch::get_branches,
             commands::branch::get_current_branch,
@@ -118,6 +144,9 @@ pub fn run() {
             commands::merge::revert_abort,
             commands::merge::revert_continue,
             commands::merge::rebase_onto,
+            commands::merge::get_rebase_todo,
+            commands::merge::interactive_rebase,
+            commands::merge::get_rebase_status,
             commands::merge::rebase_abort,
             commands::merge::rebase_continue,
             commands::merge::rebase_skip,
@@ -139,6 +168,30 @@ pub fn run() {
             // AI
             commands::ai::generate_commit_message,
             commands::ai::generate_change_summary,
+            commands::ai::generate_commit_message_stream,
+            commands::ai::generate_change_summary_stream,
+            commands::ai::cancel_ai_request,
+            commands::ai::get_ai_diff_context,
+            commands::ai::get_redaction_config,
+            commands::ai::update_redaction_config,
+            commands::ai::get_ai_providers,
+            commands::ai::set_ai_api_key,
+            commands::ai::validate_ai_provider,
+            // Operations
+            commands::operation::cancel_operation,
+            commands::operation::respond_credential_request,
+            // Operation journal
+            commands::journal::get_operation_journal,
+            commands::journal::undo_operation,
+            // Reflog
+            commands::reflog::get_reflog_refs,
+            commands::reflog::get_reflog,
+            commands::reflog::get_dangling_commits,
+            commands::reflog::restore_branch,
+            commands::reflog::create_branch_at,
+            // Settings
+            commands::settings::get_settings,
+            commands::settings::update_settings,
             // GitHub
             commands::github::github_check_auth,
             commands::github::github_save_token,
@@ -1,5 +1,9 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // git and ssh run this binary to ask for credentials, see git::askpass
+    if let Some(code) = gitsage_lib::git::askpass::run_helper() {
+        std::process::exit(code);
+    }
     gitsage_lib::run()
 }
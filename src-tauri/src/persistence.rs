@@ -1,16 +1,23 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use crate::config::AppConfig;
 use crate::error::AppResult;
 use crate::state::RepoEntry;
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(serde::Deserialize)]
 struct RecentReposFile {
-    version: u32,
     repos: Vec<RepoEntry>,
 }
 
 const RECENT_REPOS_FILE: &str = "recent_repos.json";
 
+/// Path of the pre-database recent repos file, if it is still around
+pub fn legacy_recent_repos_path(app_data_dir: &Path) -> Option<PathBuf> {
+    let path = app_data_dir.join(RECENT_REPOS_FILE);
+    path.exists().then_some(path)
+}
+
+/// Read the pre-database recent repos file; superseded by the `recent_repos` table
 pub fn load_recent_repos(app_data_dir: &Path) -> AppResult<Vec<RepoEntry>> {
     let path = app_data_dir.join(RECENT_REPOS_FILE);
     if !path.exists() {
@@ -21,13 +28,63 @@ pub fn load_recent_repos(app_data_dir: &Path) -> AppResult<Vec<RepoEntry>> {
     Ok(file.repos)
 }
 
-pub fn save_recent_repos(app_data_dir: &Path, repos: &[RepoEntry]) -> AppResult<()> {
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SettingsFile {
+    version: u32,
+    config: AppConfig,
+}
+
+const SETTINGS_FILE: &str = "settings.json";
+const SETTINGS_VERSION: u32 = 1;
+
+pub fn load_settings(app_data_dir: &Path) -> AppResult<AppConfig> {
+    let path = app_data_dir.join(SETTINGS_FILE);
+    if !path.exists() {
+        return Ok(AppConfig::default());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    let value: serde_json::Value = serde_json::from_str(&content)?;
+    let file: SettingsFile = serde_json::from_value(migrate_settings(value))?;
+    Ok(file.config)
+}
+
+pub fn save_settings(app_data_dir: &Path, config: &AppConfig) -> AppResult<()> {
     std::fs::create_dir_all(app_data_dir)?;
-    let file = RecentReposFile {
-        version: 1,
-        repos: repos.to_vec(),
+    let file = SettingsFile {
+        version: SETTINGS_VERSION,
+        config: config.clone(),
     };
     let content = serde_json::to_string_pretty(&file)?;
-    std::fs::write(app_data_dir.join(RECENT_REPOS_FILE), content)?;
+    // Write to a temp file first so a crash mid-write can't leave truncated settings
+    let tmp = app_data_dir.join(format!("{SETTINGS_FILE}.tmp"));
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, app_data_dir.join(SETTINGS_FILE))?;
     Ok(())
 }
+
+/// Bring an older settings file up to `SETTINGS_VERSION`.
+///
+/// Fields added within a version are covered by `#[serde(default)]` on the
+/// config structs; only structural changes need a step here.
+fn migrate_settings(mut value: serde_json::Value) -> serde_json::Value {
+    let mut version = value
+        .get("version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as u32;
+
+    while version < SETTINGS_VERSION {
+        value = match version {
+            // Unversioned file: the bare `AppConfig` object
+            0 => serde_json::json!({ "version": 1, "config": value }),
+            _ => unreachable!("missing settings migration from version {version}"),
+        };
+        version += 1;
+    }
+
+    if version > SETTINGS_VERSION {
+        tracing::warn!(
+            "Settings file version {version} is newer than supported version {SETTINGS_VERSION}, unknown fields are ignored"
+        );
+    }
+    value
+}
@@ -1,22 +1,206 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use tokio::sync::{oneshot, Mutex};
 
+use crate::config::repo::RepoSettings;
+use crate::config::AppConfig;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::git::askpass::CredentialResponse;
 use crate::git::repository::GitRepository;
 use crate::github::client::GitHubClient;
+use crate::watcher::RepoWatcher;
 
 /// Application state shared across commands
 pub struct AppState {
-    /// Currently opened repository
-    pub current_repo: Mutex<Option<Arc<GitRepository>>>,
-    /// Recent repositories list
-    pub recent_repos: Mutex<Vec<RepoEntry>>,
+    /// Repositories open in the app
+    pub repos: Mutex<RepoRegistry>,
     /// Cached GitHub API client (authenticated)
     pub github_client: Mutex<Option<GitHubClient>>,
+    /// Application configuration
+    pub config: Mutex<AppConfig>,
+    /// Cancel handles for in-flight streaming AI requests, keyed by request id
+    pub ai_requests: Mutex<CancelRegistry>,
+    /// Cancel handles for running git network operations, keyed by operation id
+    pub operations: Mutex<CancelRegistry>,
+    /// Askpass prompts waiting for an answer from the frontend, keyed by request id
+    pub credential_requests: Mutex<HashMap<String, oneshot::Sender<Option<CredentialResponse>>>>,
+    /// Application database (recent repos, per-repo settings, caches)
+    pub db: Database,
     /// Application data directory (for persisting JSON files)
     pub app_data_dir: PathBuf,
 }
 
+/// A repository open in the app, together with the state scoped to it.
+///
+/// Derefs to `GitRepository`, so commands can call git operations on it directly.
+pub struct OpenRepo {
+    pub id: String,
+    pub repo: GitRepository,
+    /// Per-repo settings, resolved when the repository is opened
+    pub settings: Mutex<RepoSettings>,
+    /// File watcher, stopped when the entry is dropped
+    pub watcher: std::sync::Mutex<RepoWatcher>,
+}
+
+impl Deref for OpenRepo {
+    type Target = GitRepository;
+
+    fn deref(&self) -> &GitRepository {
+        &self.repo
+    }
+}
+
+/// Open repository as listed to the frontend (one per tab)
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenRepoInfo {
+    pub id: String,
+    pub name: String,
+    pub path: PathBuf,
+    pub active: bool,
+}
+
+/// Open repositories keyed by id, plus the one commands target by default
+#[derive(Default)]
+pub struct RepoRegistry {
+    /// In the order they were opened, which is the tab order
+    repos: Vec<Arc<OpenRepo>>,
+    active: Option<String>,
+    next_id: u64,
+}
+
+impl RepoRegistry {
+    pub fn get(&self, id: &str) -> Option<Arc<OpenRepo>> {
+        self.repos.iter().find(|r| r.id == id).cloned()
+    }
+
+    /// Find the repository open at `path`, comparing canonical paths so a
+    /// symlink or a `..` reaches the same entry
+    pub fn find_by_path(&self, path: &Path) -> Option<Arc<OpenRepo>> {
+        let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+        let path = canonical(path);
+        self.repos.iter().find(|r| canonical(&r.path) == path).cloned()
+    }
+
+    pub fn active(&self) -> Option<Arc<OpenRepo>> {
+        self.active.as_deref().and_then(|id| self.get(id))
+    }
+
+    /// Register a newly opened repository and make it the active one
+    pub fn insert(&mut self, repo: GitRepository, settings: RepoSettings) -> Arc<OpenRepo> {
+        self.next_id += 1;
+        let entry = Arc::new(OpenRepo {
+            id: format!("repo-{}", self.next_id),
+            repo,
+            settings: Mutex::new(settings),
+            watcher: std::sync::Mutex::new(RepoWatcher::new()),
+        });
+        self.active = Some(entry.id.clone());
+        self.repos.push(entry.clone());
+        entry
+    }
+
+    pub fn set_active(&mut self, id: &str) -> AppResult<()> {
+        if self.get(id).is_none() {
+            return Err(AppError::RepoNotFound(id.to_string()));
+        }
+        self.active = Some(id.to_string());
+        Ok(())
+    }
+
+    /// Remove a repository; if it was active, its neighbour becomes active
+    pub fn remove(&mut self, id: &str) -> Option<Arc<OpenRepo>> {
+        let idx = self.repos.iter().position(|r| r.id == id)?;
+        let removed = self.repos.remove(idx);
+        if self.active.as_deref() == Some(id) {
+            self.active = self
+                .repos
+                .get(idx.min(self.repos.len().saturating_sub(1)))
+                .map(|r| r.id.clone());
+        }
+        Some(removed)
+    }
+
+    pub fn info(&self, repo: &OpenRepo) -> OpenRepoInfo {
+        OpenRepoInfo {
+            id: repo.id.clone(),
+            name: repo.name(),
+            path: repo.path.clone(),
+            active: self.active.as_deref() == Some(repo.id.as_str()),
+        }
+    }
+
+    pub fn list(&self) -> Vec<OpenRepoInfo> {
+        self.repos.iter().map(|r| self.info(r)).collect()
+    }
+}
+
+/// How long a cancel for a request that has not started yet is remembered
+const EARLY_CANCEL_TTL: Duration = Duration::from_secs(60);
+
+/// Cancel handles of running requests, keyed by the id the frontend gave them.
+///
+/// The frontend may cancel a request before its command got as far as
+/// registering it; such a cancel is remembered for a while, and the request
+/// is cancelled as soon as it registers.
+pub struct CancelRegistry {
+    /// What the requests are, for error messages
+    kind: &'static str,
+    running: HashMap<String, oneshot::Sender<()>>,
+    early: HashMap<String, Instant>,
+}
+
+impl CancelRegistry {
+    pub fn new(kind: &'static str) -> Self {
+        Self {
+            kind,
+            running: HashMap::new(),
+            early: HashMap::new(),
+        }
+    }
+
+    /// Register a request; the receiver fires when it is cancelled, right
+    /// away if that already happened
+    pub fn register(&mut self, id: &str) -> AppResult<oneshot::Receiver<()>> {
+        if self.running.contains_key(id) {
+            return Err(AppError::InvalidInput(format!(
+                "{} '{}' is already running",
+                self.kind, id
+            )));
+        }
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        if self.early.remove(id).is_some() {
+            let _ = cancel_tx.send(());
+        } else {
+            self.running.insert(id.to_string(), cancel_tx);
+        }
+        Ok(cancel_rx)
+    }
+
+    pub fn cancel(&mut self, id: &str) {
+        match self.running.remove(id) {
+            Some(cancel) => {
+                let _ = cancel.send(());
+            }
+            None => {
+                // Ids are not reused, so a cancel that came after its request
+                // finished only needs to be forgotten eventually
+                self.early.retain(|_, at| at.elapsed() < EARLY_CANCEL_TTL);
+                self.early.insert(id.to_string(), Instant::now());
+            }
+        }
+    }
+
+    /// Forget a request that finished
+    pub fn finish(&mut self, id: &str) {
+        self.running.remove(id);
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct RepoEntry {
     pub path: PathBuf,
@@ -25,27 +209,71 @@ pub struct RepoEntry {
 }
 
 impl AppState {
-    pub fn new() -> Self {
+    pub fn new(app_data_dir: PathBuf, db: Database, config: AppConfig) -> Self {
         Self {
-            current_repo: Mutex::new(None),
-            recent_repos: Mutex::new(Vec::new()),
+            repos: Mutex::new(RepoRegistry::default()),
             github_client: Mutex::new(None),
-            app_data_dir: PathBuf::new(),
+            config: Mutex::new(config),
+            ai_requests: Mutex::new(CancelRegistry::new("AI request")),
+            operations: Mutex::new(CancelRegistry::new("Operation")),
+            credential_requests: Mutex::new(HashMap::new()),
+            db,
+            app_data_dir,
         }
     }
 
-    pub fn new_with_data(app_data_dir: PathBuf, recent_repos: Vec<RepoEntry>) -> Self {
-        Self {
-            current_repo: Mutex::new(None),
-            recent_repos: Mutex::new(recent_repos),
-            github_client: Mutex::new(None),
-            app_data_dir,
+    /// Look up an open repository; `None` targets the active one
+    pub async fn repo(&self, id: Option<&str>) -> AppResult<Arc<OpenRepo>> {
+        let repos = self.repos.lock().await;
+        match id {
+            Some(id) => repos
+                .get(id)
+                .ok_or_else(|| AppError::RepoNotFound(id.to_string())),
+            None => repos
+                .active()
+                .ok_or(AppError::General("No repository opened".into())),
         }
     }
 }
 
-impl Default for AppState {
-    fn default() -> Self {
-        Self::new()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn repos_are_found_by_canonical_path() {
+        let dir = std::env::temp_dir()
+            .join(format!("gitsage-test-registry-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo_dir = dir.join("repo");
+        let repo = GitRepository::init(&repo_dir).unwrap();
+        std::os::unix::fs::symlink(&repo_dir, dir.join("link")).unwrap();
+
+        let mut registry = RepoRegistry::default();
+        let entry = registry.insert(repo, RepoSettings::default());
+        assert_eq!(registry.find_by_path(&dir.join("link")).unwrap().id, entry.id);
+        assert_eq!(registry.find_by_path(&dir.join("link/../repo")).unwrap().id, entry.id);
+        assert!(registry.find_by_path(&dir.join("other")).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn cancels_before_and_after_registering() {
+        let mut requests = CancelRegistry::new("Request");
+        let mut running = requests.register("a").unwrap();
+        assert!(running.try_recv().is_err());
+        assert!(requests.register("a").is_err());
+        requests.cancel("a");
+        assert!(running.try_recv().is_ok());
+
+        // Cancelled before it started
+        requests.cancel("b");
+        let mut early = requests.register("b").unwrap();
+        assert!(early.try_recv().is_ok());
+        // Only once
+        requests.finish("b");
+        let mut again = requests.register("b").unwrap();
+        assert!(again.try_recv().is_err());
     }
 }
@@ -1,66 +1,333 @@
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use notify::RecursiveMode;
-use notify_debouncer_mini::{new_debouncer, DebounceEventResult};
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
 use tauri::{AppHandle, Emitter};
-use tokio::sync::Mutex;
 use tracing::{error, info};
 
+use crate::git::libgit::LibGitOps;
+use crate::git::rebase::RebaseStatus;
+use crate::git::repository::{GitRepository, RepoOperationState};
+
+/// Payload of `repo:workdir-changed`
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkdirChanged {
+    pub repo_id: String,
+    /// Changed paths relative to the work tree, `/`-separated
+    pub paths: Vec<String>,
+}
+
+/// Payload of `repo:index-changed`, `repo:head-changed` and `repo:refs-changed`
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoChanged {
+    pub repo_id: String,
+}
+
+/// Payload of `repo:operation-state-changed`
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationStateChanged {
+    pub repo_id: String,
+    pub state: RepoOperationState,
+    /// Progress of the rebase in progress: current step, pause reason and
+    /// remaining steps
+    pub rebase: Option<RebaseStatus>,
+}
+
+/// Operation state as reported in `repo:operation-state-changed`
+type OperationState = (RepoOperationState, Option<RebaseStatus>);
+
+fn operation_state(libgit: &LibGitOps) -> Option<OperationState> {
+    let state = libgit.repo_state().ok()?;
+    Some((state, libgit.rebase_status().ok().flatten()))
+}
+
+/// What a single changed path means for the UI
+#[derive(Debug, PartialEq)]
+enum Change {
+    Workdir(String),
+    Index,
+    Head,
+    Refs,
+    OperationState,
+    /// `info/exclude`, the repository's own ignore rules
+    IgnoreRules,
+    /// Objects, logs of other refs, lock files, ...
+    Noise,
+}
+
+/// Classify a changed path inside the git directory
+fn classify_git_path(rel: &Path) -> Change {
+    let rel = rel.to_string_lossy().replace('\\', "/");
+    if rel.ends_with(".lock") {
+        // Written then renamed over the real file, which fires its own event
+        return Change::Noise;
+    }
+    match rel.as_str() {
+        "index" => Change::Index,
+        // logs/HEAD is appended on every commit, reset and checkout
+        "HEAD" | "logs/HEAD" => Change::Head,
+        "packed-refs" => Change::Refs,
+        "MERGE_HEAD" | "CHERRY_PICK_HEAD" | "REVERT_HEAD" => Change::OperationState,
+        "info/exclude" => Change::IgnoreRules,
+        _ if rel.starts_with("refs/") => Change::Refs,
+        _ if rel.starts_with("rebase-merge") || rel.starts_with("rebase-apply") => {
+            Change::OperationState
+        }
+        _ if rel.starts_with("sequencer") => Change::OperationState,
+        _ => Change::Noise,
+    }
+}
+
+/// Quiet period after the last relevant change before events are emitted
+const DEBOUNCE: Duration = Duration::from_millis(500);
+/// Longest a batch is held back while changes keep coming, so the UI still
+/// refreshes during a long build or checkout
+const MAX_BATCH_WAIT: Duration = Duration::from_secs(2);
+
+/// Gather the changes following `first` until `debounce` passes without one,
+/// or `max_wait` after `first` at the latest
+fn collect_batch(
+    rx: &mpsc::Receiver<Change>,
+    first: Change,
+    debounce: Duration,
+    max_wait: Duration,
+) -> Vec<Change> {
+    let deadline = Instant::now() + max_wait;
+    let mut batch = vec![first];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        match rx.recv_timeout(debounce.min(remaining)) {
+            Ok(change) => batch.push(change),
+            Err(_) => break,
+        }
+    }
+    batch
+}
+
+/// Paths watched for one repository, canonicalized so event paths can be matched
+#[derive(Clone)]
+struct WatchedPaths {
+    workdir: PathBuf,
+    git_dir: PathBuf,
+}
+
+impl WatchedPaths {
+    fn classify(&self, path: &Path) -> Change {
+        if let Ok(rel) = path.strip_prefix(&self.git_dir) {
+            return classify_git_path(rel);
+        }
+        match path.strip_prefix(&self.workdir) {
+            Ok(rel) if !rel.as_os_str().is_empty() => {
+                Change::Workdir(rel.to_string_lossy().replace('\\', "/"))
+            }
+            _ => Change::Noise,
+        }
+    }
+}
+
+/// The repository's ignore rules (`.gitignore` files, `info/exclude` and
+/// `core.excludesFile`), evaluated by libgit2.
+///
+/// Owns its own `git2::Repository` so matching never contends with git
+/// operations on the shared handle.
+struct IgnoreMatcher {
+    workdir: PathBuf,
+    repo: Option<git2::Repository>,
+}
+
+impl IgnoreMatcher {
+    fn new(workdir: &Path) -> Self {
+        Self {
+            workdir: workdir.to_path_buf(),
+            repo: git2::Repository::open(workdir).ok(),
+        }
+    }
+
+    /// Reopen the repository so edited ignore files take effect
+    fn reload(&mut self) {
+        self.repo = git2::Repository::open(&self.workdir).ok();
+    }
+
+    fn is_ignored(&self, rel: &str) -> bool {
+        self.repo
+            .as_ref()
+            .and_then(|repo| repo.is_path_ignored(rel).ok())
+            .unwrap_or(false)
+    }
+}
+
+/// Whether a path holds ignore rules
+fn is_ignore_file(change: &Change) -> bool {
+    match change {
+        Change::Workdir(rel) => rel == ".gitignore" || rel.ends_with("/.gitignore"),
+        Change::IgnoreRules => true,
+        _ => false,
+    }
+}
+
 /// File watcher that monitors repository changes
 pub struct RepoWatcher {
-    #[allow(dead_code)]
-    debouncer: Option<notify_debouncer_mini::Debouncer<notify::RecommendedWatcher>>,
+    watcher: Option<notify::RecommendedWatcher>,
 }
 
 impl RepoWatcher {
     pub fn new() -> Self {
-        Self { debouncer: None }
+        Self { watcher: None }
     }
 
-    /// Start watching a repository path
+    /// Start watching a repository's work tree and git directory, emitting
+    /// `repo:*` events tagged with `repo_id`.
+    ///
+    /// Paths matched by the repository's ignore rules are dropped as they
+    /// arrive, before debouncing, so build output never delays or triggers a
+    /// refresh.
     pub fn watch(
         &mut self,
-        repo_path: &Path,
+        repo: &GitRepository,
+        repo_id: String,
         app_handle: AppHandle,
     ) -> Result<(), notify::Error> {
-        let app = Arc::new(Mutex::new(app_handle));
-        let mut debouncer = new_debouncer(Duration::from_millis(500), move |res: DebounceEventResult| {
-            match res {
-                Ok(events) => {
-                    let has_changes = events.iter().any(|e| {
-                        // Ignore .git directory internal changes
-                        let path_str = e.path.to_string_lossy();
-                        !path_str.contains(".git/") && !path_str.contains(".git\\")
-                    });
-
-                    if has_changes {
-                        let app = app.clone();
-                        tokio::spawn(async move {
-                            let handle = app.lock().await;
-                            if let Err(e) = handle.emit("repo:files-changed", ()) {
-                                error!("Failed to emit files-changed event: {}", e);
-                            }
-                        });
-                    }
-                }
+        let paths = WatchedPaths {
+            workdir: repo.path.canonicalize()?,
+            git_dir: repo.git_dir.canonicalize()?,
+        };
+        let libgit: Arc<LibGitOps> = repo.libgit();
+
+        let (tx, rx) = mpsc::channel::<Change>();
+        let filter_paths = paths.clone();
+        let mut ignore = IgnoreMatcher::new(&paths.workdir);
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let event = match res {
+                Ok(event) => event,
                 Err(e) => {
                     error!("File watch error: {:?}", e);
+                    return;
+                }
+            };
+            // Reads (e.g. a compiler opening sources) change nothing
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            for path in &event.paths {
+                let change = filter_paths.classify(path);
+                if is_ignore_file(&change) {
+                    ignore.reload();
+                }
+                let keep = match &change {
+                    Change::Workdir(rel) => !ignore.is_ignored(rel),
+                    Change::IgnoreRules | Change::Noise => false,
+                    _ => true,
+                };
+                // The receiver only goes away once the watcher is dropped
+                if keep && tx.send(change).is_err() {
+                    return;
                 }
             }
         })?;
 
-        debouncer.watcher().watch(repo_path, RecursiveMode::Recursive)?;
-        info!("Watching repository: {}", repo_path.display());
-        self.debouncer = Some(debouncer);
+        watcher.watch(&paths.workdir, RecursiveMode::Recursive)?;
+        // Linked worktrees and separate git dirs live outside the work tree
+        if !paths.git_dir.starts_with(&paths.workdir) {
+            watcher.watch(&paths.git_dir, RecursiveMode::Recursive)?;
+        }
+
+        let workdir = paths.workdir.clone();
+        std::thread::spawn(move || {
+            let mut last_state = operation_state(&libgit);
+            // Ends when the watcher, and with it the sender, is dropped
+            while let Ok(first) = rx.recv() {
+                let batch = collect_batch(&rx, first, DEBOUNCE, MAX_BATCH_WAIT);
+                emit_changes(&app_handle, &repo_id, &libgit, &mut last_state, batch);
+            }
+        });
+
+        info!("Watching repository: {}", workdir.display());
+        self.watcher = Some(watcher);
         Ok(())
     }
 
     /// Stop watching
     pub fn unwatch(&mut self) {
-        self.debouncer = None;
-        info!("Stopped watching repository");
+        if self.watcher.take().is_some() {
+            info!("Stopped watching repository");
+        }
+    }
+}
+
+/// Emit one event per kind of change in a debounced batch
+fn emit_changes(
+    app_handle: &AppHandle,
+    repo_id: &str,
+    libgit: &LibGitOps,
+    last_state: &mut Option<OperationState>,
+    batch: Vec<Change>,
+) {
+    let mut workdir_paths = BTreeSet::new();
+    let (mut index, mut head, mut refs, mut operation) = (false, false, false, false);
+    for change in batch {
+        match change {
+            Change::Workdir(path) => {
+                workdir_paths.insert(path);
+            }
+            Change::Index => index = true,
+            Change::Head => head = true,
+            Change::Refs => refs = true,
+            Change::OperationState => operation = true,
+            Change::IgnoreRules | Change::Noise => {}
+        }
+    }
+
+    let emit = |event: &str, payload: RepoChanged| {
+        if let Err(e) = app_handle.emit(event, payload) {
+            error!("Failed to emit {} event: {}", event, e);
+        }
+    };
+    let changed = || RepoChanged {
+        repo_id: repo_id.to_string(),
+    };
+
+    if !workdir_paths.is_empty() {
+        let payload = WorkdirChanged {
+            repo_id: repo_id.to_string(),
+            paths: workdir_paths.into_iter().collect(),
+        };
+        if let Err(e) = app_handle.emit("repo:workdir-changed", payload) {
+            error!("Failed to emit repo:workdir-changed event: {}", e);
+        }
+    }
+    if index {
+        emit("repo:index-changed", changed());
+    }
+    if head {
+        emit("repo:head-changed", changed());
+    }
+    if refs {
+        emit("repo:refs-changed", changed());
+    }
+    if operation {
+        // Only report real transitions and rebase steps, not every write
+        // git makes to its state files
+        let state = operation_state(libgit);
+        if state != *last_state {
+            if let Some((state, rebase)) = state.clone() {
+                let payload = OperationStateChanged {
+                    repo_id: repo_id.to_string(),
+                    state,
+                    rebase,
+                };
+                if let Err(e) = app_handle.emit("repo:operation-state-changed", payload) {
+                    error!("Failed to emit repo:operation-state-changed event: {}", e);
+                }
+            }
+            *last_state = state;
+        }
     }
 }
 
@@ -69,3 +336,136 @@ impl Default for RepoWatcher {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn git_paths() {
+        let cases = [
+            ("index", Change::Index),
+            ("HEAD", Change::Head),
+            ("logs/HEAD", Change::Head),
+            ("packed-refs", Change::Refs),
+            ("refs/heads/main", Change::Refs),
+            ("refs/remotes/origin/main", Change::Refs),
+            ("refs/tags/v1.0", Change::Refs),
+            ("MERGE_HEAD", Change::OperationState),
+            ("CHERRY_PICK_HEAD", Change::OperationState),
+            ("REVERT_HEAD", Change::OperationState),
+            ("rebase-merge/msgnum", Change::OperationState),
+            ("rebase-merge", Change::OperationState),
+            ("rebase-apply/next", Change::OperationState),
+            ("sequencer/todo", Change::OperationState),
+            ("info/exclude", Change::IgnoreRules),
+            // Lock files are renamed over the real file, which is reported too
+            ("index.lock", Change::Noise),
+            ("HEAD.lock", Change::Noise),
+            ("refs/heads/main.lock", Change::Noise),
+            ("objects/ab/cdef0123", Change::Noise),
+            ("logs/refs/heads/main", Change::Noise),
+            ("FETCH_HEAD", Change::Noise),
+            ("ORIG_HEAD", Change::Noise),
+            ("config", Change::Noise),
+            ("COMMIT_EDITMSG", Change::Noise),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(classify_git_path(Path::new(path)), expected, "{path}");
+        }
+    }
+
+    #[test]
+    fn windows_separators() {
+        assert_eq!(classify_git_path(Path::new("refs\\heads\\main")), Change::Refs);
+        assert_eq!(
+            classify_git_path(Path::new("rebase-merge\\done")),
+            Change::OperationState
+        );
+    }
+
+    #[test]
+    fn watched_paths() {
+        let paths = WatchedPaths {
+            workdir: PathBuf::from("/work/repo"),
+            git_dir: PathBuf::from("/work/repo/.git"),
+        };
+        assert_eq!(paths.classify(Path::new("/work/repo/.git/index")), Change::Index);
+        assert_eq!(
+            paths.classify(Path::new("/work/repo/src/main.rs")),
+            Change::Workdir("src/main.rs".into())
+        );
+        assert_eq!(paths.classify(Path::new("/work/repo")), Change::Noise);
+        assert_eq!(paths.classify(Path::new("/elsewhere/file")), Change::Noise);
+    }
+
+    #[test]
+    fn batch_ends_after_a_quiet_period() {
+        let (tx, rx) = mpsc::channel();
+        tx.send(Change::Head).unwrap();
+        let batch = collect_batch(
+            &rx,
+            Change::Index,
+            Duration::from_millis(50),
+            Duration::from_secs(10),
+        );
+        assert_eq!(batch, [Change::Index, Change::Head]);
+    }
+
+    #[test]
+    fn batch_is_flushed_while_changes_keep_coming() {
+        let (tx, rx) = mpsc::channel();
+        // Changes every 10ms, well inside the debounce, for a second
+        let sender = std::thread::spawn(move || {
+            for i in 0..100 {
+                if tx.send(Change::Workdir(i.to_string())).is_err() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        });
+
+        let started = Instant::now();
+        let batch = collect_batch(
+            &rx,
+            Change::Index,
+            Duration::from_millis(200),
+            Duration::from_millis(300),
+        );
+        let waited = started.elapsed();
+        assert!(waited < Duration::from_millis(600), "{:?}", waited);
+        assert!(batch.len() > 1 && batch.len() < 101, "{}", batch.len());
+
+        drop(rx);
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn ignore_rules_are_reloaded() {
+        let dir = std::env::temp_dir()
+            .join(format!("gitsage-test-ignore-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        git2::Repository::init(&dir).unwrap();
+        let paths = WatchedPaths {
+            workdir: dir.clone(),
+            git_dir: dir.join(".git"),
+        };
+        let mut ignore = IgnoreMatcher::new(&dir);
+        assert!(!ignore.is_ignored("build.log"));
+        assert!(!ignore.is_ignored("target/out"));
+
+        std::fs::write(dir.join(".gitignore"), "*.log\n").unwrap();
+        let change = paths.classify(&dir.join(".gitignore"));
+        assert!(is_ignore_file(&change));
+        ignore.reload();
+        assert!(ignore.is_ignored("build.log"));
+
+        std::fs::write(dir.join(".git/info/exclude"), "target/\n").unwrap();
+        let change = paths.classify(&dir.join(".git/info/exclude"));
+        assert_eq!(change, Change::IgnoreRules);
+        assert!(is_ignore_file(&change));
+        ignore.reload();
+        assert!(ignore.is_ignored("target/out"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -161,6 +161,7 @@ const App: Component = () => {
                 <WorkspaceView
                   files={repoState.fileStatuses}
                   repoState={repoState.repoState}
+                  rebaseStatus={repoState.rebaseStatus}
                   stashes={repoState.stashes}
                   onRefresh={() => repoActions.refreshAll()}
                   onStageFiles={(paths) => repoActions.stageFiles(paths)}
@@ -8,10 +8,10 @@ export interface DiffViewerProps {
   diff: DiffOutput | null;
   mode?: 'unified' | 'side-by-side';
   staged?: boolean;
-  onStageHunk?: (path: string, index: number) => void;
-  onUnstageHunk?: (path: string, index: number) => void;
-  onDiscardHunk?: (path: string, index: number) => void;
-  onStageLines?: (path: string, hunkIndex: number, lines: number[]) => void;
+  onStageHunk?: (path: string, index: number, fingerprint: string) => void;
+  onUnstageHunk?: (path: string, index: number, fingerprint: string) => void;
+  onDiscardHunk?: (path: string, index: number, fingerprint: string) => void;
+  onStageLines?: (path: string, hunkIndex: number, fingerprint: string, lines: number[]) => void;
 }
 
 const containerStyle: Record<string, string> = {
@@ -152,10 +152,10 @@ const DiffViewer: Component<DiffViewerProps> = (props) => {
     return (selectedLines().get(key)?.size ?? 0) > 0;
   };
 
-  const stageSelectedLines = (filePath: string, hunkIndex: number) => {
+  const stageSelectedLines = (filePath: string, hunkIndex: number, fingerprint: string) => {
     const lines = getSelectedLineIndices(filePath, hunkIndex);
     if (lines.length > 0) {
-      props.onStageLines?.(filePath, hunkIndex, lines);
+      props.onStageLines?.(filePath, hunkIndex, fingerprint, lines);
       // Clear selection after staging
       setSelectedLines((prev) => {
         const next = new Map(prev);
@@ -214,17 +214,17 @@ const DiffViewer: Component<DiffViewerProps> = (props) => {
                                 staged={props.staged}
                                 onStageHunk={
                                   props.onStageHunk
-                                    ? (idx) => props.onStageHunk!(filePath, idx)
+                                    ? (idx) => props.onStageHunk!(filePath, idx, hunk.fingerprint)
                                     : undefined
                                 }
                                 onUnstageHunk={
                                   props.onUnstageHunk
-                                    ? (idx) => props.onUnstageHunk!(filePath, idx)
+                                    ? (idx) => props.onUnstageHunk!(filePath, idx, hunk.fingerprint)
                                     : undefined
                                 }
                                 onDiscardHunk={
                                   props.onDiscardHunk
-                                    ? (idx) => props.onDiscardHunk!(filePath, idx)
+                                    ? (idx) => props.onDiscardHunk!(filePath, idx, hunk.fingerprint)
                                     : undefined
                                 }
                               />
@@ -232,7 +232,7 @@ const DiffViewer: Component<DiffViewerProps> = (props) => {
                             <Show when={hasSelectedLines(filePath, hunkIdx()) && props.onStageLines}>
                               <button
                                 style={stageSelectedBtnStyle}
-                                onClick={() => stageSelectedLines(filePath, hunkIdx())}
+                                onClick={() => stageSelectedLines(filePath, hunkIdx(), hunk.fingerprint)}
                                 title="Stage selected lines"
                               >
                                 Stage Selected
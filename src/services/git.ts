@@ -1,6 +1,6 @@
 import type {
-  FileStatus, BranchInfo, CommitInfo, DiffOutput, RepoEntry,
-  StashEntry, RepoOperationState, TagInfo, RemoteInfo,
+  FileStatus, BranchInfo, CommitInfo, DiffOutput, RepoEntry, OpenRepoInfo,
+  StashEntry, RepoOperationState, RebaseStatus, TagInfo, RemoteInfo,
   ConflictFile, ConflictVersions, BlameLine, LineChange, SubmoduleInfo,
 } from '../types';
 import * as ipc from './ipc';
@@ -12,11 +12,11 @@ import * as ipc from './ipc';
 
 // ==================== 仓库管理 ====================
 
-export async function openRepo(path: string): Promise<string> {
+export async function openRepo(path: string): Promise<OpenRepoInfo> {
   return ipc.openRepo(path);
 }
 
-export async function initRepo(path: string): Promise<string> {
+export async function initRepo(path: string): Promise<OpenRepoInfo> {
   return ipc.initRepo(path);
 }
 
@@ -66,20 +66,55 @@ export async function getStagedDiff(): Promise<DiffOutput> {
 
 // ==================== Hunk 操作 ====================
 
-export async function stageHunk(path: string, hunkIndex: number): Promise<void> {
-  return ipc.stageHunk(path, hunkIndex);
+export async function stageHunk(
+  path: string,
+  hunkIndex: number,
+  fingerprint: string,
+): Promise<void> {
+  return ipc.stageHunk(path, hunkIndex, fingerprint);
 }
 
-export async function unstageHunk(path: string, hunkIndex: number): Promise<void> {
-  return ipc.unstageHunk(path, hunkIndex);
+export async function unstageHunk(
+  path: string,
+  hunkIndex: number,
+  fingerprint: string,
+): Promise<void> {
+  return ipc.unstageHunk(path, hunkIndex, fingerprint);
 }
 
-export async function stageLines(path: string, hunkIndex: number, lineIndices: number[]): Promise<void> {
-  return ipc.stageLines(path, hunkIndex, lineIndices);
+export async function stageLines(
+  path: string,
+  hunkIndex: number,
+  fingerprint: string,
+  lineIndices: number[],
+): Promise<void> {
+  return ipc.stageLines(path, hunkIndex, fingerprint, lineIndices);
 }
 
-export async function discardHunk(path: string, hunkIndex: number): Promise<void> {
-  return ipc.discardHunk(path, hunkIndex);
+export async function unstageLines(
+  path: string,
+  hunkIndex: number,
+  fingerprint: string,
+  lineIndices: number[],
+): Promise<void> {
+  return ipc.unstageLines(path, hunkIndex, fingerprint, lineIndices);
+}
+
+export async function discardHunk(
+  path: string,
+  hunkIndex: number,
+  fingerprint: string,
+): Promise<void> {
+  return ipc.discardHunk(path, hunkIndex, fingerprint);
+}
+
+export async function discardLines(
+  path: string,
+  hunkIndex: number,
+  fingerprint: string,
+  lineIndices: number[],
+): Promise<void> {
+  return ipc.discardLines(path, hunkIndex, fingerprint, lineIndices);
 }
 
 // ==================== 提交 ====================
@@ -244,6 +279,10 @@ export async function getRepoState(): Promise<RepoOperationState> {
   return ipc.getRepoState();
 }
 
+export async function getRebaseStatus(): Promise<RebaseStatus | null> {
+  return ipc.getRebaseStatus();
+}
+
 export async function cherryPick(commitId: string): Promise<void> {
   return ipc.cherryPick(commitId);
 }
@@ -298,11 +337,11 @@ export async function deleteTag(name: string): Promise<void> {
   return ipc.deleteTag(name);
 }
 
-export async function pushTag(remote: string, tag: string): Promise<void> {
-  return ipc.pushTag(remote, tag);
+export async function pushTag(tag: string, remote?: string): Promise<void> {
+  return ipc.pushTag(tag, remote);
 }
 
-export async function pushAllTags(remote: string): Promise<void> {
+export async function pushAllTags(remote?: string): Promise<void> {
   return ipc.pushAllTags(remote);
 }
 
@@ -1,15 +1,18 @@
 import { invoke } from "@tauri-apps/api/core";
 import { logger } from "@/utils/logger";
+import { IpcError, errorMessage } from "@/utils/error";
 import type {
   FileStatus,
   BranchInfo,
   CommitInfo,
   DiffOutput,
   RepoEntry,
+  OpenRepoInfo,
   ProviderConfig,
   AiConfig,
   StashEntry,
   RepoOperationState,
+  RebaseStatus,
   TagInfo,
   RemoteInfo,
   ConflictFile,
@@ -39,20 +42,20 @@ async function ipcInvoke<T>(command: string, args?: Record<string, unknown>): Pr
   try {
     return await invoke<T>(command, args);
   } catch (error) {
-    const message = error instanceof Error ? error.message : String(error);
-    logger.error("IPC", `调用 ${command} 失败:`, message);
-    throw new Error(`IPC 调用失败 (${command}): ${message}`);
+    const ipcError = new IpcError(command, error);
+    logger.error("IPC", `调用 ${command} 失败:`, ipcError.code ?? "", errorMessage(error));
+    throw ipcError;
   }
 }
 
 // ==================== 仓库操作 ====================
 
-export function openRepo(path: string): Promise<string> {
-  return ipcInvoke<string>("open_repo", { path });
+export function openRepo(path: string): Promise<OpenRepoInfo> {
+  return ipcInvoke<OpenRepoInfo>("open_repo", { path });
 }
 
-export function initRepo(path: string): Promise<string> {
-  return ipcInvoke<string>("init_repo", { path });
+export function initRepo(path: string): Promise<OpenRepoInfo> {
+  return ipcInvoke<OpenRepoInfo>("init_repo", { path });
 }
 
 export function closeRepo(): Promise<void> {
@@ -101,20 +104,43 @@ export function getStagedDiff(): Promise<DiffOutput> {
 
 // ==================== Hunk 操作 ====================
 
-export function stageHunk(path: string, hunkIndex: number): Promise<void> {
-  return ipcInvoke<void>("stage_hunk", { path, hunkIndex });
+export function stageHunk(path: string, hunkIndex: number, fingerprint: string): Promise<void> {
+  return ipcInvoke<void>("stage_hunk", { path, hunkIndex, fingerprint });
 }
 
-export function unstageHunk(path: string, hunkIndex: number): Promise<void> {
-  return ipcInvoke<void>("unstage_hunk", { path, hunkIndex });
+export function unstageHunk(path: string, hunkIndex: number, fingerprint: string): Promise<void> {
+  return ipcInvoke<void>("unstage_hunk", { path, hunkIndex, fingerprint });
 }
 
-export function stageLines(path: string, hunkIndex: number, lineIndices: number[]): Promise<void> {
-  return ipcInvoke<void>("stage_lines", { path, hunkIndex, lineIndices });
+export function stageLines(
+  path: string,
+  hunkIndex: number,
+  fingerprint: string,
+  lineIndices: number[],
+): Promise<void> {
+  return ipcInvoke<void>("stage_lines", { path, hunkIndex, fingerprint, lineIndices });
 }
 
-export function discardHunk(path: string, hunkIndex: number): Promise<void> {
-  return ipcInvoke<void>("discard_hunk", { path, hunkIndex });
+export function unstageLines(
+  path: string,
+  hunkIndex: number,
+  fingerprint: string,
+  lineIndices: number[],
+): Promise<void> {
+  return ipcInvoke<void>("unstage_lines", { path, hunkIndex, fingerprint, lineIndices });
+}
+
+export function discardHunk(path: string, hunkIndex: number, fingerprint: string): Promise<void> {
+  return ipcInvoke<void>("discard_hunk", { path, hunkIndex, fingerprint });
+}
+
+export function discardLines(
+  path: string,
+  hunkIndex: number,
+  fingerprint: string,
+  lineIndices: number[],
+): Promise<void> {
+  return ipcInvoke<void>("discard_lines", { path, hunkIndex, fingerprint, lineIndices });
 }
 
 // ==================== 提交 ====================
@@ -196,15 +222,15 @@ export function getBranchTips(): Promise<Record<string, string[]>> {
 // ==================== 远程操作 ====================
 
 export function fetchRemote(remote?: string): Promise<void> {
-  return ipcInvoke<void>("fetch_remote", { remote: remote ?? "origin" });
+  return ipcInvoke<void>("fetch_remote", { remote });
 }
 
 export function pullRemote(remote?: string, branch?: string): Promise<void> {
-  return ipcInvoke<void>("pull_remote", { remote: remote ?? "origin", branch: branch ?? "" });
+  return ipcInvoke<void>("pull_remote", { remote, branch: branch ?? "" });
 }
 
 export function pushRemote(remote?: string, branch?: string): Promise<void> {
-  return ipcInvoke<void>("push_remote", { remote: remote ?? "origin", branch: branch ?? "" });
+  return ipcInvoke<void>("push_remote", { remote, branch: branch ?? "" });
 }
 
 export function getRemotes(): Promise<RemoteInfo[]> {
@@ -232,11 +258,11 @@ export function pushSetUpstream(remote: string, branch: string): Promise<void> {
 }
 
 export function pullRebase(remote?: string, branch?: string): Promise<void> {
-  return ipcInvoke<void>("pull_rebase", { remote: remote ?? "origin", branch: branch ?? "" });
+  return ipcInvoke<void>("pull_rebase", { remote, branch: branch ?? "" });
 }
 
 export function syncRemote(remote?: string, branch?: string): Promise<void> {
-  return ipcInvoke<void>("sync_remote", { remote: remote ?? "origin", branch: branch ?? "" });
+  return ipcInvoke<void>("sync_remote", { remote, branch: branch ?? "" });
 }
 
 // ==================== Stash ====================
@@ -283,6 +309,10 @@ export function getRepoState(): Promise<RepoOperationState> {
   return ipcInvoke<RepoOperationState>("get_repo_state");
 }
 
+export function getRebaseStatus(): Promise<RebaseStatus | null> {
+  return ipcInvoke<RebaseStatus | null>("get_rebase_status");
+}
+
 export function cherryPick(commitId: string): Promise<void> {
   return ipcInvoke<void>("cherry_pick", { commitId });
 }
@@ -337,11 +367,11 @@ export function deleteTag(name: string): Promise<void> {
   return ipcInvoke<void>("delete_tag", { name });
 }
 
-export function pushTag(remote: string, tag: string): Promise<void> {
+export function pushTag(tag: string, remote?: string): Promise<void> {
   return ipcInvoke<void>("push_tag", { remote, tag });
 }
 
-export function pushAllTags(remote: string): Promise<void> {
+export function pushAllTags(remote?: string): Promise<void> {
   return ipcInvoke<void>("push_all_tags", { remote });
 }
 
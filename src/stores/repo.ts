@@ -5,15 +5,18 @@ import type {
   BranchInfo,
   StashEntry,
   RepoOperationState,
+  RebaseStatus,
   TagInfo,
   RemoteInfo,
 } from "../types";
 import * as gitService from "../services/git";
+import { errorMessage } from "../utils/error";
 
 // ==================== 类型定义 ====================
 
 export interface RepoState {
   currentRepo: {
+    id: string;
     name: string;
     path: string;
   } | null;
@@ -22,6 +25,8 @@ export interface RepoState {
   branches: BranchInfo[];
   stashes: StashEntry[];
   repoState: RepoOperationState;
+  /** 变基进行中时的步骤进度 */
+  rebaseStatus: RebaseStatus | null;
   tags: TagInfo[];
   remotes: RemoteInfo[];
   isLoading: boolean;
@@ -92,6 +97,7 @@ const initialState: RepoState = {
   branches: [],
   stashes: [],
   repoState: "Normal",
+  rebaseStatus: null,
   tags: [],
   remotes: [],
   isLoading: false,
@@ -125,7 +131,7 @@ export function createRepoStore(): RepoStore {
     try {
       return await fn();
     } catch (error) {
-      setState("error", error instanceof Error ? error.message : String(error));
+      setState("error", errorMessage(error));
       if (rethrow) throw error;
       return undefined as T;
     } finally {
@@ -151,8 +157,8 @@ export function createRepoStore(): RepoStore {
     async openRepo(path: string) {
       await wrapAction(
         async () => {
-          const repoName = await gitService.openRepo(path);
-          setState("currentRepo", { name: repoName, path });
+          const repo = await gitService.openRepo(path);
+          setState("currentRepo", { id: repo.id, name: repo.name, path });
           await actions.refreshAll();
         },
         { loading: true },
@@ -170,6 +176,7 @@ export function createRepoStore(): RepoStore {
             s.branches = [];
             s.stashes = [];
             s.repoState = "Normal";
+            s.rebaseStatus = null;
             s.tags = [];
             s.remotes = [];
             s.error = null;
@@ -216,7 +223,9 @@ export function createRepoStore(): RepoStore {
       await wrapAction(
         async () => {
           const repoState = await gitService.getRepoState();
-          setState("repoState", repoState);
+          const rebaseStatus =
+            repoState === "Rebasing" ? await gitService.getRebaseStatus() : null;
+          setState({ repoState, rebaseStatus });
         },
         { rethrow: false },
       );
@@ -0,0 +1,66 @@
+// ==================== 后端错误类型 ====================
+// 与 src-tauri/src/error.rs 中 AppError 的序列化格式保持一致
+
+export type ErrorCategory =
+  | 'git'
+  | 'network'
+  | 'auth'
+  | 'operation'
+  | 'io'
+  | 'storage'
+  | 'ai'
+  | 'config'
+  | 'input'
+  | 'internal';
+
+export type ErrorCode =
+  | 'merge_conflict'
+  | 'uncommitted_changes'
+  | 'nothing_to_commit'
+  | 'not_a_repository'
+  | 'not_found'
+  | 'already_exists'
+  | 'branch_not_fully_merged'
+  | 'locked'
+  | 'repo_not_found'
+  | 'repo_state'
+  | 'stale_diff'
+  | 'git_failed'
+  | 'non_fast_forward'
+  | 'push_rejected'
+  | 'remote_not_found'
+  | 'network_unreachable'
+  | 'http_failed'
+  | 'authentication_failed'
+  | 'unauthenticated'
+  | 'cancelled'
+  | 'timeout'
+  | 'io'
+  | 'database'
+  | 'ai_provider'
+  | 'config'
+  | 'invalid_input'
+  | 'path_validation'
+  | 'serialization'
+  | 'internal';
+
+export interface RejectedRef {
+  source: string;
+  destination: string;
+  reason: string | null;
+}
+
+export interface ErrorDetails {
+  conflicted_paths?: string[];
+  overwritten_paths?: string[];
+  rejected_refs?: RejectedRef[];
+  http_status?: number;
+}
+
+/** 后端命令失败时返回的错误对象 */
+export interface AppErrorPayload {
+  code: ErrorCode;
+  category: ErrorCategory;
+  message: string;
+  details?: ErrorDetails;
+}
@@ -39,6 +39,8 @@ export interface DiffHunk {
   new_lines: number;
   header: string;
   lines: DiffLine[];
+  /** Identifies the hunk to hunk and line commands, which reject it once the diff has changed */
+  fingerprint: string;
 }
 
 export interface DiffFile {
@@ -65,6 +67,14 @@ export interface RepoEntry {
   last_opened: string;
 }
 
+/** 应用中打开的仓库（每个标签页一个） */
+export interface OpenRepoInfo {
+  id: string;
+  name: string;
+  path: string;
+  active: boolean;
+}
+
 // ==================== Phase 1 类型 ====================
 
 export interface StashEntry {
@@ -75,6 +85,29 @@ export interface StashEntry {
 
 export type RepoOperationState = 'Normal' | 'Merging' | 'Rebasing' | 'CherryPicking' | 'Reverting';
 
+export type RebaseAction = 'pick' | 'reword' | 'edit' | 'squash' | 'fixup' | 'drop';
+
+export interface RebaseStep {
+  action: RebaseAction;
+  commit_id: string;
+  summary: string;
+  message: string | null;
+}
+
+/** 变基暂停的原因 */
+export type RebasePause = 'conflict' | 'edit' | 'other';
+
+export interface RebaseStatus {
+  branch: string | null;
+  onto: string;
+  /** 当前步骤，从 1 开始 */
+  current: number;
+  total: number;
+  stopped_at: string | null;
+  pause: RebasePause | null;
+  remaining: RebaseStep[];
+}
+
 // ==================== Phase 2 类型 ====================
 
 export interface TagInfo {
@@ -9,8 +9,13 @@ export type {
   DiffStats,
   DiffOutput,
   RepoEntry,
+  OpenRepoInfo,
   StashEntry,
   RepoOperationState,
+  RebaseAction,
+  RebaseStep,
+  RebasePause,
+  RebaseStatus,
   TagInfo,
   RemoteInfo,
   ConflictFile,
@@ -46,3 +51,11 @@ export type {
   CreateReviewRequest,
   ReviewCommentRequest,
 } from './github';
+
+export type {
+  ErrorCategory,
+  ErrorCode,
+  RejectedRef,
+  ErrorDetails,
+  AppErrorPayload,
+} from './error';
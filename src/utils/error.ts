@@ -0,0 +1,37 @@
+/**
+ * 错误信息提取
+ * 后端错误以 { code, category, message, details } 对象形式返回，而不是 Error 实例
+ */
+
+import type { AppErrorPayload, ErrorCode } from "@/types";
+
+/** 是否为后端返回的错误对象 */
+export function isAppErrorPayload(error: unknown): error is AppErrorPayload {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    typeof (error as AppErrorPayload).code === "string" &&
+    typeof (error as AppErrorPayload).message === "string"
+  );
+}
+
+/** 可展示的错误信息 */
+export function errorMessage(error: unknown): string {
+  if (error instanceof Error || isAppErrorPayload(error)) return error.message;
+  return String(error);
+}
+
+/** IPC 调用失败时抛出的错误，保留后端的错误码与详情 */
+export class IpcError extends Error {
+  readonly command: string;
+  readonly code: ErrorCode | undefined;
+  readonly payload: AppErrorPayload | undefined;
+
+  constructor(command: string, cause: unknown) {
+    super(`IPC 调用失败 (${command}): ${errorMessage(cause)}`);
+    this.name = "IpcError";
+    this.command = command;
+    this.payload = isAppErrorPayload(cause) ? cause : undefined;
+    this.code = this.payload?.code;
+  }
+}
@@ -19,6 +19,7 @@ import type {
   DiffOutput,
   StashEntry,
   RepoOperationState,
+  RebaseStatus,
 } from "@/types";
 import * as gitService from "@/services/git";
 import { logger } from "@/utils/logger";
@@ -115,6 +116,7 @@ const STATUS_LABEL_CLASS: Record<FileStatusKind, string> = {
 export interface WorkspaceViewProps {
   files?: FileStatus[];
   repoState?: RepoOperationState;
+  rebaseStatus?: RebaseStatus | null;
   stashes?: StashEntry[];
   onRefresh?: () => void;
   onStageFiles?: (paths: string[]) => Promise<void>;
@@ -200,6 +202,11 @@ const WorkspaceView: Component<WorkspaceViewProps> = (props) => {
     setViewMode((m) => (m === "list" ? "tree" : "list"));
   };
 
+  const operationMessage = (): string =>
+    props.rebaseStatus?.pause === "edit"
+      ? t("workspace.operation.amendThenContinue")
+      : t("workspace.operation.resolveConflicts");
+
   const operationLabel = (): string => {
     switch (repoState()) {
       case "Merging":
@@ -464,7 +471,16 @@ const WorkspaceView: Component<WorkspaceViewProps> = (props) => {
       <Show when={isInOperation()}>
         <div class={styles.operationBanner}>
           <span class={styles.operationLabel}>{operationLabel()}</span>
-          <span class={styles.operationMessage}>{t("workspace.operation.resolveConflicts")}</span>
+          <Show when={repoState() === "Rebasing" && props.rebaseStatus}>
+            {(status) => (
+              <span class={styles.operationMessage}>
+                {t("workspace.operation.rebaseStep")
+                  .replace("{current}", String(status().current))
+                  .replace("{total}", String(status().total))}
+              </span>
+            )}
+          </Show>
+          <span class={styles.operationMessage}>{operationMessage()}</span>
           <Show when={repoState() === "Merging"}>
             <Button variant="ghost" size="sm" onClick={() => props.onMergeAbort?.()}>
               {t("workspace.operation.abortMerge")}